@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use collections::HashMap;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The name of a debug adapter, e.g. `debugpy`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct DebugAdapterName(pub Arc<str>);
+
+impl fmt::Display for DebugAdapterName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// An installed debug adapter: the command that starts it and its arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DebugAdapterBinary {
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    /// Environment variables the adapter needs, set on top of the inherited environment.
+    pub env: Option<HashMap<String, String>>,
+}
+
+/// A debug adapter for a language, which can install itself when it isn't yet.
+pub trait DebugAdapter: Send + Sync + 'static {
+    fn name(&self) -> DebugAdapterName;
+
+    /// Returns the binary that was installed into `container_dir` before, if any.
+    fn cached_binary(&self, container_dir: &Path) -> Option<DebugAdapterBinary>;
+
+    /// Downloads and installs the adapter into `container_dir`.
+    fn install_binary(
+        &self,
+        container_dir: PathBuf,
+    ) -> BoxFuture<'static, Result<DebugAdapterBinary>>;
+}
+
+/// Returns the binary of the adapter, installing it into `container_dir` first when needed.
+pub async fn resolve_binary(
+    adapter: &dyn DebugAdapter,
+    container_dir: PathBuf,
+) -> Result<DebugAdapterBinary> {
+    if let Some(binary) = adapter.cached_binary(&container_dir) {
+        return Ok(binary);
+    }
+
+    adapter
+        .install_binary(container_dir)
+        .await
+        .with_context(|| format!("failed to install debug adapter {}", adapter.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use futures::FutureExt;
+
+    struct FakeAdapter;
+
+    impl DebugAdapter for FakeAdapter {
+        fn name(&self) -> DebugAdapterName {
+            DebugAdapterName("fake".into())
+        }
+
+        fn cached_binary(&self, container_dir: &Path) -> Option<DebugAdapterBinary> {
+            container_dir
+                .ends_with("installed")
+                .then(|| binary(container_dir))
+        }
+
+        fn install_binary(
+            &self,
+            container_dir: PathBuf,
+        ) -> BoxFuture<'static, Result<DebugAdapterBinary>> {
+            async move {
+                if container_dir.ends_with("offline") {
+                    Err(anyhow!("no network"))
+                } else {
+                    Ok(binary(&container_dir.join("installed")))
+                }
+            }
+            .boxed()
+        }
+    }
+
+    fn binary(container_dir: &Path) -> DebugAdapterBinary {
+        DebugAdapterBinary {
+            command: container_dir.join("adapter").to_string_lossy().into_owned(),
+            arguments: vec!["--stdio".into()],
+            env: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_binary() {
+        smol::block_on(async {
+            let binary = resolve_binary(&FakeAdapter, PathBuf::from("/adapters/installed"))
+                .await
+                .unwrap();
+            assert_eq!(binary.command, "/adapters/installed/adapter");
+
+            let binary = resolve_binary(&FakeAdapter, PathBuf::from("/adapters/new"))
+                .await
+                .unwrap();
+            assert_eq!(binary.command, "/adapters/new/installed/adapter");
+
+            let error = resolve_binary(&FakeAdapter, PathBuf::from("/adapters/offline"))
+                .await
+                .unwrap_err();
+            assert_eq!(
+                format!("{error:#}"),
+                "failed to install debug adapter fake: no network"
+            );
+        });
+    }
+}
@@ -0,0 +1,73 @@
+use serde_json::{json, Value};
+use sysinfo::{ProcessRefreshKind, RefreshKind, System};
+
+/// A process running on this machine, which a debugger can attach to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// The processes running on this machine, e.g. to pick the one to attach to, ordered
+/// by name.
+pub fn local_processes() -> Vec<LocalProcess> {
+    let system =
+        System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
+    let mut processes = system
+        .processes()
+        .iter()
+        .map(|(pid, process)| LocalProcess {
+            pid: pid.as_u32(),
+            name: process.name().to_string(),
+        })
+        .collect::<Vec<_>>();
+    processes.sort_by(|a, b| a.name.cmp(&b.name).then(a.pid.cmp(&b.pid)));
+    processes
+}
+
+/// Adds the picked process to the arguments of an `attach` request.
+///
+/// Adapters disagree on the key of the process id: it is set as `pid` when the
+/// arguments already have that key, e.g. as a placeholder, and as `processId` otherwise.
+pub fn attach_arguments(args: Option<Value>, pid: u32) -> Value {
+    let mut args = match args {
+        Some(Value::Object(args)) => args,
+        _ => Default::default(),
+    };
+    let key = if args.contains_key("pid") {
+        "pid"
+    } else {
+        "processId"
+    };
+    args.insert(key.to_string(), json!(pid));
+    Value::Object(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attach_arguments() {
+        assert_eq!(attach_arguments(None, 7), json!({ "processId": 7 }));
+        assert_eq!(
+            attach_arguments(
+                Some(json!({ "justMyCode": false, "processId": "${pickProcess}" })),
+                7
+            ),
+            json!({ "justMyCode": false, "processId": 7 })
+        );
+        assert_eq!(
+            attach_arguments(Some(json!({ "pid": null })), 7),
+            json!({ "pid": 7 })
+        );
+    }
+
+    #[test]
+    fn test_local_processes() {
+        let pid = std::process::id();
+        assert!(local_processes()
+            .iter()
+            .any(|process| process.pid == pid && !process.name.is_empty()));
+    }
+}
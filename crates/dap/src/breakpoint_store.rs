@@ -0,0 +1,105 @@
+use crate::client::row_to_dap_line;
+use dap_types::SourceBreakpoint;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// A breakpoint the user set in the gutter of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredBreakpoint {
+    /// The zero-based row of the editor.
+    pub row: u64,
+    pub condition: Option<String>,
+    /// Disabled breakpoints are kept, but not sent to the adapter.
+    pub enabled: bool,
+}
+
+impl StoredBreakpoint {
+    pub fn to_source_breakpoint(&self) -> SourceBreakpoint {
+        SourceBreakpoint {
+            line: row_to_dap_line(self.row),
+            column: None,
+            condition: self.condition.clone(),
+            hit_condition: None,
+            log_message: None,
+            mode: None,
+        }
+    }
+}
+
+/// The breakpoints of a project by absolute file path, which outlive debug sessions
+/// and are replayed to every new session, see
+/// [`crate::client::DebugAdapterClient::replay_breakpoints`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakpointStore {
+    breakpoints: BTreeMap<PathBuf, BTreeMap<u64, StoredBreakpoint>>, // path -> row -> breakpoint
+}
+
+impl BreakpointStore {
+    /// Adds an enabled breakpoint on the row, or removes the breakpoint that is on it,
+    /// returning whether the row has a breakpoint now.
+    pub fn toggle(&mut self, path: &Path, row: u64) -> bool {
+        let breakpoints = self.breakpoints.entry(path.to_path_buf()).or_default();
+        let added = breakpoints.remove(&row).is_none();
+        if added {
+            breakpoints.insert(
+                row,
+                StoredBreakpoint {
+                    row,
+                    condition: None,
+                    enabled: true,
+                },
+            );
+        } else if breakpoints.is_empty() {
+            self.breakpoints.remove(path);
+        }
+        added
+    }
+
+    pub fn set_condition(&mut self, path: &Path, row: u64, condition: Option<String>) {
+        if let Some(breakpoint) = self.breakpoint_mut(path, row) {
+            breakpoint.condition = condition;
+        }
+    }
+
+    pub fn set_enabled(&mut self, path: &Path, row: u64, enabled: bool) {
+        if let Some(breakpoint) = self.breakpoint_mut(path, row) {
+            breakpoint.enabled = enabled;
+        }
+    }
+
+    /// Disables an enabled breakpoint or enables a disabled one, keeping its condition,
+    /// and returns whether it's enabled now. Returns `None` when the row has no breakpoint.
+    pub fn toggle_enabled(&mut self, path: &Path, row: u64) -> Option<bool> {
+        let breakpoint = self.breakpoint_mut(path, row)?;
+        breakpoint.enabled = !breakpoint.enabled;
+        Some(breakpoint.enabled)
+    }
+
+    fn breakpoint_mut(&mut self, path: &Path, row: u64) -> Option<&mut StoredBreakpoint> {
+        self.breakpoints.get_mut(path)?.get_mut(&row)
+    }
+
+    /// The breakpoints of the file, ordered by row.
+    pub fn breakpoints(&self, path: &Path) -> impl Iterator<Item = &StoredBreakpoint> {
+        self.breakpoints
+            .get(path)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+    }
+
+    /// The files that have breakpoints, enabled or not.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.breakpoints.keys().map(PathBuf::as_path)
+    }
+
+    /// The enabled breakpoints of the file, as they are sent to the adapter.
+    pub fn source_breakpoints(&self, path: &Path) -> Vec<SourceBreakpoint> {
+        self.breakpoints(path)
+            .filter(|breakpoint| breakpoint.enabled)
+            .map(StoredBreakpoint::to_source_breakpoint)
+            .collect()
+    }
+}
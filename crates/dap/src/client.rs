@@ -0,0 +1,9072 @@
+use crate::{
+    adapters::DebugAdapterBinary,
+    breakpoint_store::BreakpointStore,
+    config::{DebugAdapterConfig, DebugAdapterKind, DebugRequestType, TCPHost},
+    transport::{self, Events, Payload, Request, Response, TransportParams},
+};
+use anyhow::{anyhow, Context, Result};
+use collections::{HashMap, HashSet};
+use dap_types::{
+    requests::{
+        Attach, BreakpointLocations, Cancel, Completions, ConfigurationDone, Continue,
+        DataBreakpointInfo, Disassemble, Disconnect, Evaluate, ExceptionInfo, Goto, GotoTargets,
+        Initialize, Launch, LoadedSources, Modules, Next, Pause, ReadMemory, Restart, RestartFrame,
+        RunInTerminal, Scopes, SetBreakpoints, SetDataBreakpoints, SetExceptionBreakpoints,
+        SetVariable, StackTrace, StepBack, StepIn, StepOut, Terminate, TerminateThreads, Threads,
+        Variables, WriteMemory,
+    },
+    AttachRequestArguments, Breakpoint, BreakpointEvent, BreakpointEventReason, BreakpointLocation,
+    BreakpointLocationsArguments, CancelArguments, Capabilities, CompletionItem,
+    CompletionsArguments, ConfigurationDoneArguments, ContinueArguments, DataBreakpoint,
+    DataBreakpointInfoArguments, DataBreakpointInfoResponse, DisassembleResponse,
+    DisassembledInstruction, DisconnectArguments, EvaluateArguments, EvaluateArgumentsContext,
+    EvaluateResponse, ExceptionFilterOptions, ExceptionInfoArguments, ExceptionInfoResponse,
+    ExceptionOptions, GotoArguments, GotoTarget, GotoTargetsArguments, InitializeRequestArguments,
+    InitializeRequestArgumentsPathFormat, InvalidatedAreas, InvalidatedEvent,
+    LaunchRequestArguments, LoadedSourceEvent, LoadedSourceEventReason, LoadedSourcesArguments,
+    MemoryEvent, Module, ModuleEvent, ModuleEventReason, ModuleId, ModulesArguments, NextArguments,
+    OutputEvent, OutputEventCategory, PauseArguments, ProcessEvent, ReadMemoryArguments,
+    RestartArguments, RestartFrameArguments, RunInTerminalRequestArguments,
+    RunInTerminalRequestArgumentsKind, RunInTerminalResponse, Scope, ScopesArguments,
+    SetBreakpointsArguments, SetBreakpointsResponse, SetDataBreakpointsArguments,
+    SetDataBreakpointsResponse, SetExceptionBreakpointsArguments, SetExceptionBreakpointsResponse,
+    SetVariableArguments, SetVariableResponse, Source, SourceArguments, SourceBreakpoint,
+    SourceResponse, StackFrame, StackTraceArguments, StepBackArguments, StepInArguments,
+    StepOutArguments, SteppingGranularity, StoppedEvent, StoppedEventReason, TerminateArguments,
+    TerminateThreadsArguments, Thread, ThreadEventReason, ValueFormat, Variable,
+    VariablePresentationHintAttributes, VariablesArguments, VariablesArgumentsFilter,
+    WriteMemoryArguments, WriteMemoryResponse,
+};
+use futures::{channel::oneshot, future::BoxFuture, Future, FutureExt};
+use gpui::{AppContext, AsyncAppContext, BackgroundExecutor, Task};
+use parking_lot::{Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use smol::{
+    channel::{unbounded, Receiver, Sender},
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    process::{self, Child},
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    net::{Ipv4Addr, SocketAddrV4},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use util::ResultExt;
+
+const THREAD_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_INITIALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_INITIALIZED_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+/// How many indexed children [`DebugAdapterClient::child_variables`] fetches at once.
+pub const VARIABLES_PAGE_SIZE: u64 = 100;
+/// How long [`DebugAdapterClient::update_breakpoints_debounced`] waits for further updates.
+pub const BREAKPOINT_UPDATE_DEBOUNCE: Duration = Duration::from_millis(50);
+/// How long [`DebugAdapterClient::evaluate_hover`] waits for the hover to settle.
+const HOVER_DEBOUNCE: Duration = Duration::from_millis(50);
+/// How deep [`DebugAdapterClient::export_variables_json`] expands the variables of a scope.
+const EXPORTED_VARIABLES_DEPTH: usize = 4;
+/// How the client identifies itself to adapters in the `initialize` request.
+pub const CLIENT_ID: &str = "zed";
+pub const CLIENT_NAME: &str = "Zed";
+const DEFAULT_LOCALE: &str = "en-US";
+
+/// The startup phase of a debug adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupPhase {
+    /// Waiting for a spawned TCP adapter to accept our connection.
+    Connect,
+    /// Waiting for the adapter to respond to the `initialize` request.
+    Initialize,
+    /// Waiting for the adapter to send the `initialized` event.
+    Initialized,
+}
+
+/// The step of the DAP handshake a client has reached, see
+/// [`DebugAdapterClient::configuration_phase`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigurationPhase {
+    #[default]
+    NotStarted,
+    InitializeSent,
+    /// The adapter sent the `initialized` event, so it accepts configuration requests.
+    InitializedReceived,
+    BreakpointsSent,
+    ConfigurationDone,
+}
+
+impl std::fmt::Display for ConfigurationPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConfigurationPhase::NotStarted => "starting the debug adapter",
+            ConfigurationPhase::InitializeSent => "initializing",
+            ConfigurationPhase::InitializedReceived => "waiting for the configuration",
+            ConfigurationPhase::BreakpointsSent => "sending breakpoints",
+            ConfigurationPhase::ConfigurationDone => "finishing the configuration",
+        })
+    }
+}
+
+/// Returned when a debug adapter didn't finish a startup phase in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupTimeout {
+    pub phase: StartupPhase,
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for StartupTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let phase = match self.phase {
+            StartupPhase::Connect => "accept a connection",
+            StartupPhase::Initialize => "respond to the initialize request",
+            StartupPhase::Initialized => "send the initialized event",
+        };
+        write!(
+            f,
+            "debug adapter did not {phase} within {}ms",
+            self.timeout.as_millis()
+        )
+    }
+}
+
+impl std::error::Error for StartupTimeout {}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadStatus {
+    #[default]
+    Running,
+    Stopped,
+    Exited,
+    Ended,
+}
+
+impl ThreadStatus {
+    /// Whether the thread won't run again, so it can e.g. be dimmed in the thread list.
+    pub fn is_finished(self) -> bool {
+        matches!(self, ThreadStatus::Exited | ThreadStatus::Ended)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct DebugAdapterClientId(pub usize);
+
+#[derive(Debug, Default, Clone)]
+pub struct ThreadState {
+    pub status: ThreadStatus,
+    /// The name of the thread, once it was fetched with the `threads` request.
+    pub name: Option<String>,
+    pub stack_frames: Vec<StackFrame>,
+    /// Scopes keyed by the id of the stack frame they belong to.
+    pub scopes: HashMap<u64, Vec<Scope>>,
+    /// Variables keyed by the `variables_reference` they were fetched for.
+    pub variables: HashMap<u64, Vec<Variable>>,
+    pub current_stack_frame_id: Option<u64>,
+    /// What the adapter said about the exception the thread stopped on, if it did.
+    pub exception_description: Option<String>,
+    /// How many times the thread stopped during the session.
+    pub stop_count: usize,
+    /// Why the thread stopped the last time, e.g. on a breakpoint or after a step.
+    pub stop_reason: Option<StoppedEventReason>,
+    /// The breakpoints that made the thread stop, when it stopped on breakpoints.
+    pub hit_breakpoint_ids: Vec<u64>,
+}
+
+impl ThreadState {
+    fn continued(&mut self) {
+        self.status = ThreadStatus::Running;
+        self.stop_reason = None;
+        self.hit_breakpoint_ids.clear();
+    }
+}
+
+/// The settings a debug adapter was spawned with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpawnSummary {
+    pub command: String,
+    pub args: Vec<String>,
+    /// The environment variables set on top of the inherited environment.
+    pub env: BTreeMap<String, String>,
+    pub cwd: PathBuf,
+}
+
+impl SpawnSummary {
+    /// A hash that only changes when one of the settings changes, to detect whether
+    /// restarting an adapter would apply different settings.
+    pub fn hash_value(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// What an entry of the debug console is, e.g. to pick its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleOutputKind {
+    /// Output of the debuggee, or of an adapter that didn't categorize it.
+    Stdout,
+    /// Error output of the debuggee, or the error a console command failed with.
+    Stderr,
+    /// A message of the adapter itself.
+    Console,
+    /// A message of the adapter the user should notice.
+    Important,
+    /// A command that was typed into the console.
+    Command,
+    /// The result of a command that was typed into the console.
+    Result,
+}
+
+/// An output event shown in the debug console.
+#[derive(Debug, Clone)]
+pub struct ConsoleOutput {
+    pub event: OutputEvent,
+    pub kind: ConsoleOutputKind,
+    /// Whether the output contains ANSI escape sequences, e.g. colors, to be rendered.
+    pub has_ansi: bool,
+}
+
+impl ConsoleOutput {
+    fn new(event: OutputEvent) -> Self {
+        let kind = match event.category {
+            Some(OutputEventCategory::Stderr) => ConsoleOutputKind::Stderr,
+            Some(OutputEventCategory::Console) => ConsoleOutputKind::Console,
+            Some(OutputEventCategory::Important) => ConsoleOutputKind::Important,
+            _ => ConsoleOutputKind::Stdout,
+        };
+        let has_ansi = event.output.contains('\x1b');
+        Self {
+            event,
+            kind,
+            has_ansi,
+        }
+    }
+
+    /// The output without ANSI escape sequences, e.g. to copy it to the clipboard.
+    pub fn plain_text(&self) -> String {
+        if self.has_ansi {
+            strip_ansi(&self.event.output)
+        } else {
+            self.event.output.clone()
+        }
+    }
+}
+
+/// A summary of a thread for an overview, without its stack, scopes and variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadSummary {
+    pub thread_id: u64,
+    pub name: Option<String>,
+    pub status: ThreadStatus,
+    /// Why the thread is stopped, `None` unless it is.
+    pub stop_reason: Option<StoppedEventReason>,
+    pub top_frame: Option<StackFrameSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrameSummary {
+    pub id: u64,
+    pub name: String,
+    pub path: Option<String>,
+    pub line: u64,
+}
+
+/// Where the adapter said something, e.g. a variable, is declared, see
+/// [`DebugAdapterClient::variable_declaration_location`].
+#[derive(Debug, Clone)]
+pub struct DeclarationLocation {
+    pub source: Source,
+    pub line: u64,
+    pub column: Option<u64>,
+    /// The content of a source that has no local file, fetched by its `sourceReference`.
+    pub content: Option<String>,
+}
+
+/// The `variables` request, also reading the location references newer adapters send
+/// with variables, which dap-types doesn't know yet.
+enum VariablesWithLocations {}
+
+impl dap_types::requests::Request for VariablesWithLocations {
+    const COMMAND: &'static str = Variables::COMMAND;
+    type Arguments = VariablesArguments;
+    type Response = VariablesWithLocationsResponse;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VariablesWithLocationsResponse {
+    variables: Vec<VariableWithLocations>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariableWithLocations {
+    #[serde(flatten)]
+    variable: Variable,
+    #[serde(default)]
+    declaration_location_reference: Option<u64>,
+}
+
+/// The `locations` request of newer DAP versions, which resolves a location reference.
+enum Locations {}
+
+impl dap_types::requests::Request for Locations {
+    const COMMAND: &'static str = "locations";
+    type Arguments = LocationsArguments;
+    type Response = LocationsResponse;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LocationsArguments {
+    location_reference: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LocationsResponse {
+    source: Source,
+    line: u64,
+    #[serde(default)]
+    column: Option<u64>,
+}
+
+/// The `disassemble` request with signed offsets, as the protocol allows disassembling
+/// before a memory reference, which dap-types can't represent.
+enum DisassembleWithSignedOffsets {}
+
+impl dap_types::requests::Request for DisassembleWithSignedOffsets {
+    const COMMAND: &'static str = Disassemble::COMMAND;
+    type Arguments = SignedDisassembleArguments;
+    type Response = DisassembleResponse;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedDisassembleArguments {
+    memory_reference: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instruction_offset: Option<i64>,
+    instruction_count: u64,
+    resolve_symbols: Option<bool>,
+}
+
+/// Fired whenever the client's cached state changes, so views can re-render.
+///
+/// Views don't listen to clients directly, they subscribe to the
+/// [`DapStore`](crate::dap_store::DapStore), which emits these as
+/// [`DapStoreEvent::Client`](crate::dap_store::DapStoreEvent::Client).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientNotification {
+    /// A thread, or all threads when `all_threads_continued` is set, continued.
+    ThreadContinued {
+        thread_id: u64,
+        all_threads_continued: bool,
+    },
+    /// A thread, or all threads when `all_threads_stopped` is set, stopped.
+    ThreadStopped {
+        thread_id: Option<u64>,
+        all_threads_stopped: bool,
+        /// The adapter asked not to take focus, e.g. because a background thread hit
+        /// a breakpoint, so views should update without revealing the stop.
+        preserve_focus: bool,
+    },
+    /// A thread started or exited, or the names of the threads were fetched.
+    ThreadsUpdated,
+    /// Output was added to the debug console, see [`DebugAdapterClient::console_output`].
+    OutputReceived,
+    /// The stack frames (and the selected frame) of a thread were re-fetched.
+    StackFramesUpdated { thread_id: u64 },
+    /// A stack frame was selected, e.g. in the call stack, so the line that is shown as
+    /// paused moved, see [`DebugAdapterClient::execution_marker`].
+    StackFrameSelected { thread_id: u64, stack_frame_id: u64 },
+    /// The scopes of a thread's selected stack frame were fetched.
+    ScopesUpdated { thread_id: u64, stack_frame_id: u64 },
+    /// The adapter verified, moved or removed a breakpoint.
+    BreakpointUpdated { breakpoint_id: u64 },
+    /// The debug session was terminated, all threads are ended.
+    SessionEnded,
+    /// The adapter process exited without being disconnected, all threads are ended.
+    SessionFailed { error: String },
+    /// The sources the adapter loaded changed.
+    LoadedSourcesUpdated,
+    /// A module was loaded, changed or unloaded.
+    ModulesUpdated,
+    /// The watch expressions were re-evaluated, see [`DebugAdapterClient::watch_results`].
+    WatchesUpdated,
+    /// The adapter changed its capabilities after initialization.
+    CapabilitiesUpdated,
+    /// The adapter said which process is debugged, see [`DebugAdapterClient::debuggee_process`].
+    ProcessStarted,
+    /// A long running operation of the adapter started, made progress or ended.
+    ///
+    /// Ended operations are no longer returned by [`DebugAdapterClient::progress_by_id`].
+    ProgressUpdated { progress_id: String },
+    /// The DAP handshake reached the next step, see [`DebugAdapterClient::configuration_phase`].
+    ConfigurationPhaseChanged(ConfigurationPhase),
+}
+
+/// An action of a debug session that only works when the adapter supports it, so it
+/// should only be enabled, e.g. in the command palette, for sessions that do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionAction {
+    StepBack,
+    RestartFrame,
+    Restart,
+    Terminate,
+    GotoTargets,
+    SetVariable,
+    ReadMemory,
+    WriteMemory,
+    Disassemble,
+    DataBreakpoints,
+    LoadedSources,
+    Completions,
+    ExceptionInfo,
+    TerminateThreads,
+}
+
+impl SessionAction {
+    pub const ALL: [Self; 14] = [
+        Self::StepBack,
+        Self::RestartFrame,
+        Self::Restart,
+        Self::Terminate,
+        Self::GotoTargets,
+        Self::SetVariable,
+        Self::ReadMemory,
+        Self::WriteMemory,
+        Self::Disassemble,
+        Self::DataBreakpoints,
+        Self::LoadedSources,
+        Self::Completions,
+        Self::ExceptionInfo,
+        Self::TerminateThreads,
+    ];
+
+    pub fn is_supported(self, capabilities: &Capabilities) -> bool {
+        let capability = match self {
+            Self::StepBack => capabilities.supports_step_back,
+            Self::RestartFrame => capabilities.supports_restart_frame,
+            Self::Restart => capabilities.supports_restart_request,
+            Self::Terminate => capabilities.supports_terminate_request,
+            Self::GotoTargets => capabilities.supports_goto_targets_request,
+            Self::SetVariable => capabilities.supports_set_variable,
+            Self::ReadMemory => capabilities.supports_read_memory_request,
+            Self::WriteMemory => capabilities.supports_write_memory_request,
+            Self::Disassemble => capabilities.supports_disassemble_request,
+            Self::DataBreakpoints => capabilities.supports_data_breakpoints,
+            Self::LoadedSources => capabilities.supports_loaded_sources_request,
+            Self::Completions => capabilities.supports_completions_request,
+            Self::ExceptionInfo => capabilities.supports_exception_info_request,
+            Self::TerminateThreads => capabilities.supports_terminate_threads_request,
+        };
+        capability == Some(true)
+    }
+}
+
+/// The optional features of a debug adapter the debugger toolbar and gutter decide
+/// their controls on, so they don't offer controls that always fail.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DebugFeatures {
+    pub step_back: bool,
+    pub restart: bool,
+    pub restart_frame: bool,
+    pub terminate: bool,
+    pub set_variable: bool,
+    pub conditional_breakpoints: bool,
+    pub hit_conditional_breakpoints: bool,
+    pub log_points: bool,
+    pub function_breakpoints: bool,
+}
+
+impl DebugFeatures {
+    pub fn new(capabilities: &Capabilities) -> Self {
+        Self {
+            step_back: SessionAction::StepBack.is_supported(capabilities),
+            restart: SessionAction::Restart.is_supported(capabilities),
+            restart_frame: SessionAction::RestartFrame.is_supported(capabilities),
+            terminate: SessionAction::Terminate.is_supported(capabilities),
+            set_variable: SessionAction::SetVariable.is_supported(capabilities),
+            conditional_breakpoints: capabilities.supports_conditional_breakpoints == Some(true),
+            hit_conditional_breakpoints: capabilities.supports_hit_conditional_breakpoints
+                == Some(true),
+            log_points: capabilities.supports_log_points == Some(true),
+            function_breakpoints: capabilities.supports_function_breakpoints == Some(true),
+        }
+    }
+}
+
+/// A long running operation the adapter reports progress for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub title: String,
+    pub message: Option<String>,
+    /// The progress in percent, from 0 to 100.
+    pub percentage: Option<u64>,
+    pub cancellable: bool,
+}
+
+/// The result of an evaluated expression, telling whether it can be expanded
+/// into child variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandableValue {
+    pub value: String,
+    pub type_: Option<String>,
+    /// The reference to pass to the `variables` request to fetch the children, `0` if there are none.
+    pub variables_reference: u64,
+    pub has_children: bool,
+}
+
+impl From<EvaluateResponse> for ExpandableValue {
+    fn from(response: EvaluateResponse) -> Self {
+        Self {
+            value: response.result,
+            type_: response.type_,
+            variables_reference: response.variables_reference,
+            has_children: response.variables_reference > 0,
+        }
+    }
+}
+
+impl ExpandableValue {
+    /// The tree of child variables to show below the value, e.g. an object printed in
+    /// the debug console, whose children are fetched once it's expanded.
+    pub fn variable_tree(&self) -> VariableTree {
+        VariableTree::new(self.variables_reference)
+    }
+}
+
+/// The children of an evaluated value or a variable, which are only fetched when the
+/// tree is expanded, see [`DebugAdapterClient::expand_variable_tree`].
+#[derive(Debug, Clone)]
+pub struct VariableTree {
+    pub variables_reference: u64,
+    /// `None` until the tree was expanded.
+    pub children: Option<Vec<(Variable, VariableTree)>>,
+}
+
+impl VariableTree {
+    pub fn new(variables_reference: u64) -> Self {
+        Self {
+            variables_reference,
+            children: None,
+        }
+    }
+
+    pub fn is_expandable(&self) -> bool {
+        self.variables_reference > 0
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.children.is_some()
+    }
+
+    /// Collapses the tree, the children are fetched again the next time it's expanded.
+    pub fn collapse(&mut self) {
+        self.children = None;
+    }
+}
+
+/// An expression that is evaluated whenever the debuggee stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Watch {
+    expression: String,
+    /// How many evaluations in a row failed.
+    failures: usize,
+    result: Option<Result<ExpandableValue, String>>,
+}
+
+impl Watch {
+    fn new(expression: String) -> Self {
+        Self {
+            expression,
+            failures: 0,
+            result: None,
+        }
+    }
+}
+
+/// A watch expression with the result of its last evaluation, see
+/// [`DebugAdapterClient::watch_results`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchResult {
+    pub expression: String,
+    /// `None` until the watch was evaluated, the error message when it failed to
+    /// evaluate, e.g. because it isn't in scope.
+    pub result: Option<Result<ExpandableValue, String>>,
+}
+
+/// The thread and frame that were selected before a restart, to be selected again
+/// once a thread that looks the same stops.
+#[derive(Debug, Clone)]
+struct PendingSelection {
+    thread_name: String,
+    top_frame_name: String,
+    stack_frame_name: Option<String>,
+}
+
+/// Memory read from the debuggee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryContents {
+    /// The address of the first byte of `data`.
+    pub address: String,
+    pub data: Vec<u8>,
+    /// The number of bytes following `data` that could not be read, e.g. because
+    /// the region isn't mapped.
+    pub unreadable_bytes: u64,
+}
+
+/// A range of memory the adapter said changed, relative to the memory reference it
+/// was subscribed to with [`DebugAdapterClient::memory_updates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUpdate {
+    pub offset: i64,
+    pub count: u64,
+}
+
+impl MemoryUpdate {
+    /// Whether the update touches the `count` bytes at `offset`, e.g. the ones a
+    /// memory view shows.
+    pub fn overlaps(&self, offset: i64, count: u64) -> bool {
+        self.offset < offset.saturating_add_unsigned(count)
+            && offset < self.offset.saturating_add_unsigned(self.count)
+    }
+}
+
+/// Consecutive disassembled instructions that belong to the same source lines.
+#[derive(Debug, Clone)]
+pub struct DisassemblyGroup {
+    pub source: Option<Source>,
+    pub line: Option<u64>,
+    pub end_line: Option<u64>,
+    pub instructions: Vec<DisassembledInstruction>,
+}
+
+/// Where the editor should show a selected stack frame, see
+/// [`DebugAdapterClient::select_stack_frame`].
+#[derive(Debug, Clone)]
+pub struct StackFrameLocation {
+    pub source: Source,
+    /// The zero-based line, as used by the editor.
+    pub row: u64,
+    /// The zero-based column, as used by the editor.
+    pub column: u64,
+    /// The content of a source that has no local file, fetched by its `sourceReference`.
+    pub content: Option<String>,
+}
+
+/// The line the current thread is paused at, shown with an arrow in the editor's gutter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionMarker {
+    pub path: Arc<Path>,
+    /// The zero-based line, as used by the editor.
+    pub row: u64,
+    /// Whether the selected frame is the top one, where execution continues, rather
+    /// than one of its callers.
+    pub is_top_frame: bool,
+}
+
+/// The breakpoint as it was requested in the editor: its file and the line it was set at.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BreakpointMarker {
+    pub path: Arc<Path>,
+    pub line: u64,
+}
+
+/// A breakpoint as it was sent with the `setBreakpoints` request, and the adapter's
+/// response to it.
+#[derive(Debug, Clone)]
+struct RequestedBreakpoint {
+    source_breakpoint: SourceBreakpoint,
+    breakpoint: Option<Breakpoint>,
+}
+
+/// Passed to the [`DebugAdapterClient::on_breakpoint_changed`] callbacks.
+#[derive(Debug, Clone)]
+pub struct BreakpointChange {
+    /// The marker the breakpoint was requested for, unless the adapter added the breakpoint itself.
+    pub marker: Option<BreakpointMarker>,
+    pub breakpoint: Breakpoint,
+}
+
+/// Launches the debuggee for a `runInTerminal` reverse request, e.g. in a workspace terminal.
+pub type RunInTerminalHandler = Arc<
+    dyn Fn(RunInTerminalRequestArguments) -> BoxFuture<'static, Result<RunInTerminalResponse>>
+        + Send
+        + Sync,
+>;
+
+/// The requests that wait for their response, keyed by their sequence id.
+/// `None` once the connection to the adapter was closed.
+type PendingRequests = Arc<Mutex<Option<HashMap<u64, oneshot::Sender<Response>>>>>;
+
+pub struct DebugAdapterClient {
+    id: DebugAdapterClientId,
+    spawn_summary: Option<SpawnSummary>,
+    server_tx: Sender<Payload>,
+    pending_requests: PendingRequests,
+    event_rx: Mutex<Option<Receiver<Events>>>,
+    request_count: Arc<AtomicU64>,
+    capabilities: Arc<Mutex<Option<Capabilities>>>,
+    config: DebugAdapterConfig,
+    thread_states: Arc<Mutex<HashMap<u64, ThreadState>>>, // thread_id -> thread_state
+    current_thread_id: Mutex<Option<u64>>,
+    pending_selection: Mutex<Option<PendingSelection>>,
+    notification_subscribers: Arc<Mutex<Vec<Sender<ClientNotification>>>>,
+    disconnecting: Arc<AtomicBool>,
+    session_failed: Arc<AtomicBool>,
+    console_output: Mutex<Vec<ConsoleOutput>>,
+    repl_history: Mutex<Vec<String>>,
+    telemetry_subscribers: Mutex<Vec<Sender<OutputEvent>>>,
+    memory_subscribers: Mutex<HashMap<String, Vec<Sender<MemoryUpdate>>>>, // memory_reference -> subscribers
+    stop_waiters: Mutex<HashMap<u64, Vec<oneshot::Sender<StoppedEvent>>>>, // thread_id -> waiters
+    sources: Mutex<HashMap<u64, Source>>, // source_reference -> source
+    source_contents: Mutex<HashMap<u64, SourceResponse>>, // source_reference -> content
+    declaration_locations: Mutex<HashMap<(u64, String), u64>>, // (variables_reference, name) -> location_reference
+    loaded_sources: Mutex<Vec<Source>>,
+    modules: Mutex<HashMap<ModuleId, Module>>,
+    total_modules: Mutex<Option<u64>>,
+    exit_code: Mutex<Option<i32>>,
+    debuggee_process: Mutex<Option<ProcessEvent>>,
+    configuration_phases: Mutex<Vec<ConfigurationPhase>>,
+    initialized_received: AtomicBool,
+    initialized_waiters: Mutex<Vec<oneshot::Sender<()>>>,
+    last_error: Arc<Mutex<Option<String>>>,
+    run_in_terminal_handler: Arc<Mutex<Option<RunInTerminalHandler>>>,
+    progress: Mutex<HashMap<String, Progress>>,
+    variable_formats: Mutex<HashMap<u64, ValueFormat>>,
+    watches: Mutex<Vec<Watch>>,
+    hover_generation: AtomicU64,
+    hover_request: Mutex<Option<u64>>, // seq of the in-flight hover evaluation
+    thread_locks: Mutex<HashMap<u64, Arc<smol::lock::Mutex<()>>>>,
+    breakpoints: Mutex<HashMap<u64, Breakpoint>>, // breakpoint_id -> breakpoint
+    breakpoint_hit_counts: Mutex<HashMap<u64, u64>>, // breakpoint_id -> hit count
+    data_breakpoints: Mutex<Vec<DataBreakpoint>>,
+    exception_breakpoints: Mutex<Option<SetExceptionBreakpointsArguments>>,
+    persistent_data_ids: Mutex<HashSet<String>>,
+    breakpoint_change_callbacks: Mutex<Vec<Box<dyn Fn(&BreakpointChange) + Send + Sync>>>,
+    requested_breakpoints: Mutex<HashMap<Arc<Path>, Vec<RequestedBreakpoint>>>,
+    pending_breakpoint_updates: Mutex<HashMap<Arc<Path>, Vec<SourceBreakpoint>>>,
+    modified_sources: Mutex<HashSet<Arc<Path>>>,
+    executor: BackgroundExecutor,
+    _tasks: Vec<Task<()>>,
+}
+
+impl DebugAdapterClient {
+    /// Creates & returns a new debug adapter client
+    ///
+    /// # Parameters
+    /// - `id`: The id that the project uses to keep track of specific clients
+    /// - `config`: The adapter specific configurations from debugger task that is starting
+    /// - `binary`: The installed debugger, see [`crate::adapters::resolve_binary`]
+    /// - `project_path`: The absolute path of the project that is being debugged,
+    ///   the working directory of the adapter unless the config sets one
+    /// - `cx`: The IO loops and event handling of the client run on its background executor
+    pub async fn new(
+        id: DebugAdapterClientId,
+        config: DebugAdapterConfig,
+        binary: &DebugAdapterBinary,
+        project_path: PathBuf,
+        cx: &AsyncAppContext,
+    ) -> Result<Self> {
+        let executor = cx.background_executor().clone();
+        let cwd = config.cwd.clone().unwrap_or(project_path);
+        // the environment of the launch config wins over the one the adapter needs
+        let mut env = binary.env.clone().unwrap_or_default();
+        env.extend(config.env.clone());
+
+        let spawn_summary = SpawnSummary {
+            command: binary.command.clone(),
+            args: binary.arguments.clone(),
+            env: env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            cwd: cwd.clone(),
+        };
+
+        let command = &binary.command;
+        let args = binary.arguments.clone();
+        let transport_params = match config.transport.clone() {
+            DebugAdapterKind::TCP(host) => {
+                Self::create_tcp_client(host, command, args, cwd, &env, &executor).await?
+            }
+            DebugAdapterKind::STDIO => Self::create_stdio_client(command, args, cwd, &env).await?,
+        };
+
+        let TransportParams {
+            rx,
+            tx,
+            err,
+            process,
+            address,
+        } = transport_params;
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::new()));
+        let mut client = if let Some(address) = address {
+            let (server_tx, client_rx) = unbounded::<Payload>();
+            let (client_tx, server_rx) = unbounded::<Payload>();
+            let mut tasks = Vec::new();
+            if let Some(err) = err {
+                let stderr_tail = stderr_tail.clone();
+                tasks.push(executor.spawn(async move {
+                    transport::handle_error(err, stderr_tail).await.log_err();
+                }));
+            }
+            let mut client = Self::new_internal(id, config, server_tx, server_rx, tasks, executor);
+            client.handle_tcp_connection(rx, tx, address, client_tx, client_rx);
+            client
+        } else {
+            let (server_tx, server_rx, tasks) = Self::handle_transport(
+                rx,
+                tx,
+                err,
+                stderr_tail.clone(),
+                config.log_messages,
+                &executor,
+            );
+            Self::new_internal(id, config, server_tx, server_rx, tasks, executor)
+        };
+        client.spawn_summary = Some(spawn_summary);
+        if let Some(process) = process {
+            client.watch_process(process, stderr_tail);
+        }
+        Ok(client)
+    }
+
+    fn new_internal(
+        id: DebugAdapterClientId,
+        config: DebugAdapterConfig,
+        server_tx: Sender<Payload>,
+        server_rx: Receiver<Payload>,
+        mut tasks: Vec<Task<()>>,
+        executor: BackgroundExecutor,
+    ) -> Self {
+        let pending_requests = PendingRequests::new(Mutex::new(Some(HashMap::default())));
+        let (event_tx, event_rx) = unbounded::<Events>();
+        let request_count = Arc::new(AtomicU64::new(1));
+        let last_error = Arc::new(Mutex::new(None));
+        let run_in_terminal_handler = Arc::new(Mutex::new(
+            config
+                .run_in_terminal_in_background
+                .then(Self::background_run_in_terminal_handler),
+        ));
+
+        tasks.push(executor.spawn({
+            let executor = executor.clone();
+            let server_tx = server_tx.clone();
+            let request_count = request_count.clone();
+            let last_error = last_error.clone();
+            let pending_requests = pending_requests.clone();
+            let run_in_terminal_handler = run_in_terminal_handler.clone();
+            async move {
+                let result = Self::handle_recv(
+                    server_rx,
+                    server_tx,
+                    pending_requests.clone(),
+                    event_tx,
+                    request_count,
+                    run_in_terminal_handler,
+                    executor,
+                )
+                .await;
+                // fails the pending requests and the ones that are still to be sent
+                pending_requests.lock().take();
+                if let Err(error) = &result {
+                    *last_error.lock() = Some(format!("{error:#}"));
+                }
+                result.log_err();
+            }
+        }));
+
+        Self {
+            id,
+            spawn_summary: None,
+            server_tx,
+            pending_requests,
+            event_rx: Mutex::new(Some(event_rx)),
+            request_count,
+            capabilities: Default::default(),
+            config,
+            thread_states: Arc::new(Mutex::new(HashMap::default())),
+            current_thread_id: Default::default(),
+            pending_selection: Default::default(),
+            notification_subscribers: Default::default(),
+            disconnecting: Default::default(),
+            session_failed: Default::default(),
+            console_output: Default::default(),
+            repl_history: Default::default(),
+            telemetry_subscribers: Default::default(),
+            memory_subscribers: Default::default(),
+            stop_waiters: Default::default(),
+            sources: Default::default(),
+            source_contents: Default::default(),
+            declaration_locations: Default::default(),
+            loaded_sources: Default::default(),
+            modules: Default::default(),
+            total_modules: Default::default(),
+            exit_code: Default::default(),
+            debuggee_process: Default::default(),
+            configuration_phases: Default::default(),
+            initialized_received: Default::default(),
+            initialized_waiters: Default::default(),
+            last_error,
+            run_in_terminal_handler,
+            progress: Default::default(),
+            variable_formats: Default::default(),
+            watches: Default::default(),
+            hover_generation: Default::default(),
+            hover_request: Default::default(),
+            thread_locks: Default::default(),
+            breakpoints: Default::default(),
+            breakpoint_hit_counts: Default::default(),
+            data_breakpoints: Default::default(),
+            exception_breakpoints: Default::default(),
+            persistent_data_ids: Default::default(),
+            breakpoint_change_callbacks: Default::default(),
+            requested_breakpoints: Default::default(),
+            modified_sources: Default::default(),
+            pending_breakpoint_updates: Default::default(),
+            executor,
+            _tasks: tasks,
+        }
+    }
+
+    /// Creates a debug client that connects to an adapter through tcp
+    ///
+    /// The messages are exchanged over the socket, while the stderr of the adapter is kept
+    /// as its error communication stream, e.g. to explain why it exited
+    ///
+    /// # Parameters
+    /// - `command`: The command that starts the debugger
+    /// - `args`: Arguments of the command that starts the debugger
+    /// - `cwd`: The working directory of the debugger
+    /// - `env`: Environment variables set for the debugger
+    async fn create_tcp_client(
+        host: TCPHost,
+        command: &str,
+        args: Vec<String>,
+        cwd: PathBuf,
+        env: &HashMap<String, String>,
+        executor: &BackgroundExecutor,
+    ) -> Result<TransportParams> {
+        let host_address = host.host.map_or_else(
+            || Ipv4Addr::new(127, 0, 0, 1),
+            |host| Ipv4Addr::new(host[0], host[1], host[2], host[3]),
+        );
+
+        let mut port = host.port;
+        if port.is_none() {
+            port = Self::get_port(host_address).await;
+        }
+        let port = port.context("failed to find an open port for the debug adapter")?;
+
+        let mut command = process::Command::new(command);
+        command
+            .current_dir(cwd)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut process = command
+            .spawn()
+            .with_context(|| "failed to start debug adapter.")?;
+
+        let stderr = process
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stderr"))?;
+
+        if let Some(delay) = host.delay {
+            // some debug adapters need some time to start the TCP server
+            // so we have to wait few milliseconds before we can connect to it
+            executor.timer(Duration::from_millis(delay)).await;
+        }
+
+        let address = SocketAddrV4::new(host_address, port);
+        let connect_timeout = host
+            .connect_timeout
+            .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_millis);
+        let stream = Self::connect_to_adapter(address, connect_timeout, executor).await?;
+
+        let mut params = TransportParams::new(
+            Box::new(BufReader::new(stream.clone())),
+            Box::new(stream),
+            Some(Box::new(BufReader::new(stderr))),
+            Some(process),
+        );
+        params.address = Some(address);
+        Ok(params)
+    }
+
+    /// Connects to a spawned adapter, retrying until it accepts the connection or `timeout` elapses.
+    async fn connect_to_adapter(
+        address: SocketAddrV4,
+        timeout: Duration,
+        executor: &BackgroundExecutor,
+    ) -> Result<TcpStream> {
+        let connect = async {
+            loop {
+                match TcpStream::connect(address).await {
+                    Ok(stream) => return Ok(stream),
+                    Err(error) => {
+                        log::debug!("failed to connect to debug adapter at {address}: {error}");
+                        executor.timer(CONNECT_RETRY_INTERVAL).await;
+                    }
+                }
+            }
+        };
+
+        smol::future::or(connect, async {
+            executor.timer(timeout).await;
+            Err(StartupTimeout {
+                phase: StartupPhase::Connect,
+                timeout,
+            }
+            .into())
+        })
+        .await
+    }
+
+    /// Get an open port to use with the tcp client when not supplied by debug config
+    async fn get_port(host: Ipv4Addr) -> Option<u16> {
+        Some(
+            TcpListener::bind(SocketAddrV4::new(host, 0))
+                .await
+                .ok()?
+                .local_addr()
+                .ok()?
+                .port(),
+        )
+    }
+
+    /// Creates a debug client that connects to an adapter through std input/output
+    ///
+    /// # Parameters
+    /// - `command`: The command that starts the debugger
+    /// - `args`: Arguments of the command that starts the debugger
+    /// - `cwd`: The working directory of the debugger
+    /// - `env`: Environment variables set for the debugger
+    async fn create_stdio_client(
+        command: &str,
+        args: Vec<String>,
+        cwd: PathBuf,
+        env: &HashMap<String, String>,
+    ) -> Result<TransportParams> {
+        let mut command = process::Command::new(command);
+        command
+            .current_dir(cwd)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut process = command
+            .spawn()
+            .with_context(|| "failed to spawn command.")?;
+
+        let stdin = process
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdin"))?;
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdout"))?;
+        let stderr = process
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stderr"))?;
+
+        Ok(TransportParams::new(
+            Box::new(BufReader::new(stdout)),
+            Box::new(stdin),
+            Some(Box::new(BufReader::new(stderr))),
+            Some(process),
+        ))
+    }
+
+    /// Spawns the IO loops of a started adapter, returning the channel to send payloads
+    /// to the adapter and the channel that receives the payloads it sends back.
+    fn handle_transport(
+        rx: Box<dyn futures::AsyncBufRead + Unpin + Send>,
+        tx: Box<dyn futures::AsyncWrite + Unpin + Send>,
+        err: Option<Box<dyn futures::AsyncBufRead + Unpin + Send>>,
+        stderr_tail: Arc<Mutex<VecDeque<String>>>,
+        log_messages: bool,
+        executor: &BackgroundExecutor,
+    ) -> (Sender<Payload>, Receiver<Payload>, Vec<Task<()>>) {
+        let (server_tx, client_rx) = unbounded::<Payload>();
+        let (client_tx, server_rx) = unbounded::<Payload>();
+
+        let mut tasks = vec![
+            executor.spawn(async move {
+                transport::handle_input(rx, client_tx, log_messages)
+                    .await
+                    .log_err();
+            }),
+            executor.spawn(async move {
+                transport::handle_output(tx, client_rx, log_messages)
+                    .await
+                    .log_err();
+            }),
+        ];
+
+        if let Some(err) = err {
+            tasks.push(executor.spawn(async move {
+                transport::handle_error(err, stderr_tail).await.log_err();
+            }));
+        }
+
+        (server_tx, server_rx, tasks)
+    }
+
+    /// Runs the connection to an adapter that listens on `address`, see
+    /// [`transport::handle_tcp_connection`]. When it couldn't reconnect after the
+    /// connection dropped, the session failed like in [`Self::watch_process`].
+    fn handle_tcp_connection(
+        &mut self,
+        rx: Box<dyn futures::AsyncBufRead + Unpin + Send>,
+        tx: Box<dyn futures::AsyncWrite + Unpin + Send>,
+        address: SocketAddrV4,
+        client_tx: Sender<Payload>,
+        client_rx: Receiver<Payload>,
+    ) {
+        let disconnecting = self.disconnecting.clone();
+        let fail_session = self.fail_session_callback();
+        let log_messages = self.config.log_messages;
+        let request_count = self.request_count.clone();
+        let executor = self.executor.clone();
+        self._tasks.push(self.executor.spawn(async move {
+            // keeps the receiving side open until the failure is reported, so the
+            // pending requests fail with the reason instead of a closed channel
+            let _client_tx = client_tx.clone();
+            let result = transport::handle_tcp_connection(
+                rx,
+                tx,
+                address,
+                client_tx,
+                client_rx,
+                request_count,
+                log_messages,
+                executor,
+            )
+            .await;
+            if let Err(error) = result {
+                if !disconnecting.load(Ordering::SeqCst) {
+                    let error = format!("{error:#}");
+                    log::error!("{error}");
+                    fail_session(error);
+                }
+            }
+        }));
+    }
+
+    /// Waits for the adapter process to exit. When it wasn't asked to disconnect, the
+    /// session failed: the threads are ended, requests fail and
+    /// [`ClientNotification::SessionFailed`] is sent with the exit status and the
+    /// last lines the adapter wrote to stderr.
+    fn watch_process(&mut self, mut process: Child, stderr_tail: Arc<Mutex<VecDeque<String>>>) {
+        let disconnecting = self.disconnecting.clone();
+        let fail_session = self.fail_session_callback();
+
+        // the process is killed when the task, and with it the client, is dropped
+        self._tasks.push(self.executor.spawn(async move {
+            let status = process.status().await;
+            if disconnecting.load(Ordering::SeqCst) {
+                return;
+            }
+
+            let mut error = match status {
+                Ok(status) => format!("debug adapter exited with {status}"),
+                Err(error) => format!("failed to wait for the debug adapter to exit: {error}"),
+            };
+            let stderr_tail = stderr_tail.lock();
+            if !stderr_tail.is_empty() {
+                error.push_str(":\n");
+                error.push_str(&Vec::from_iter(stderr_tail.iter().map(String::as_str)).join("\n"));
+            }
+            log::error!("{error}");
+            drop(stderr_tail);
+            fail_session(error);
+        }));
+    }
+
+    /// Returns the callback that ends the threads, fails the requests and sends
+    /// [`ClientNotification::SessionFailed`]. Only the first failure is reported, e.g.
+    /// when the adapter exits, its TCP connection drops as well.
+    fn fail_session_callback(&self) -> impl FnOnce(String) + Send + 'static {
+        let session_failed = self.session_failed.clone();
+        let thread_states = self.thread_states.clone();
+        let pending_requests = self.pending_requests.clone();
+        let server_tx = self.server_tx.clone();
+        let last_error = self.last_error.clone();
+        let notification_subscribers = self.notification_subscribers.clone();
+
+        move |error| {
+            if session_failed.swap(true, Ordering::SeqCst) {
+                return;
+            }
+
+            for thread_state in thread_states.lock().values_mut() {
+                thread_state.status = ThreadStatus::Ended;
+            }
+            *last_error.lock() = Some(error.clone());
+            pending_requests.lock().take();
+            server_tx.close();
+
+            let notification = ClientNotification::SessionFailed { error };
+            notification_subscribers
+                .lock()
+                .retain(|subscriber| subscriber.try_send(notification.clone()).is_ok());
+        }
+    }
+
+    /// Routes every payload the adapter sends: responses go back to the pending
+    /// [`Self::request`], events are queued for [`Self::handle_events`].
+    async fn handle_recv(
+        server_rx: Receiver<Payload>,
+        server_tx: Sender<Payload>,
+        pending_requests: PendingRequests,
+        event_tx: Sender<Events>,
+        request_count: Arc<AtomicU64>,
+        run_in_terminal_handler: Arc<Mutex<Option<RunInTerminalHandler>>>,
+        executor: BackgroundExecutor,
+    ) -> Result<()> {
+        while let Ok(payload) = server_rx.recv().await {
+            match payload {
+                Payload::Event(event) => event_tx.send(*event).await?,
+                Payload::Response(response) => Self::resolve_request(&pending_requests, response),
+                Payload::Request(request) => {
+                    let server_tx = server_tx.clone();
+                    let request_count = request_count.clone();
+                    let run_in_terminal_handler = run_in_terminal_handler.lock().clone();
+                    // reverse requests may take a while, don't hold up the adapter's other messages
+                    executor
+                        .spawn(async move {
+                            let result =
+                                Self::handle_reverse_request(&request, run_in_terminal_handler)
+                                    .await;
+                            let (success, message, body) = match result {
+                                Ok(body) => (true, None, Some(body)),
+                                Err(error) => {
+                                    log::error!(
+                                        "{} reverse request failed: {error:#}",
+                                        request.command
+                                    );
+                                    (false, Some(format!("{error:#}")), None)
+                                }
+                            };
+
+                            server_tx
+                                .send(Payload::Response(Response {
+                                    seq: request_count.fetch_add(1, Ordering::SeqCst),
+                                    request_seq: request.seq,
+                                    success,
+                                    command: request.command,
+                                    message,
+                                    body,
+                                }))
+                                .await
+                                .log_err();
+                        })
+                        .detach();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Passes a response to the request it belongs to.
+    fn resolve_request(pending_requests: &PendingRequests, response: Response) {
+        let tx = pending_requests
+            .lock()
+            .as_mut()
+            .and_then(|pending_requests| pending_requests.remove(&response.request_seq));
+
+        if let Some(tx) = tx {
+            // the request may have been given up on, e.g. after a timeout
+            tx.send(response).ok();
+        } else {
+            log::error!(
+                "debug adapter responded to request {} ({}), which is not pending",
+                response.request_seq,
+                response.command
+            );
+        }
+    }
+
+    async fn handle_reverse_request(
+        request: &Request,
+        run_in_terminal_handler: Option<RunInTerminalHandler>,
+    ) -> Result<Value> {
+        match request.command.as_str() {
+            <RunInTerminal as dap_types::requests::Request>::COMMAND => {
+                let arguments: RunInTerminalRequestArguments =
+                    serde_json::from_value(request.arguments.clone().unwrap_or_default())?;
+                if arguments.kind == Some(RunInTerminalRequestArgumentsKind::External) {
+                    return Ok(serde_json::to_value(Self::spawn_in_external_terminal(
+                        arguments,
+                    )?)?);
+                }
+                let handler = run_in_terminal_handler.context(
+                    "running the debuggee in a terminal is not supported, \
+                    set `run_in_terminal_in_background` to run it in the background",
+                )?;
+                let response = handler(arguments).await?;
+                Ok(serde_json::to_value(response)?)
+            }
+            command => Err(anyhow!("{command} request is not supported by the client")),
+        }
+    }
+
+    /// The [`RunInTerminalHandler`] of clients configured with
+    /// `run_in_terminal_in_background`, until a handler with a terminal is set.
+    fn background_run_in_terminal_handler() -> RunInTerminalHandler {
+        Arc::new(|arguments| {
+            async move {
+                log::warn!(
+                    "running `{}` in the background, as no terminal is available: \
+                    its output isn't shown",
+                    arguments.args.join(" ")
+                );
+                Self::spawn_in_background(arguments)
+            }
+            .boxed()
+        })
+    }
+
+    /// Spawns the debuggee of a `runInTerminal` request without a terminal.
+    fn spawn_in_background(
+        arguments: RunInTerminalRequestArguments,
+    ) -> Result<RunInTerminalResponse> {
+        let (program, args) = arguments
+            .args
+            .split_first()
+            .context("runInTerminal request without a command")?;
+
+        let mut command = process::Command::new(program);
+        command.args(args);
+        Self::set_run_in_terminal_environment(&mut command, &arguments);
+
+        let child = command
+            .spawn()
+            .with_context(|| format!("failed to spawn {program}"))?;
+
+        Ok(RunInTerminalResponse {
+            process_id: Some(child.id() as u64),
+            shell_process_id: None,
+        })
+    }
+
+    /// Runs the debuggee of a `runInTerminal` request in a new terminal window, for
+    /// launch configs with an `externalTerminal` console.
+    fn spawn_in_external_terminal(
+        arguments: RunInTerminalRequestArguments,
+    ) -> Result<RunInTerminalResponse> {
+        if arguments.args.is_empty() {
+            return Err(anyhow!("runInTerminal request without a command"));
+        }
+
+        let mut command = if cfg!(target_os = "macos") {
+            // Terminal.app starts a new shell, so it has to change the directory itself
+            let script = format!(
+                "cd {} && {}",
+                shell_quote(&arguments.cwd),
+                Vec::from_iter(arguments.args.iter().map(|arg| shell_quote(arg))).join(" ")
+            );
+            let mut command = process::Command::new("osascript");
+            command.arg("-e").arg(format!(
+                "tell application \"Terminal\" to do script \"{}\"",
+                script.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+            command
+        } else if cfg!(windows) {
+            let mut command = process::Command::new("cmd");
+            command.args(["/C", "start", ""]).args(&arguments.args);
+            command
+        } else {
+            let mut command = process::Command::new("x-terminal-emulator");
+            command.arg("-e").args(&arguments.args);
+            command
+        };
+        Self::set_run_in_terminal_environment(&mut command, &arguments);
+
+        let child = command
+            .spawn()
+            .context("failed to open an external terminal")?;
+
+        // the process of the terminal, the debuggee is started by it
+        Ok(RunInTerminalResponse {
+            process_id: None,
+            shell_process_id: Some(child.id() as u64),
+        })
+    }
+
+    fn set_run_in_terminal_environment(
+        command: &mut process::Command,
+        arguments: &RunInTerminalRequestArguments,
+    ) {
+        command.current_dir(&arguments.cwd);
+        if let Some(Value::Object(env)) = &arguments.env {
+            for (key, value) in env {
+                match value {
+                    Value::String(value) => command.env(key, value),
+                    Value::Null => command.env_remove(key),
+                    value => command.env(key, value.to_string()),
+                };
+            }
+        }
+    }
+
+    /// Send a request to an adapter and get a response back
+    /// Note: This function will block until a response is sent back from the adapter
+    ///
+    /// A failed request is remembered as the client's [`Self::last_error`],
+    /// a successful one clears it.
+    pub async fn request<R: dap_types::requests::Request>(
+        &self,
+        arguments: R::Arguments,
+    ) -> Result<R::Response> {
+        self.request_with_seq::<R>(self.next_sequence_id(), arguments)
+            .await
+    }
+
+    /// Like [`Self::request`], but with a sequence id that was taken from
+    /// [`Self::next_sequence_id`] up front, e.g. to be able to cancel the request.
+    async fn request_with_seq<R: dap_types::requests::Request>(
+        &self,
+        seq: u64,
+        arguments: R::Arguments,
+    ) -> Result<R::Response> {
+        let result = self.send_request::<R>(seq, arguments).await;
+        *self.last_error.lock() = result.as_ref().err().map(|error| format!("{error:#}"));
+        result
+    }
+
+    async fn send_request<R: dap_types::requests::Request>(
+        &self,
+        seq: u64,
+        arguments: R::Arguments,
+    ) -> Result<R::Response> {
+        let serialized_arguments = serde_json::to_value(arguments)?;
+
+        let request = Request {
+            seq,
+            command: R::COMMAND.to_string(),
+            arguments: Some(serialized_arguments),
+        };
+
+        let (tx, rx) = oneshot::channel();
+        self.pending_requests
+            .lock()
+            .as_mut()
+            .context("debug adapter closed the connection")?
+            .insert(seq, tx);
+
+        if let Err(error) = self.server_tx.send(Payload::Request(request)).await {
+            self.remove_pending_request(seq);
+            return Err(error.into());
+        }
+
+        // the sender is dropped when the session ends or the connection is closed
+        let response = rx.await.map_err(|_| {
+            anyhow!(
+                "debug session ended before the adapter responded to the {} request",
+                R::COMMAND
+            )
+        })?;
+
+        if response.success {
+            Ok(serde_json::from_value(
+                response.body.unwrap_or(Value::Null),
+            )?)
+        } else {
+            Err(anyhow!(
+                "{} request failed: {}",
+                R::COMMAND,
+                response.message.unwrap_or_default()
+            ))
+        }
+    }
+
+    /// Stops waiting for the response of a request.
+    fn remove_pending_request(&self, seq: u64) {
+        if let Some(pending_requests) = self.pending_requests.lock().as_mut() {
+            pending_requests.remove(&seq);
+        }
+    }
+
+    /// Sets how the debuggee is launched when the adapter sends a `runInTerminal` request,
+    /// e.g. the debug panel runs it in a terminal of the workspace.
+    ///
+    /// Without a handler, the request fails unless the client is configured with
+    /// `run_in_terminal_in_background`, which spawns the debuggee as a background process.
+    pub fn set_run_in_terminal_handler(&self, handler: RunInTerminalHandler) {
+        *self.run_in_terminal_handler.lock() = Some(handler);
+    }
+
+    /// The hash of the settings the adapter was spawned with, see [`SpawnSummary::hash_value`].
+    pub fn spawn_summary_hash(&self) -> Option<u64> {
+        self.spawn_summary.as_ref().map(SpawnSummary::hash_value)
+    }
+
+    pub fn id(&self) -> DebugAdapterClientId {
+        self.id
+    }
+
+    pub fn config(&self) -> DebugAdapterConfig {
+        self.config.clone()
+    }
+
+    pub fn request_type(&self) -> DebugRequestType {
+        self.config.request.clone()
+    }
+
+    pub fn next_sequence_id(&self) -> u64 {
+        self.request_count.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Registers a new listener for [`ClientNotification`]s, used by the store to forward them.
+    pub(crate) fn notifications(&self) -> Receiver<ClientNotification> {
+        let (tx, rx) = unbounded();
+        self.notification_subscribers.lock().push(tx);
+        rx
+    }
+
+    fn notify(&self, notification: ClientNotification) {
+        self.notification_subscribers
+            .lock()
+            .retain(|subscriber| subscriber.try_send(notification.clone()).is_ok());
+    }
+
+    /// The output events that should be shown in the debug console, in the order they were received.
+    pub fn console_output(&self) -> Vec<ConsoleOutput> {
+        self.console_output.lock().clone()
+    }
+
+    /// Registers a new listener for `telemetry` output events.
+    ///
+    /// These events are never added to the console output.
+    pub fn telemetry_events(&self) -> Receiver<OutputEvent> {
+        let (tx, rx) = unbounded();
+        self.telemetry_subscribers.lock().push(tx);
+        rx
+    }
+
+    fn handle_output_event(&self, event: &OutputEvent) {
+        if event.category == Some(OutputEventCategory::Telemetry) {
+            self.telemetry_subscribers
+                .lock()
+                .retain(|subscriber| subscriber.try_send(event.clone()).is_ok());
+        } else {
+            self.append_console_output(ConsoleOutput::new(event.clone()));
+        }
+    }
+
+    fn append_console_output(&self, output: ConsoleOutput) {
+        self.console_output.lock().push(output);
+        self.notify(ClientNotification::OutputReceived);
+    }
+
+    /// Returns the full [`Source`] the adapter sent for a `sourceReference`,
+    /// including any `adapterData` it attached to it.
+    pub fn source_by_reference(&self, source_reference: u64) -> Option<Source> {
+        self.sources.lock().get(&source_reference).cloned()
+    }
+
+    /// Returns the full version of a source the adapter referred to, which may only
+    /// contain its `sourceReference`.
+    pub fn resolve_source(&self, source: &Source) -> Source {
+        source
+            .source_reference
+            .filter(|source_reference| *source_reference > 0)
+            .and_then(|source_reference| self.source_by_reference(source_reference))
+            .unwrap_or_else(|| source.clone())
+    }
+
+    /// Remembers the sources (and their related sources) the adapter sent, so they can be
+    /// sent back exactly as the adapter provided them.
+    fn remember_sources<'a>(&self, sources: impl IntoIterator<Item = &'a Source>) {
+        let mut known_sources = self.sources.lock();
+        let mut sources = sources.into_iter().collect::<Vec<_>>();
+
+        while let Some(source) = sources.pop() {
+            if let Some(source_reference) = source.source_reference.filter(|id| *id > 0) {
+                known_sources.insert(source_reference, source.clone());
+            }
+            sources.extend(source.sources.iter().flatten());
+        }
+    }
+
+    /// The sources the adapter loaded, including the ones that have no local file and
+    /// can only be retrieved through their `sourceReference`.
+    pub fn loaded_source_list(&self) -> Vec<Source> {
+        self.loaded_sources.lock().clone()
+    }
+
+    fn handle_loaded_source_event(&self, event: &LoadedSourceEvent) {
+        self.remember_sources([&event.source]);
+
+        if event.reason != LoadedSourceEventReason::New {
+            if let Some(source_reference) = event.source.source_reference {
+                self.source_contents.lock().remove(&source_reference);
+            }
+        }
+
+        {
+            let mut loaded_sources = self.loaded_sources.lock();
+            let index = loaded_sources
+                .iter()
+                .position(|source| is_same_source(source, &event.source));
+            match (event.reason, index) {
+                (LoadedSourceEventReason::Removed, Some(index)) => {
+                    loaded_sources.remove(index);
+                }
+                (LoadedSourceEventReason::Removed, None) => return,
+                (_, Some(index)) => loaded_sources[index] = event.source.clone(),
+                (_, None) => loaded_sources.push(event.source.clone()),
+            }
+        }
+
+        self.notify(ClientNotification::LoadedSourcesUpdated);
+    }
+
+    /// Fetches `count` modules of the debuggee starting at `start`, or all of them, and
+    /// adds them to the modules collected from `module` events. The adapter may only
+    /// know the number of all modules once they were fetched, see [`Self::total_modules`].
+    pub async fn modules(&self, start: Option<u64>, count: Option<u64>) -> Result<Vec<Module>> {
+        self.require_capability(
+            |capabilities| capabilities.supports_modules_request == Some(true),
+            "modules",
+        )?;
+
+        let response = self
+            .request::<Modules>(ModulesArguments {
+                start_module: start,
+                module_count: count,
+            })
+            .await?;
+
+        self.modules.lock().extend(
+            response
+                .modules
+                .iter()
+                .map(|module| (module.id.clone(), module.clone())),
+        );
+        if response.total_modules.is_some() {
+            *self.total_modules.lock() = response.total_modules;
+        }
+        self.notify(ClientNotification::ModulesUpdated);
+
+        Ok(response.modules)
+    }
+
+    /// The number of modules the adapter reported with the last page of [`Self::modules`].
+    pub fn total_modules(&self) -> Option<u64> {
+        *self.total_modules.lock()
+    }
+
+    /// The modules of the debuggee that are known so far, ordered by name.
+    pub fn module_list(&self) -> Vec<Module> {
+        let mut modules = self.modules.lock().values().cloned().collect::<Vec<_>>();
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+        modules
+    }
+
+    pub fn module_by_id(&self, module_id: &ModuleId) -> Option<Module> {
+        self.modules.lock().get(module_id).cloned()
+    }
+
+    /// Returns the module a stack frame belongs to, if the adapter reported it.
+    pub fn module_for_frame(&self, stack_frame: &StackFrame) -> Option<Module> {
+        self.module_by_id(stack_frame.module_id.as_ref()?)
+    }
+
+    fn handle_module_event(&self, event: &ModuleEvent) {
+        let mut modules = self.modules.lock();
+        match event.reason {
+            ModuleEventReason::New | ModuleEventReason::Changed => {
+                modules.insert(event.module.id.clone(), event.module.clone());
+            }
+            ModuleEventReason::Removed => {
+                modules.remove(&event.module.id);
+            }
+        }
+        drop(modules);
+
+        self.notify(ClientNotification::ModulesUpdated);
+    }
+
+    /// Returns a long running operation of the adapter, as long as it didn't end.
+    pub fn progress_by_id(&self, progress_id: &str) -> Option<Progress> {
+        self.progress.lock().get(progress_id).cloned()
+    }
+
+    /// The [`SessionAction`]s the adapter currently supports.
+    ///
+    /// The store emits [`ClientNotification::CapabilitiesUpdated`] when this changes.
+    pub fn supported_actions(&self) -> Vec<SessionAction> {
+        SessionAction::ALL
+            .into_iter()
+            .filter(|action| self.has_capability(|capabilities| action.is_supported(capabilities)))
+            .collect()
+    }
+
+    /// Whether the adapter reported the capability checked by `has`.
+    fn has_capability(&self, has: impl Fn(&Capabilities) -> bool) -> bool {
+        self.capabilities.lock().as_ref().is_some_and(has)
+    }
+
+    /// Fails with a clear error when the adapter doesn't support an optional request,
+    /// instead of sending it and getting back whatever error the adapter comes up with.
+    fn require_capability(&self, has: impl Fn(&Capabilities) -> bool, name: &str) -> Result<()> {
+        if self.has_capability(has) {
+            Ok(())
+        } else {
+            Err(anyhow!("debug adapter does not support {name}"))
+        }
+    }
+
+    /// The capabilities of the debug adapter, including updates sent after initialization.
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        self.capabilities.lock().clone()
+    }
+
+    /// The optional features of the debug adapter, `None` until it was initialized.
+    pub fn features(&self) -> Option<DebugFeatures> {
+        self.capabilities.lock().as_ref().map(DebugFeatures::new)
+    }
+
+    /// The most recent error of this client, cleared by the next successful request.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().clone()
+    }
+
+    /// The exit code of the debuggee, once the adapter reported that it exited.
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.lock()
+    }
+
+    /// The process that is debugged, once the adapter sent the `process` event.
+    pub fn debuggee_process(&self) -> Option<ProcessEvent> {
+        self.debuggee_process.lock().clone()
+    }
+
+    /// What is debugged for the header of the session, e.g. "Debugging python (pid 1234)",
+    /// to confirm which process was attached to.
+    pub fn session_title(&self) -> String {
+        let Some(process) = self.debuggee_process() else {
+            return format!("Debugging {}", self.config.id);
+        };
+
+        let name = Path::new(&process.name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&process.name);
+        match process.system_process_id {
+            Some(pid) => format!("Debugging {name} (pid {pid})"),
+            None => format!("Debugging {name}"),
+        }
+    }
+
+    /// The step of the DAP handshake this client has reached.
+    pub fn configuration_phase(&self) -> ConfigurationPhase {
+        self.configuration_phases
+            .lock()
+            .last()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Every step of the DAP handshake this client has reached, in order.
+    pub fn configuration_phases(&self) -> Vec<ConfigurationPhase> {
+        self.configuration_phases.lock().clone()
+    }
+
+    /// Records that the handshake reached `phase`, unless it is already past it.
+    fn advance_configuration_phase(&self, phase: ConfigurationPhase) {
+        {
+            let mut phases = self.configuration_phases.lock();
+            if phases.last().map_or(false, |last| *last >= phase) {
+                return;
+            }
+            phases.push(phase);
+        }
+        self.notify(ClientNotification::ConfigurationPhaseChanged(phase));
+    }
+
+    /// Returns the last known state of a breakpoint the adapter assigned an id to.
+    pub fn breakpoint_by_id(&self, breakpoint_id: u64) -> Option<Breakpoint> {
+        self.breakpoints.lock().get(&breakpoint_id).cloned()
+    }
+
+    /// Returns the last known state of the breakpoint that was requested at the given
+    /// line, which may have been moved to another line by the adapter.
+    pub fn breakpoint_for_requested_line(&self, path: &Path, line: u64) -> Option<Breakpoint> {
+        let breakpoint = self
+            .requested_breakpoints
+            .lock()
+            .get(path)?
+            .iter()
+            .find(|requested| requested.source_breakpoint.line == line)?
+            .breakpoint
+            .clone()?;
+
+        breakpoint
+            .id
+            .and_then(|breakpoint_id| self.breakpoint_by_id(breakpoint_id))
+            .or(Some(breakpoint))
+    }
+
+    /// Returns why the breakpoint requested at the given line could not be verified.
+    pub fn breakpoint_message(&self, path: &Path, line: u64) -> Option<String> {
+        self.breakpoint_for_requested_line(path, line)
+            .filter(|breakpoint| !breakpoint.verified)?
+            .message
+    }
+
+    /// Returns the last known state of the breakpoints set in the given file.
+    pub fn breakpoints_for_path(&self, path: &Path) -> Vec<Breakpoint> {
+        let path = path.to_string_lossy();
+        let mut breakpoints = self
+            .breakpoints
+            .lock()
+            .values()
+            .filter(|breakpoint| breakpoint_path(breakpoint) == Some(path.as_ref()))
+            .cloned()
+            .collect::<Vec<_>>();
+        breakpoints.sort_by_key(|breakpoint| (breakpoint.line, breakpoint.id));
+        breakpoints
+    }
+
+    fn handle_breakpoint_event(&self, event: &BreakpointEvent) {
+        let Some(breakpoint_id) = event.breakpoint.id else {
+            log::warn!("debug adapter sent a breakpoint event without a breakpoint id");
+            return;
+        };
+
+        let verification_changed = {
+            let mut breakpoints = self.breakpoints.lock();
+            let was_verified = breakpoints
+                .get(&breakpoint_id)
+                .map(|breakpoint| breakpoint.verified);
+            match event.reason {
+                BreakpointEventReason::Removed => {
+                    breakpoints.remove(&breakpoint_id);
+                }
+                _ => {
+                    let updated = event.breakpoint.clone();
+                    match breakpoints.get_mut(&breakpoint_id) {
+                        // Only the attributes the adapter sent are updated,
+                        // e.g. a moved breakpoint usually doesn't repeat its source.
+                        Some(breakpoint) => {
+                            breakpoint.verified = updated.verified;
+                            breakpoint.message = updated.message;
+                            breakpoint.source = updated.source.or(breakpoint.source.take());
+                            breakpoint.line = updated.line.or(breakpoint.line);
+                            breakpoint.column = updated.column.or(breakpoint.column);
+                            breakpoint.end_line = updated.end_line.or(breakpoint.end_line);
+                            breakpoint.end_column = updated.end_column.or(breakpoint.end_column);
+                            breakpoint.reason = updated.reason;
+                        }
+                        None => {
+                            breakpoints.insert(breakpoint_id, updated);
+                        }
+                    }
+                }
+            }
+
+            breakpoints
+                .get(&breakpoint_id)
+                .filter(|breakpoint| was_verified != Some(breakpoint.verified))
+                .cloned()
+        };
+
+        if let Some(breakpoint) = verification_changed {
+            let change = BreakpointChange {
+                marker: self.marker_for_breakpoint(breakpoint_id),
+                breakpoint,
+            };
+            for callback in self.breakpoint_change_callbacks.lock().iter() {
+                callback(&change);
+            }
+        }
+
+        self.notify(ClientNotification::BreakpointUpdated { breakpoint_id });
+    }
+
+    /// Registers a callback that is called whenever the adapter verifies or unverifies a
+    /// breakpoint, with the editor marker the breakpoint was requested for.
+    pub fn on_breakpoint_changed(
+        &self,
+        callback: impl Fn(&BreakpointChange) + Send + Sync + 'static,
+    ) {
+        self.breakpoint_change_callbacks
+            .lock()
+            .push(Box::new(callback));
+    }
+
+    /// Returns the marker in the editor the breakpoint was requested for.
+    fn marker_for_breakpoint(&self, breakpoint_id: u64) -> Option<BreakpointMarker> {
+        self.requested_breakpoints
+            .lock()
+            .iter()
+            .find_map(|(path, breakpoints)| {
+                let requested = breakpoints.iter().find(|requested| {
+                    requested
+                        .breakpoint
+                        .as_ref()
+                        .is_some_and(|breakpoint| breakpoint.id == Some(breakpoint_id))
+                })?;
+                Some(BreakpointMarker {
+                    path: path.clone(),
+                    line: requested.source_breakpoint.line,
+                })
+            })
+    }
+
+    /// The thread the user is looking at.
+    pub fn current_thread_id(&self) -> Option<u64> {
+        *self.current_thread_id.lock()
+    }
+
+    pub fn set_current_thread_id(&self, thread_id: Option<u64>) {
+        *self.current_thread_id.lock() = thread_id;
+    }
+
+    /// Where the current thread is paused, in its selected stack frame, or `None` while
+    /// it's running or when the frame has no local source file.
+    pub fn execution_marker(&self) -> Option<ExecutionMarker> {
+        let thread_id = self.current_thread_id()?;
+        let thread_states = self.thread_states();
+        let thread_state = thread_states.get(&thread_id)?;
+        if thread_state.status != ThreadStatus::Stopped {
+            return None;
+        }
+
+        let top_frame = thread_state.stack_frames.first()?;
+        let stack_frame = thread_state
+            .current_stack_frame_id
+            .and_then(|id| {
+                thread_state
+                    .stack_frames
+                    .iter()
+                    .find(|frame| frame.id == id)
+            })
+            .unwrap_or(top_frame);
+        let source = self.resolve_source(stack_frame.source.as_ref()?);
+
+        Some(ExecutionMarker {
+            path: Path::new(source.path.as_ref()?).into(),
+            row: dap_line_to_row(stack_frame.line),
+            is_top_frame: stack_frame.id == top_frame.id,
+        })
+    }
+
+    pub fn thread_states(&self) -> MutexGuard<'_, HashMap<u64, ThreadState>> {
+        self.thread_states.lock()
+    }
+
+    /// Summarizes all threads, sorted by id, without cloning their cached stacks,
+    /// scopes and variables.
+    pub fn thread_states_snapshot(&self) -> Vec<ThreadSummary> {
+        let mut snapshot = self
+            .thread_states()
+            .iter()
+            .map(|(thread_id, thread_state)| ThreadSummary {
+                thread_id: *thread_id,
+                name: thread_state.name.clone(),
+                status: thread_state.status,
+                stop_reason: thread_state
+                    .stop_reason
+                    .clone()
+                    .filter(|_| thread_state.status == ThreadStatus::Stopped),
+                top_frame: thread_state
+                    .stack_frames
+                    .first()
+                    .map(|frame| StackFrameSummary {
+                        id: frame.id,
+                        name: frame.name.clone(),
+                        path: frame.source.as_ref().and_then(|source| source.path.clone()),
+                        line: frame.line,
+                    }),
+            })
+            .collect::<Vec<_>>();
+        snapshot.sort_by_key(|summary| summary.thread_id);
+        snapshot
+    }
+
+    pub fn thread_state_by_id(&self, thread_id: u64) -> ThreadState {
+        self.thread_states
+            .lock()
+            .get(&thread_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// How many times the thread stopped during the session, e.g. to detect a breakpoint
+    /// that is hit in a loop.
+    pub fn thread_stop_count(&self, thread_id: u64) -> usize {
+        self.thread_states()
+            .get(&thread_id)
+            .map_or(0, |thread_state| thread_state.stop_count)
+    }
+
+    /// Why the thread stopped, while it is stopped.
+    pub fn thread_stop_reason(&self, thread_id: u64) -> Option<StoppedEventReason> {
+        self.thread_states().get(&thread_id)?.stop_reason.clone()
+    }
+
+    /// The breakpoints that made the thread stop, to highlight the one that fired when
+    /// several are close to each other.
+    pub fn hit_breakpoint_ids(&self, thread_id: u64) -> Vec<u64> {
+        self.thread_states()
+            .get(&thread_id)
+            .map(|thread_state| thread_state.hit_breakpoint_ids.clone())
+            .unwrap_or_default()
+    }
+
+    /// How often the debuggee stopped at the breakpoint in this session, e.g. to show
+    /// how often a line with a hit condition was executed.
+    pub fn breakpoint_hit_count(&self, breakpoint_id: u64) -> u64 {
+        self.breakpoint_hit_counts
+            .lock()
+            .get(&breakpoint_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets the status of a thread, adding the thread when it wasn't seen yet, e.g.
+    /// when the adapter didn't send a `thread` event for it before it stopped.
+    pub fn update_thread_state_status(&self, thread_id: u64, status: ThreadStatus) {
+        self.thread_states().entry(thread_id).or_default().status = status;
+    }
+
+    /// Processes the events sent by the adapter, keeping the thread states up to date
+    /// before handing every event to `event_handler`.
+    ///
+    /// The loop only holds a weak reference to the client, and returns once the last
+    /// strong reference is dropped.
+    pub async fn handle_events<F>(this: Arc<Self>, mut event_handler: F) -> Result<()>
+    where
+        F: FnMut(Events),
+    {
+        let event_rx = this
+            .event_rx
+            .lock()
+            .take()
+            .context("events of this client are already being handled")?;
+        let client = Arc::downgrade(&this);
+        drop(this);
+
+        // Dropping the client drops the task that owns the sender, which ends the loop.
+        while let Ok(event) = event_rx.recv().await {
+            let Some(this) = client.upgrade() else {
+                break;
+            };
+            this.handle_event(&event);
+            // events that need requests to be handled
+            match &event {
+                Events::Stopped(StoppedEvent {
+                    thread_id: None,
+                    reason,
+                    preserve_focus_hint,
+                    ..
+                }) => {
+                    let reason = reason.clone();
+                    let preserve_focus = preserve_focus_hint.unwrap_or_default();
+                    this.executor
+                        .clone()
+                        .spawn(async move {
+                            this.handle_threadless_stop(reason, preserve_focus)
+                                .await
+                                .log_err()
+                        })
+                        .detach();
+                }
+                // steps of the client refresh the thread themselves once it stopped,
+                // other stops (e.g. on a breakpoint) re-evaluate the watches here
+                Events::Stopped(StoppedEvent {
+                    thread_id: Some(thread_id),
+                    ..
+                }) if this.watches_follow_stop(*thread_id) => {
+                    let thread_id = *thread_id;
+                    this.executor
+                        .clone()
+                        .spawn(async move { this.refresh_thread(thread_id).await.log_err() })
+                        .detach();
+                }
+                Events::Invalidated(event) => {
+                    let event = event.clone();
+                    this.executor
+                        .clone()
+                        .spawn(async move { this.handle_invalidated_event(&event).await.log_err() })
+                        .detach();
+                }
+                _ => {}
+            }
+            event_handler(event);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the stop of the thread should re-evaluate the watches: it is the current
+    /// thread, and no step of the client, which refreshes the thread itself, is running.
+    fn watches_follow_stop(&self, thread_id: u64) -> bool {
+        let is_current_thread = self
+            .current_thread_id()
+            .map_or(true, |current_thread_id| current_thread_id == thread_id);
+        is_current_thread && !self.thread_is_busy(thread_id) && !self.watches.lock().is_empty()
+    }
+
+    fn handle_event(&self, event: &Events) {
+        match event {
+            Events::Thread(event) => match event.reason {
+                ThreadEventReason::Started => {
+                    self.thread_states()
+                        .insert(event.thread_id, ThreadState::default());
+                    self.notify(ClientNotification::ThreadsUpdated);
+                }
+                ThreadEventReason::Exited => {
+                    self.update_thread_state_status(event.thread_id, ThreadStatus::Exited);
+                    self.notify(ClientNotification::ThreadsUpdated);
+                }
+                _ => {}
+            },
+            Events::Initialized(_) => {
+                self.advance_configuration_phase(ConfigurationPhase::InitializedReceived);
+                let mut initialized_waiters = self.initialized_waiters.lock();
+                self.initialized_received.store(true, Ordering::SeqCst);
+                for waiter in initialized_waiters.drain(..) {
+                    waiter.send(()).ok();
+                }
+            }
+            Events::Stopped(event) => self.handle_stopped_event(event),
+            Events::Continued(event) => {
+                // an omitted `allThreadsContinued` means that all threads continued
+                self.mark_continued(event.thread_id, event.all_threads_continued.unwrap_or(true));
+                self.notify(ClientNotification::ThreadContinued {
+                    thread_id: event.thread_id,
+                    all_threads_continued: event.all_threads_continued.unwrap_or(true),
+                });
+            }
+            Events::Output(event) => {
+                self.remember_sources(&event.source);
+                self.handle_output_event(event);
+            }
+            Events::Breakpoint(event) => {
+                self.remember_sources(&event.breakpoint.source);
+                self.handle_breakpoint_event(event);
+            }
+            Events::Process(event) => {
+                *self.debuggee_process.lock() = Some(event.clone());
+                self.notify(ClientNotification::ProcessStarted);
+            }
+            Events::Exited(event) => {
+                // exit codes are 32 bits wide, e.g. unsigned NTSTATUS codes on Windows
+                *self.exit_code.lock() = Some(event.exit_code as u32 as i32);
+            }
+            Events::Terminated(_) => {
+                for thread_state in self.thread_states().values_mut() {
+                    thread_state.status = ThreadStatus::Ended;
+                }
+                // the adapter won't respond to the requests that were sent before, but
+                // may still respond to new ones, e.g. `disconnect`
+                if let Some(pending_requests) = self.pending_requests.lock().as_mut() {
+                    pending_requests.clear();
+                }
+                self.notify(ClientNotification::SessionEnded);
+            }
+            Events::LoadedSource(event) => self.handle_loaded_source_event(event),
+            Events::Memory(event) => self.handle_memory_event(event),
+            Events::Module(event) => self.handle_module_event(event),
+            Events::ProgressStart(event) => {
+                self.progress.lock().insert(
+                    event.progress_id.clone(),
+                    Progress {
+                        title: event.title.clone(),
+                        message: event.message.clone(),
+                        percentage: event.percentage,
+                        cancellable: event.cancellable.unwrap_or_default(),
+                    },
+                );
+                self.notify(ClientNotification::ProgressUpdated {
+                    progress_id: event.progress_id.clone(),
+                });
+            }
+            Events::ProgressUpdate(event) => {
+                let updated = self
+                    .progress
+                    .lock()
+                    .get_mut(&event.progress_id)
+                    .map(|progress| {
+                        if event.message.is_some() {
+                            progress.message.clone_from(&event.message);
+                        }
+                        if event.percentage.is_some() {
+                            progress.percentage = event.percentage;
+                        }
+                    })
+                    .is_some();
+                if updated {
+                    self.notify(ClientNotification::ProgressUpdated {
+                        progress_id: event.progress_id.clone(),
+                    });
+                } else {
+                    log::debug!("progress update for unknown progress {}", event.progress_id);
+                }
+            }
+            Events::ProgressEnd(event) => {
+                if self.progress.lock().remove(&event.progress_id).is_some() {
+                    self.notify(ClientNotification::ProgressUpdated {
+                        progress_id: event.progress_id.clone(),
+                    });
+                } else {
+                    log::debug!("progress end for unknown progress {}", event.progress_id);
+                }
+            }
+            Events::Capabilities(event) => {
+                {
+                    let mut capabilities = self.capabilities.lock();
+                    *capabilities = Some(match capabilities.take() {
+                        Some(current) => merge_capabilities(current, &event.capabilities),
+                        None => event.capabilities.clone(),
+                    });
+                }
+                self.notify(ClientNotification::CapabilitiesUpdated);
+            }
+            _ => {}
+        }
+    }
+
+    fn mark_continued(&self, thread_id: u64, all_threads_continued: bool) {
+        let mut thread_states = self.thread_states();
+        if all_threads_continued {
+            thread_states.values_mut().for_each(ThreadState::continued);
+        } else {
+            thread_states.entry(thread_id).or_default().continued();
+        }
+    }
+
+    fn handle_stopped_event(&self, event: &StoppedEvent) {
+        // some adapters omit the thread id when all threads stopped
+        let all_threads_stopped =
+            event.all_threads_stopped.unwrap_or_default() || event.thread_id.is_none();
+
+        let mut thread_states = self.thread_states();
+        // the adapter may report a stop before the `thread` event of the thread
+        if let Some(thread_id) = event.thread_id {
+            thread_states.entry(thread_id).or_default();
+        }
+        if all_threads_stopped {
+            for thread_state in thread_states.values_mut() {
+                thread_state.status = ThreadStatus::Stopped;
+                thread_state.stop_count += 1;
+                thread_state.stop_reason = Some(event.reason.clone());
+            }
+        } else if let Some(thread_state) = event
+            .thread_id
+            .and_then(|thread_id| thread_states.get_mut(&thread_id))
+        {
+            thread_state.status = ThreadStatus::Stopped;
+            thread_state.stop_count += 1;
+            thread_state.stop_reason = Some(event.reason.clone());
+        }
+
+        if let Some(thread_state) = event
+            .thread_id
+            .and_then(|thread_id| thread_states.get_mut(&thread_id))
+        {
+            thread_state.hit_breakpoint_ids = event.hit_breakpoint_ids.clone().unwrap_or_default();
+            thread_state.exception_description = if event.reason == StoppedEventReason::Exception {
+                event.text.clone().or_else(|| event.description.clone())
+            } else {
+                None
+            };
+        }
+        drop(thread_states);
+
+        if let Some(hit_breakpoint_ids) = &event.hit_breakpoint_ids {
+            let mut breakpoint_hit_counts = self.breakpoint_hit_counts.lock();
+            for breakpoint_id in hit_breakpoint_ids {
+                *breakpoint_hit_counts.entry(*breakpoint_id).or_default() += 1;
+            }
+        }
+
+        let waiters = {
+            let mut stop_waiters = self.stop_waiters.lock();
+            if all_threads_stopped {
+                stop_waiters
+                    .drain()
+                    .flat_map(|(_, waiters)| waiters)
+                    .collect()
+            } else {
+                event
+                    .thread_id
+                    .and_then(|thread_id| stop_waiters.remove(&thread_id))
+                    .unwrap_or_default()
+            }
+        };
+
+        for waiter in waiters {
+            waiter.send(event.clone()).ok();
+        }
+
+        // a stop without a thread id is announced by `handle_threadless_stop`,
+        // once the threads are fetched
+        if event.thread_id.is_some() {
+            self.notify(ClientNotification::ThreadStopped {
+                thread_id: event.thread_id,
+                all_threads_stopped,
+                preserve_focus: event.preserve_focus_hint.unwrap_or_default(),
+            });
+        }
+    }
+
+    /// Handles a `stopped` event without a thread id, which means that all threads
+    /// stopped: the threads are fetched, as the client may not know all of them, and
+    /// marked as stopped. When the current thread isn't one of them, the first thread
+    /// becomes the current one, unless the adapter asked to preserve the focus.
+    ///
+    /// When the threads can't be fetched, the stop is still announced for the current
+    /// thread, as the known threads were already marked as stopped.
+    async fn handle_threadless_stop(
+        &self,
+        reason: StoppedEventReason,
+        preserve_focus: bool,
+    ) -> Result<()> {
+        let threads = match self.threads().await {
+            Ok(threads) => threads,
+            Err(error) => {
+                self.notify(ClientNotification::ThreadStopped {
+                    thread_id: self.current_thread_id(),
+                    all_threads_stopped: true,
+                    preserve_focus,
+                });
+                return Err(error);
+            }
+        };
+
+        {
+            let mut thread_states = self.thread_states();
+            for thread in &threads {
+                let thread_state = thread_states.entry(thread.id).or_default();
+                if thread_state.status != ThreadStatus::Stopped {
+                    thread_state.status = ThreadStatus::Stopped;
+                    thread_state.stop_count += 1;
+                    thread_state.stop_reason = Some(reason.clone());
+                }
+            }
+        }
+
+        let current_thread_id = self.current_thread_id();
+        let thread_id = if preserve_focus {
+            current_thread_id
+        } else {
+            let thread_id = current_thread_id
+                .filter(|thread_id| threads.iter().any(|thread| thread.id == *thread_id))
+                .or_else(|| threads.first().map(|thread| thread.id));
+            self.set_current_thread_id(thread_id);
+            thread_id
+        };
+
+        self.notify(ClientNotification::ThreadStopped {
+            thread_id,
+            all_threads_stopped: true,
+            preserve_focus,
+        });
+        Ok(())
+    }
+
+    /// Returns the lock that is held while a control operation of the thread is in flight.
+    fn thread_lock(&self, thread_id: u64) -> Arc<smol::lock::Mutex<()>> {
+        self.thread_locks
+            .lock()
+            .entry(thread_id)
+            .or_default()
+            .clone()
+    }
+
+    /// Whether a control operation (step, continue, pause or goto) of the thread is in flight,
+    /// so no other one should be started.
+    pub fn thread_is_busy(&self, thread_id: u64) -> bool {
+        self.thread_locks
+            .lock()
+            .get(&thread_id)
+            .map_or(false, |lock| lock.try_lock().is_none())
+    }
+
+    /// Sends a request that resumes the thread and waits until it stopped again,
+    /// after which the stack of the thread is refreshed.
+    async fn run_until_stopped(
+        &self,
+        thread_id: u64,
+        request: impl Future<Output = Result<()>>,
+    ) -> Result<()> {
+        let thread_lock = self.thread_lock(thread_id);
+        let guard = thread_lock.lock().await;
+
+        let stopped = self.wait_for_stop(thread_id);
+        request.await?;
+        self.await_stop(thread_id, stopped, THREAD_STOP_TIMEOUT)
+            .await?;
+        drop(guard);
+
+        self.refresh_thread(thread_id).await
+    }
+
+    /// Returns a receiver that resolves with the next `stopped` event of the given thread.
+    fn wait_for_stop(&self, thread_id: u64) -> oneshot::Receiver<StoppedEvent> {
+        let (tx, rx) = oneshot::channel();
+        self.stop_waiters
+            .lock()
+            .entry(thread_id)
+            .or_default()
+            .push(tx);
+        rx
+    }
+
+    async fn await_stop(
+        &self,
+        thread_id: u64,
+        stopped: oneshot::Receiver<StoppedEvent>,
+        timeout: Duration,
+    ) -> Result<StoppedEvent> {
+        smol::future::or(
+            async {
+                stopped
+                    .await
+                    .context("the debug adapter client was dropped")
+            },
+            async {
+                self.executor.timer(timeout).await;
+                Err(anyhow!("timed out waiting for thread {thread_id} to stop"))
+            },
+        )
+        .await
+    }
+
+    pub async fn initialize(&self) -> Result<Capabilities> {
+        let args = InitializeRequestArguments {
+            client_id: Some(CLIENT_ID.to_owned()),
+            client_name: Some(CLIENT_NAME.to_owned()),
+            adapter_id: self.config.id.clone(),
+            locale: Some(
+                self.config
+                    .locale
+                    .clone()
+                    .or_else(system_locale)
+                    .unwrap_or_else(|| DEFAULT_LOCALE.to_owned()),
+            ),
+            path_format: Some(InitializeRequestArgumentsPathFormat::Path),
+            supports_variable_type: Some(true),
+            supports_variable_paging: Some(true),
+            supports_run_in_terminal_request: Some(true),
+            supports_memory_references: Some(true),
+            supports_progress_reporting: Some(true),
+            supports_invalidated_event: Some(true),
+            lines_start_at1: Some(true),
+            columns_start_at1: Some(true),
+            supports_memory_event: Some(true),
+            supports_args_can_be_interpreted_by_shell: None,
+            supports_start_debugging_request: Some(false),
+        };
+
+        let timeout = self
+            .config
+            .initialize_timeout
+            .map_or(DEFAULT_INITIALIZE_TIMEOUT, Duration::from_millis);
+        self.advance_configuration_phase(ConfigurationPhase::InitializeSent);
+        let capabilities = smol::future::or(self.request::<Initialize>(args), async {
+            self.executor.timer(timeout).await;
+            Err(StartupTimeout {
+                phase: StartupPhase::Initialize,
+                timeout,
+            }
+            .into())
+        })
+        .await?;
+
+        *self.capabilities.lock() = Some(capabilities.clone());
+
+        Ok(capabilities)
+    }
+
+    /// Launches the debuggee. The `console` of the config is added to the arguments,
+    /// unless they set one already.
+    pub async fn launch(&self, args: Option<Value>) -> Result<()> {
+        let mut args = args.unwrap_or(Value::Null);
+        if let Value::Object(args) = &mut args {
+            args.entry("console")
+                .or_insert_with(|| json!(self.config.console));
+        }
+
+        self.request::<Launch>(LaunchRequestArguments { raw: args })
+            .await
+    }
+
+    pub async fn attach(&self, args: Option<Value>) -> Result<()> {
+        self.request::<Attach>(AttachRequestArguments {
+            raw: args.unwrap_or(Value::Null),
+        })
+        .await
+    }
+
+    /// Tells the adapter that the configuration, e.g. of breakpoints, is done. Adapters
+    /// that don't support the request start right away, so it is only sent when the
+    /// adapter says that it does.
+    pub async fn configuration_done(&self) -> Result<()> {
+        if self.has_capability(|capabilities| {
+            capabilities.supports_configuration_done_request == Some(true)
+        }) {
+            self.request::<ConfigurationDone>(ConfigurationDoneArguments)
+                .await?;
+        }
+        self.advance_configuration_phase(ConfigurationPhase::ConfigurationDone);
+
+        Ok(())
+    }
+
+    pub async fn disconnect(
+        &self,
+        restart: Option<bool>,
+        terminate: Option<bool>,
+        suspend: Option<bool>,
+    ) -> Result<()> {
+        // the adapter exiting after this is expected
+        self.disconnecting.store(true, Ordering::SeqCst);
+
+        self.request::<Disconnect>(DisconnectArguments {
+            restart,
+            terminate_debuggee: terminate,
+            suspend_debuggee: suspend,
+        })
+        .await
+    }
+
+    /// Asks the adapter to end the debuggee gracefully, giving it a chance to clean up,
+    /// unlike [`Self::disconnect`] which kills it.
+    pub async fn terminate(&self) -> Result<()> {
+        self.require_capability(
+            |capabilities| capabilities.supports_terminate_request == Some(true),
+            "terminating",
+        )?;
+
+        self.request::<Terminate>(TerminateArguments { restart: None })
+            .await
+    }
+
+    /// Records that the file was edited, so the next breakpoints sent for it tell the
+    /// adapter to resolve their lines again.
+    pub fn mark_source_modified(&self, absolute_file_path: Arc<Path>) {
+        self.modified_sources.lock().insert(absolute_file_path);
+    }
+
+    /// Replaces all breakpoints of the given file.
+    pub async fn set_breakpoints(
+        &self,
+        absolute_file_path: Arc<Path>,
+        breakpoints: Option<Vec<SourceBreakpoint>>,
+    ) -> Result<SetBreakpointsResponse> {
+        let requested_breakpoints = breakpoints.clone().unwrap_or_default();
+        let supports_hit_conditions = self.has_capability(|capabilities| {
+            capabilities.supports_hit_conditional_breakpoints == Some(true)
+        });
+        let breakpoints = breakpoints.map(|mut breakpoints| {
+            if !supports_hit_conditions {
+                for breakpoint in &mut breakpoints {
+                    breakpoint.hit_condition = None;
+                }
+            }
+            breakpoints
+        });
+        let path = absolute_file_path.to_string_lossy().into_owned();
+        let source = Source {
+            path: Some(path.clone()),
+            name: None,
+            source_reference: None,
+            presentation_hint: None,
+            origin: None,
+            sources: None,
+            adapter_data: None,
+            checksums: None,
+        };
+
+        // edits made while the request is pending mark the file again
+        let source_modified = self
+            .modified_sources
+            .lock()
+            .remove(&absolute_file_path)
+            .then_some(true);
+
+        let response = self
+            .request::<SetBreakpoints>(SetBreakpointsArguments {
+                source: source.clone(),
+                breakpoints,
+                source_modified,
+                lines: None,
+            })
+            .await
+            .inspect_err(|_| {
+                if source_modified.is_some() {
+                    self.mark_source_modified(absolute_file_path.clone());
+                }
+            })?;
+
+        let mut known_breakpoints = self.breakpoints.lock();
+        known_breakpoints.retain(|_, breakpoint| breakpoint_path(breakpoint) != Some(&path));
+        for breakpoint in &response.breakpoints {
+            if let Some(breakpoint_id) = breakpoint.id {
+                let mut breakpoint = breakpoint.clone();
+                // adapters are not required to repeat the source in the response
+                breakpoint.source.get_or_insert_with(|| source.clone());
+                known_breakpoints.insert(breakpoint_id, breakpoint);
+            }
+        }
+        // removed breakpoints start counting from zero when they're set again
+        self.breakpoint_hit_counts
+            .lock()
+            .retain(|breakpoint_id, _| known_breakpoints.contains_key(breakpoint_id));
+        drop(known_breakpoints);
+
+        // the adapter responds with the breakpoints in the order they were requested
+        self.requested_breakpoints.lock().insert(
+            absolute_file_path,
+            requested_breakpoints
+                .into_iter()
+                .enumerate()
+                .map(|(ix, source_breakpoint)| RequestedBreakpoint {
+                    source_breakpoint,
+                    breakpoint: response.breakpoints.get(ix).cloned(),
+                })
+                .collect(),
+        );
+
+        Ok(response)
+    }
+
+    /// Sets the hit condition (e.g. `>= 5`) of the breakpoint requested at the given line,
+    /// resending the other breakpoints of the file as they were.
+    ///
+    /// Hit conditions are only sent to adapters that support them.
+    pub async fn set_breakpoint_hit_condition(
+        &self,
+        absolute_file_path: Arc<Path>,
+        line: u64,
+        hit_condition: Option<String>,
+    ) -> Result<SetBreakpointsResponse> {
+        let hit_condition = hit_condition
+            .map(|hit_condition| hit_condition.trim().to_string())
+            .filter(|hit_condition| !hit_condition.is_empty());
+        if let Some(hit_condition) = &hit_condition {
+            validate_hit_condition(hit_condition)?;
+        }
+
+        let mut breakpoints = self
+            .requested_breakpoints
+            .lock()
+            .get(&absolute_file_path)
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .map(|requested| requested.source_breakpoint.clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let breakpoint = breakpoints
+            .iter_mut()
+            .find(|breakpoint| breakpoint.line == line)
+            .with_context(|| {
+                format!(
+                    "no breakpoint at {}:{line}",
+                    absolute_file_path.to_string_lossy()
+                )
+            })?;
+        breakpoint.hit_condition = hit_condition;
+
+        self.set_breakpoints(absolute_file_path, Some(breakpoints))
+            .await
+    }
+
+    /// Configures which exceptions the debuggee should stop at.
+    pub async fn set_exception_breakpoints(
+        &self,
+        filters: Vec<String>,
+        filter_options: Option<Vec<ExceptionFilterOptions>>,
+        exception_options: Option<Vec<ExceptionOptions>>,
+    ) -> Result<SetExceptionBreakpointsResponse> {
+        let arguments = SetExceptionBreakpointsArguments {
+            filters,
+            filter_options,
+            exception_options,
+        };
+
+        let response = self
+            .request::<SetExceptionBreakpoints>(arguments.clone())
+            .await?;
+        *self.exception_breakpoints.lock() = Some(arguments);
+
+        Ok(response)
+    }
+
+    /// Replaces the breakpoints of the file like [`Self::set_breakpoints`], but waits a
+    /// moment for further updates of the same file first, so toggling breakpoints in
+    /// quick succession results in a single request with the latest breakpoints.
+    pub fn update_breakpoints_debounced(
+        self: &Arc<Self>,
+        absolute_file_path: Arc<Path>,
+        breakpoints: Vec<SourceBreakpoint>,
+    ) {
+        let is_scheduled = self
+            .pending_breakpoint_updates
+            .lock()
+            .insert(absolute_file_path.clone(), breakpoints)
+            .is_some();
+        if is_scheduled {
+            return;
+        }
+
+        let this = self.clone();
+        self.executor
+            .spawn(async move {
+                this.executor.timer(BREAKPOINT_UPDATE_DEBOUNCE).await;
+                let breakpoints = this
+                    .pending_breakpoint_updates
+                    .lock()
+                    .remove(&absolute_file_path);
+                if let Some(breakpoints) = breakpoints {
+                    this.set_breakpoints(absolute_file_path, Some(breakpoints))
+                        .await
+                        .log_err();
+                }
+            })
+            .detach();
+    }
+
+    /// Starts the debuggee after [`Self::initialize`], following the order of the protocol:
+    /// `launch` or `attach` is sent right away, but the adapter only completes it once
+    /// it was configured. That happens when it sent the `initialized` event, as
+    /// breakpoints sent before it are ignored by many adapters, see [`Self::configure`].
+    pub async fn start(&self, args: Option<Value>, store: &BreakpointStore) -> Result<()> {
+        let start = async {
+            match self.request_type() {
+                DebugRequestType::Launch => self.launch(args).await,
+                DebugRequestType::Attach => self.attach(args).await,
+            }
+        };
+        let configure = async {
+            self.initialized_with_timeout().await?;
+            self.configure(store).await
+        };
+
+        futures::future::try_join(start, configure).await?;
+        Ok(())
+    }
+
+    /// Waits until the adapter sent the `initialized` event, which it may have already.
+    async fn initialized(&self) -> Result<()> {
+        let rx = {
+            let mut initialized_waiters = self.initialized_waiters.lock();
+            if self.initialized_received.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            let (tx, rx) = oneshot::channel();
+            initialized_waiters.push(tx);
+            rx
+        };
+        rx.await
+            .context("debug adapter stopped before it was initialized")
+    }
+
+    /// Waits for the `initialized` event like [`Self::initialized`], but gives up after the
+    /// configured timeout. Adapters that never send it fail to start, unless the config
+    /// asks to configure them anyway.
+    async fn initialized_with_timeout(&self) -> Result<()> {
+        let timeout = self
+            .config
+            .initialized_timeout
+            .map_or(DEFAULT_INITIALIZED_TIMEOUT, Duration::from_millis);
+        let result = smol::future::or(self.initialized(), async {
+            self.executor.timer(timeout).await;
+            Err(StartupTimeout {
+                phase: StartupPhase::Initialized,
+                timeout,
+            }
+            .into())
+        })
+        .await;
+
+        match result {
+            Err(error)
+                if self.config.configure_without_initialized
+                    && error.downcast_ref::<StartupTimeout>().is_some() =>
+            {
+                log::warn!("{error}, configuring it anyway");
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Configures the session once the adapter sent the `initialized` event: sends the
+    /// breakpoints of the store and then `configurationDone`, when the adapter
+    /// supports it.
+    pub async fn configure(&self, store: &BreakpointStore) -> Result<()> {
+        self.replay_breakpoints(store).await?;
+        self.configuration_done().await
+    }
+
+    /// Sends the enabled breakpoints of the store, e.g. the ones set in earlier sessions.
+    /// Call it once the adapter sent the `initialized` event, before
+    /// [`Self::configuration_done`].
+    pub async fn replay_breakpoints(&self, store: &BreakpointStore) -> Result<()> {
+        self.advance_configuration_phase(ConfigurationPhase::BreakpointsSent);
+        let breakpoints = store
+            .paths()
+            .map(|path| (Arc::<Path>::from(path), store.source_breakpoints(path)))
+            .filter(|(_, breakpoints)| !breakpoints.is_empty())
+            .collect::<Vec<_>>();
+        for (path, breakpoints) in breakpoints {
+            self.set_breakpoints(path, Some(breakpoints)).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends all configured source, exception and data breakpoints again, e.g. after
+    /// the debuggee was restarted.
+    pub async fn resend_breakpoints(&self) -> Result<()> {
+        let mut source_breakpoints = self
+            .requested_breakpoints
+            .lock()
+            .iter()
+            .map(|(path, breakpoints)| {
+                (
+                    path.clone(),
+                    breakpoints
+                        .iter()
+                        .map(|requested| requested.source_breakpoint.clone())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        source_breakpoints.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (path, breakpoints) in source_breakpoints {
+            self.set_breakpoints(path, Some(breakpoints)).await?;
+        }
+
+        let exception_breakpoints = self.exception_breakpoints.lock().clone();
+        if let Some(arguments) = exception_breakpoints {
+            self.set_exception_breakpoints(
+                arguments.filters,
+                arguments.filter_options,
+                arguments.exception_options,
+            )
+            .await?;
+        }
+
+        let data_breakpoints = self.data_breakpoints();
+        if !data_breakpoints.is_empty() {
+            self.set_data_breakpoints(data_breakpoints).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The locations between `line` and `end_line` (or on `line`) where breakpoints can
+    /// be set, e.g. to show them before the user sets one.
+    pub async fn breakpoint_locations(
+        &self,
+        source: Source,
+        line: u64,
+        end_line: Option<u64>,
+    ) -> Result<Vec<BreakpointLocation>> {
+        self.require_capability(
+            |capabilities| capabilities.supports_breakpoint_locations_request == Some(true),
+            "breakpoint locations",
+        )?;
+
+        Ok(self
+            .request::<BreakpointLocations>(BreakpointLocationsArguments {
+                source,
+                line,
+                column: None,
+                end_line,
+                end_column: None,
+            })
+            .await?
+            .breakpoints)
+    }
+
+    /// Asks whether a data breakpoint can be set on a variable (a child of
+    /// `variables_reference`) or an expression, returning the `dataId` to set it with.
+    pub async fn data_breakpoint_info(
+        &self,
+        variables_reference: Option<u64>,
+        name: String,
+    ) -> Result<DataBreakpointInfoResponse> {
+        self.require_capability(
+            |capabilities| capabilities.supports_data_breakpoints == Some(true),
+            "data breakpoints",
+        )?;
+
+        let response = self
+            .request::<DataBreakpointInfo>(DataBreakpointInfoArguments {
+                variables_reference,
+                name,
+                frame_id: None,
+                mode: None,
+            })
+            .await?;
+
+        if let Some(data_id) = &response.data_id {
+            let mut persistent_data_ids = self.persistent_data_ids.lock();
+            if response.can_persist.unwrap_or_default() {
+                persistent_data_ids.insert(data_id.clone());
+            } else {
+                persistent_data_ids.remove(data_id);
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Replaces all data breakpoints.
+    pub async fn set_data_breakpoints(
+        &self,
+        breakpoints: Vec<DataBreakpoint>,
+    ) -> Result<SetDataBreakpointsResponse> {
+        self.require_capability(
+            |capabilities| capabilities.supports_data_breakpoints == Some(true),
+            "data breakpoints",
+        )?;
+
+        let response = self
+            .request::<SetDataBreakpoints>(SetDataBreakpointsArguments {
+                breakpoints: breakpoints.clone(),
+            })
+            .await?;
+        *self.data_breakpoints.lock() = breakpoints;
+
+        Ok(response)
+    }
+
+    /// The data breakpoints that are set, which are only valid for this debug session
+    /// unless their `dataId` can persist.
+    pub fn data_breakpoints(&self) -> Vec<DataBreakpoint> {
+        self.data_breakpoints.lock().clone()
+    }
+
+    /// Drops the data breakpoints whose `dataId` the adapter can't use in another session.
+    fn forget_session_data_breakpoints(&self) {
+        let persistent_data_ids = self.persistent_data_ids.lock();
+        self.data_breakpoints
+            .lock()
+            .retain(|breakpoint| persistent_data_ids.contains(&breakpoint.data_id));
+    }
+
+    pub async fn pause(&self, thread_id: u64) -> Result<()> {
+        self.request::<Pause>(PauseArguments { thread_id }).await
+    }
+
+    /// Runs a request of the session in the background and logs the error it fails with,
+    /// so e.g. the handler of a toolbar button can detach the returned task.
+    fn spawn_logged<T: Send + 'static>(
+        future: impl Future<Output = Result<T>> + Send + 'static,
+        cx: &AppContext,
+    ) -> Task<Result<T>> {
+        cx.background_executor().spawn(async move {
+            let result = future.await;
+            result.as_ref().log_err();
+            result
+        })
+    }
+
+    pub fn pause_task(self: &Arc<Self>, thread_id: u64, cx: &AppContext) -> Task<Result<()>> {
+        let this = self.clone();
+        Self::spawn_logged(async move { this.pause(thread_id).await }, cx)
+    }
+
+    pub fn stop_all_threads_task(self: &Arc<Self>, cx: &AppContext) -> Task<Result<()>> {
+        let this = self.clone();
+        Self::spawn_logged(async move { this.stop_all_threads().await }, cx)
+    }
+
+    pub fn continue_thread_task(
+        self: &Arc<Self>,
+        thread_id: u64,
+        cx: &AppContext,
+    ) -> Task<Result<()>> {
+        let this = self.clone();
+        Self::spawn_logged(async move { this.continue_thread(thread_id).await }, cx)
+    }
+
+    pub fn step_over_task(
+        self: &Arc<Self>,
+        thread_id: u64,
+        granularity: SteppingGranularity,
+        cx: &AppContext,
+    ) -> Task<Result<()>> {
+        let this = self.clone();
+        Self::spawn_logged(
+            async move { this.step_over(thread_id, granularity).await },
+            cx,
+        )
+    }
+
+    pub fn step_in_task(
+        self: &Arc<Self>,
+        thread_id: u64,
+        granularity: SteppingGranularity,
+        cx: &AppContext,
+    ) -> Task<Result<()>> {
+        let this = self.clone();
+        Self::spawn_logged(
+            async move { this.step_in(thread_id, granularity).await },
+            cx,
+        )
+    }
+
+    pub fn step_out_task(
+        self: &Arc<Self>,
+        thread_id: u64,
+        granularity: SteppingGranularity,
+        cx: &AppContext,
+    ) -> Task<Result<()>> {
+        let this = self.clone();
+        Self::spawn_logged(
+            async move { this.step_out(thread_id, granularity).await },
+            cx,
+        )
+    }
+
+    pub async fn continue_thread(&self, thread_id: u64) -> Result<()> {
+        let thread_lock = self.thread_lock(thread_id);
+        let _guard = thread_lock.lock().await;
+
+        self.send_continue(thread_id, None).await
+    }
+
+    /// Resumes only the given thread (if the adapter supports that) and waits for its
+    /// next stop, returning why it stopped.
+    pub async fn continue_thread_and_wait(
+        &self,
+        thread_id: u64,
+        timeout: Duration,
+    ) -> Result<StoppedEventReason> {
+        let thread_lock = self.thread_lock(thread_id);
+        let _guard = thread_lock.lock().await;
+
+        let single_thread = self
+            .has_capability(|capabilities| {
+                capabilities.supports_single_thread_execution_requests == Some(true)
+            })
+            .then_some(true);
+
+        let stopped = self.wait_for_stop(thread_id);
+        self.send_continue(thread_id, single_thread).await?;
+        let event = self.await_stop(thread_id, stopped, timeout).await?;
+
+        Ok(event.reason)
+    }
+
+    /// Sends the `continue` request. The thread is marked as running before the request
+    /// is sent, as the event loop may handle its next stop before the response arrives.
+    /// Threads that stopped in the meantime are left stopped.
+    async fn send_continue(&self, thread_id: u64, single_thread: Option<bool>) -> Result<()> {
+        let (previous, stop_counts) = {
+            let mut thread_states = self.thread_states();
+            let thread_state = thread_states.entry(thread_id).or_default();
+            let previous = (
+                thread_state.status,
+                thread_state.stop_reason.clone(),
+                thread_state.hit_breakpoint_ids.clone(),
+            );
+            thread_state.continued();
+            let stop_counts = thread_states
+                .iter()
+                .map(|(thread_id, thread_state)| (*thread_id, thread_state.stop_count))
+                .collect::<HashMap<_, _>>();
+            (previous, stop_counts)
+        };
+        let has_not_stopped_since = |thread_id: &u64, thread_state: &ThreadState| {
+            stop_counts.get(thread_id) == Some(&thread_state.stop_count)
+        };
+
+        let response = self
+            .request::<Continue>(ContinueArguments {
+                thread_id,
+                single_thread,
+            })
+            .await;
+
+        let mut thread_states = self.thread_states();
+        match response {
+            // an omitted `allThreadsContinued` means that all threads continued
+            Ok(response) if response.all_threads_continued.unwrap_or(true) => {
+                for (thread_id, thread_state) in thread_states.iter_mut() {
+                    if has_not_stopped_since(thread_id, thread_state) {
+                        thread_state.continued();
+                    }
+                }
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+            Err(error) => {
+                if let Some(thread_state) = thread_states
+                    .get_mut(&thread_id)
+                    .filter(|thread_state| has_not_stopped_since(&thread_id, thread_state))
+                {
+                    (
+                        thread_state.status,
+                        thread_state.stop_reason,
+                        thread_state.hit_breakpoint_ids,
+                    ) = previous;
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// The granularity to send with a step, which is left out for adapters that don't
+    /// support choosing it.
+    fn stepping_granularity(
+        &self,
+        granularity: SteppingGranularity,
+    ) -> Option<SteppingGranularity> {
+        self.has_capability(|capabilities| capabilities.supports_stepping_granularity == Some(true))
+            .then_some(granularity)
+    }
+
+    pub async fn step_over(&self, thread_id: u64, granularity: SteppingGranularity) -> Result<()> {
+        self.run_until_stopped(
+            thread_id,
+            self.request::<Next>(NextArguments {
+                thread_id,
+                single_thread: None,
+                granularity: self.stepping_granularity(granularity),
+            }),
+        )
+        .await
+    }
+
+    pub async fn step_in(&self, thread_id: u64, granularity: SteppingGranularity) -> Result<()> {
+        self.run_until_stopped(
+            thread_id,
+            self.request::<StepIn>(StepInArguments {
+                thread_id,
+                single_thread: None,
+                target_id: None,
+                granularity: self.stepping_granularity(granularity),
+            }),
+        )
+        .await
+    }
+
+    pub async fn step_out(&self, thread_id: u64, granularity: SteppingGranularity) -> Result<()> {
+        self.run_until_stopped(
+            thread_id,
+            self.request::<StepOut>(StepOutArguments {
+                thread_id,
+                single_thread: None,
+                granularity: self.stepping_granularity(granularity),
+            }),
+        )
+        .await
+    }
+
+    pub async fn step_back(&self, thread_id: u64, granularity: SteppingGranularity) -> Result<()> {
+        self.require_capability(
+            |capabilities| capabilities.supports_step_back == Some(true),
+            "stepping back",
+        )?;
+
+        self.run_until_stopped(
+            thread_id,
+            self.request::<StepBack>(StepBackArguments {
+                thread_id,
+                single_thread: None,
+                granularity: self.stepping_granularity(granularity),
+            }),
+        )
+        .await
+    }
+
+    /// Restarts a stack frame of a stopped thread, which stops again at its start.
+    pub async fn restart_frame(&self, thread_id: u64, stack_frame_id: u64) -> Result<()> {
+        self.require_capability(
+            |capabilities| capabilities.supports_restart_frame == Some(true),
+            "restarting frames",
+        )?;
+
+        self.run_until_stopped(
+            thread_id,
+            self.request::<RestartFrame>(RestartFrameArguments {
+                frame_id: stack_frame_id,
+            }),
+        )
+        .await
+    }
+
+    /// Pauses every running thread and waits until all of them have stopped, e.g. for
+    /// the pause button of the toolbar.
+    ///
+    /// Threads are paused one at a time, so threads that were already stopped by an
+    /// earlier `stopped` event with `allThreadsStopped` set are not paused again. Most
+    /// adapters stop all threads at once, so usually a single `pause` is sent.
+    pub async fn stop_all_threads(&self) -> Result<()> {
+        let mut thread_ids = self
+            .thread_states()
+            .iter()
+            .filter(|(_, thread_state)| thread_state.status == ThreadStatus::Running)
+            .map(|(thread_id, _)| *thread_id)
+            .collect::<Vec<_>>();
+        thread_ids.sort_unstable();
+
+        for thread_id in thread_ids {
+            if self.thread_state_by_id(thread_id).status != ThreadStatus::Running {
+                continue;
+            }
+
+            let thread_lock = self.thread_lock(thread_id);
+            let _guard = thread_lock.lock().await;
+
+            let stopped = self.wait_for_stop(thread_id);
+            self.pause(thread_id).await?;
+            self.await_stop(thread_id, stopped, THREAD_STOP_TIMEOUT)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches all sources the adapter loaded, replacing the ones collected from
+    /// `loadedSource` events.
+    pub async fn loaded_sources(&self) -> Result<Vec<Source>> {
+        self.require_capability(
+            |capabilities| capabilities.supports_loaded_sources_request == Some(true),
+            "loaded sources",
+        )?;
+
+        let sources = self
+            .request::<LoadedSources>(LoadedSourcesArguments)
+            .await?
+            .sources;
+
+        self.remember_sources(&sources);
+        *self.loaded_sources.lock() = sources.clone();
+        self.notify(ClientNotification::LoadedSourcesUpdated);
+
+        Ok(sources)
+    }
+
+    /// Fetches the content of a source that has no local file, e.g. evaluated code or
+    /// decompiled output, by its `sourceReference`.
+    ///
+    /// The content is cached until the adapter reports that the source changed.
+    pub async fn source(&self, source_reference: u64) -> Result<SourceResponse> {
+        if let Some(response) = self.source_contents.lock().get(&source_reference) {
+            return Ok(response.clone());
+        }
+
+        let response = self
+            .request::<dap_types::requests::Source>(SourceArguments {
+                source: self.source_by_reference(source_reference),
+                source_reference,
+            })
+            .await?;
+
+        self.source_contents
+            .lock()
+            .insert(source_reference, response.clone());
+
+        Ok(response)
+    }
+
+    /// Whether the memory at the `memory_reference` of e.g. a variable can be inspected
+    /// using [`Self::read_memory`].
+    pub fn supports_memory(&self) -> bool {
+        self.has_capability(|capabilities| capabilities.supports_read_memory_request == Some(true))
+    }
+
+    /// Reads `count` bytes of memory at the `memory_reference` of e.g. a variable.
+    pub async fn read_memory(
+        &self,
+        memory_reference: String,
+        offset: Option<i64>,
+        count: u64,
+    ) -> Result<MemoryContents> {
+        self.require_capability(
+            |capabilities| capabilities.supports_read_memory_request == Some(true),
+            "reading memory",
+        )?;
+
+        let response = self
+            .request::<ReadMemory>(ReadMemoryArguments {
+                memory_reference,
+                offset: memory_offset(offset)?,
+                count,
+            })
+            .await?;
+
+        let data = match response.data {
+            Some(data) => base64::decode(data).context("invalid base64 memory data")?,
+            None => Vec::new(),
+        };
+
+        Ok(MemoryContents {
+            address: response.address,
+            data,
+            unreadable_bytes: response.unreadable_bytes.unwrap_or_default(),
+        })
+    }
+
+    /// Registers a new listener for changes of the memory at `memory_reference`, e.g. for
+    /// a memory view to read the changed bytes again.
+    pub fn memory_updates(&self, memory_reference: impl Into<String>) -> Receiver<MemoryUpdate> {
+        let (tx, rx) = unbounded();
+        self.memory_subscribers
+            .lock()
+            .entry(memory_reference.into())
+            .or_default()
+            .push(tx);
+        rx
+    }
+
+    fn handle_memory_event(&self, event: &MemoryEvent) {
+        let update = MemoryUpdate {
+            offset: event.offset as i64,
+            count: event.count,
+        };
+        let mut memory_subscribers = self.memory_subscribers.lock();
+        if let Some(subscribers) = memory_subscribers.get_mut(&event.memory_reference) {
+            subscribers.retain(|subscriber| subscriber.try_send(update).is_ok());
+            if subscribers.is_empty() {
+                memory_subscribers.remove(&event.memory_reference);
+            }
+        }
+    }
+
+    /// Disassembles `instruction_count` instructions at the `memory_reference` of e.g. a
+    /// stack frame, `offset` bytes and then `instruction_offset` instructions away from it.
+    /// Negative offsets disassemble before the reference, e.g. to show the instructions
+    /// leading up to the current one.
+    ///
+    /// Adapters should return exactly `instruction_count` instructions, but may return less
+    /// when e.g. the memory isn't mapped.
+    pub async fn disassemble(
+        &self,
+        memory_reference: String,
+        offset: Option<i64>,
+        instruction_offset: Option<i64>,
+        instruction_count: u64,
+    ) -> Result<Vec<DisassembledInstruction>> {
+        self.require_capability(
+            |capabilities| capabilities.supports_disassemble_request == Some(true),
+            "disassembling",
+        )?;
+
+        let mut instructions = self
+            .request::<DisassembleWithSignedOffsets>(SignedDisassembleArguments {
+                memory_reference,
+                offset,
+                instruction_offset,
+                instruction_count,
+                resolve_symbols: Some(true),
+            })
+            .await?
+            .instructions;
+
+        if instructions.len() as u64 != instruction_count {
+            log::debug!(
+                "debug adapter returned {} instructions instead of {instruction_count}",
+                instructions.len()
+            );
+            instructions.truncate(instruction_count as usize);
+        }
+
+        self.remember_sources(
+            instructions
+                .iter()
+                .filter_map(|instruction| instruction.location.as_ref()),
+        );
+
+        Ok(instructions)
+    }
+
+    /// Disassembles memory, grouping the instructions under the source lines they were
+    /// compiled from, for a view that interleaves source and instructions.
+    pub async fn disassemble_by_line(
+        &self,
+        memory_reference: String,
+        offset: Option<i64>,
+        instruction_offset: Option<i64>,
+        instruction_count: u64,
+    ) -> Result<Vec<DisassemblyGroup>> {
+        let instructions = self
+            .disassemble(
+                memory_reference,
+                offset,
+                instruction_offset,
+                instruction_count,
+            )
+            .await?;
+
+        let mut groups = Vec::<DisassemblyGroup>::new();
+        let mut source = None;
+        let mut line = None;
+        for instruction in instructions {
+            // adapters omit the location and line when they match the previous instruction
+            if let Some(location) = &instruction.location {
+                source = Some(self.resolve_source(location));
+            }
+            if instruction.line.is_some() {
+                line = instruction.line;
+            }
+
+            let same_source = match (
+                groups.last().and_then(|group| group.source.as_ref()),
+                &source,
+            ) {
+                (Some(group_source), Some(source)) => is_same_source(group_source, source),
+                (None, None) => true,
+                _ => false,
+            };
+            match groups.last_mut() {
+                Some(group) if same_source && group.line == line => {
+                    group.end_line = group.end_line.max(instruction.end_line);
+                    group.instructions.push(instruction);
+                }
+                _ => groups.push(DisassemblyGroup {
+                    source: source.clone(),
+                    line,
+                    end_line: instruction.end_line,
+                    instructions: vec![instruction],
+                }),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Writes `data` to the memory at `memory_reference`.
+    ///
+    /// With `allow_partial`, the adapter may write fewer bytes than requested, which is
+    /// reported by the response's `bytes_written`.
+    pub async fn write_memory(
+        &self,
+        memory_reference: String,
+        offset: Option<i64>,
+        data: Vec<u8>,
+        allow_partial: bool,
+    ) -> Result<WriteMemoryResponse> {
+        self.require_capability(
+            |capabilities| capabilities.supports_write_memory_request == Some(true),
+            "writing memory",
+        )?;
+
+        self.request::<WriteMemory>(WriteMemoryArguments {
+            memory_reference,
+            offset: memory_offset(offset)?,
+            allow_partial: Some(allow_partial),
+            data: base64::encode(data),
+        })
+        .await
+    }
+
+    /// Returns the locations execution can jump to for the given source line.
+    pub async fn goto_targets(&self, source: Source, line: u64) -> Result<Vec<GotoTarget>> {
+        self.require_capability(
+            |capabilities| capabilities.supports_goto_targets_request == Some(true),
+            "goto targets",
+        )?;
+
+        Ok(self
+            .request::<GotoTargets>(GotoTargetsArguments {
+                source,
+                line,
+                column: None,
+            })
+            .await?
+            .targets)
+    }
+
+    /// Moves the execution of a stopped thread to one of its [`Self::goto_targets`].
+    ///
+    /// The adapter reports the jump with a `stopped` event, after which the stack of
+    /// the thread is refreshed.
+    pub async fn goto(&self, thread_id: u64, target_id: u64) -> Result<()> {
+        self.require_capability(
+            |capabilities| capabilities.supports_goto_targets_request == Some(true),
+            "goto targets",
+        )?;
+
+        self.run_until_stopped(
+            thread_id,
+            self.request::<Goto>(GotoArguments {
+                thread_id,
+                target_id,
+            }),
+        )
+        .await
+    }
+
+    pub async fn evaluate(
+        &self,
+        expression: String,
+        frame_id: Option<u64>,
+        context: Option<EvaluateArgumentsContext>,
+    ) -> Result<EvaluateResponse> {
+        self.request::<Evaluate>(EvaluateArguments {
+            expression,
+            frame_id,
+            context,
+            format: None,
+        })
+        .await
+    }
+
+    /// Runs a command typed into the debug console: it's added to the REPL history and
+    /// evaluated in the `repl` context, and the console shows the command followed by
+    /// its result or the error it failed with.
+    pub async fn evaluate_repl_command(
+        &self,
+        expression: String,
+        frame_id: Option<u64>,
+    ) -> Result<EvaluateResponse> {
+        {
+            let mut repl_history = self.repl_history.lock();
+            if repl_history.last() != Some(&expression) {
+                repl_history.push(expression.clone());
+            }
+        }
+        self.push_console_output(ConsoleOutputKind::Command, format!("> {expression}\n"), 0);
+
+        let result = self
+            .evaluate(expression, frame_id, Some(EvaluateArgumentsContext::Repl))
+            .await;
+        match &result {
+            Ok(response) => self.push_console_output(
+                ConsoleOutputKind::Result,
+                format!("{}\n", response.result),
+                response.variables_reference,
+            ),
+            Err(error) => {
+                self.push_console_output(ConsoleOutputKind::Stderr, format!("{error:#}\n"), 0)
+            }
+        }
+
+        result
+    }
+
+    /// The commands run in the debug console, oldest first, without consecutive repeats.
+    pub fn repl_history(&self) -> Vec<String> {
+        self.repl_history.lock().clone()
+    }
+
+    /// Adds output of the client, rather than the adapter, to the debug console.
+    fn push_console_output(
+        &self,
+        kind: ConsoleOutputKind,
+        output: String,
+        variables_reference: u64,
+    ) {
+        let category = if kind == ConsoleOutputKind::Stderr {
+            OutputEventCategory::Stderr
+        } else {
+            OutputEventCategory::Console
+        };
+        self.append_console_output(ConsoleOutput {
+            kind,
+            ..ConsoleOutput::new(OutputEvent {
+                category: Some(category),
+                output,
+                group: None,
+                variables_reference: (variables_reference > 0).then_some(variables_reference),
+                source: None,
+                line: None,
+                column: None,
+                data: None,
+            })
+        });
+    }
+
+    /// Evaluates an expression, giving up after `timeout` so an expression that hangs the
+    /// adapter (e.g. a call to a slow function) can't block e.g. the watches.
+    ///
+    /// When the evaluation times out, it's cancelled if the adapter supports it.
+    pub async fn evaluate_with_timeout(
+        &self,
+        expression: String,
+        frame_id: Option<u64>,
+        context: Option<EvaluateArgumentsContext>,
+        timeout: Duration,
+    ) -> Result<EvaluateResponse> {
+        let seq = self.next_sequence_id();
+        let evaluate = self.request_with_seq::<Evaluate>(
+            seq,
+            EvaluateArguments {
+                expression: expression.clone(),
+                frame_id,
+                context,
+                format: None,
+            },
+        );
+
+        let result = smol::future::or(async { Some(evaluate.await) }, async {
+            self.executor.timer(timeout).await;
+            None
+        })
+        .await;
+
+        match result {
+            Some(result) => result,
+            None => {
+                self.cancel_request(seq).await;
+
+                let error = anyhow!(
+                    "evaluating `{expression}` timed out after {}ms",
+                    timeout.as_millis()
+                );
+                *self.last_error.lock() = Some(error.to_string());
+                Err(error)
+            }
+        }
+    }
+
+    /// Stops waiting for the response of a request, and asks the adapter to cancel it
+    /// if it supports that.
+    async fn cancel_request(&self, seq: u64) {
+        self.remove_pending_request(seq);
+        if self.has_capability(|capabilities| capabilities.supports_cancel_request == Some(true)) {
+            self.request::<Cancel>(CancelArguments {
+                request_id: Some(seq),
+                progress_id: None,
+            })
+            .await
+            .log_err();
+        }
+    }
+
+    /// Evaluates the expression the user hovers in the editor, in the selected stack
+    /// frame of the current thread.
+    ///
+    /// Returns `None` when the current thread isn't stopped, so a running debuggee isn't
+    /// spammed with evaluations, and when the hover was superseded by another one or
+    /// cancelled with [`Self::cancel_hover`] while it was settling or being evaluated.
+    pub async fn evaluate_hover(&self, expression: String) -> Result<Option<ExpandableValue>> {
+        self.cancel_hover().await;
+        let generation = self.hover_generation.load(Ordering::SeqCst);
+
+        self.executor.timer(HOVER_DEBOUNCE).await;
+        if self.hover_generation.load(Ordering::SeqCst) != generation {
+            return Ok(None);
+        }
+
+        let Some(frame_id) = self.current_thread_id().and_then(|thread_id| {
+            let thread_states = self.thread_states();
+            let thread_state = thread_states.get(&thread_id)?;
+            (thread_state.status == ThreadStatus::Stopped)
+                .then_some(thread_state.current_stack_frame_id)
+                .flatten()
+        }) else {
+            return Ok(None);
+        };
+
+        let seq = self.next_sequence_id();
+        *self.hover_request.lock() = Some(seq);
+        let result = self
+            .request_with_seq::<Evaluate>(
+                seq,
+                EvaluateArguments {
+                    expression,
+                    frame_id: Some(frame_id),
+                    context: Some(EvaluateArgumentsContext::Hover),
+                    format: None,
+                },
+            )
+            .await;
+
+        let mut hover_request = self.hover_request.lock();
+        if *hover_request == Some(seq) {
+            *hover_request = None;
+        }
+        if self.hover_generation.load(Ordering::SeqCst) != generation {
+            return Ok(None);
+        }
+        Ok(Some(result?.into()))
+    }
+
+    /// Cancels the pending [`Self::evaluate_hover`], e.g. because the mouse moved away.
+    pub async fn cancel_hover(&self) {
+        self.hover_generation.fetch_add(1, Ordering::SeqCst);
+        let seq = self.hover_request.lock().take();
+        if let Some(seq) = seq {
+            self.cancel_request(seq).await;
+        }
+    }
+
+    /// Evaluates an expression, returning its value together with whether it has
+    /// children that can be fetched with the `variables` request.
+    pub async fn evaluate_expandable(
+        &self,
+        expression: String,
+        frame_id: Option<u64>,
+        context: Option<EvaluateArgumentsContext>,
+    ) -> Result<ExpandableValue> {
+        Ok(self.evaluate(expression, frame_id, context).await?.into())
+    }
+
+    /// Fetches the children of the tree when it is expandable and wasn't expanded yet.
+    /// Their own children are only fetched once they are expanded.
+    pub async fn expand_variable_tree(&self, tree: &mut VariableTree) -> Result<()> {
+        if !tree.is_expandable() || tree.is_expanded() {
+            return Ok(());
+        }
+
+        let variables = self.variables(tree.variables_reference, None).await?;
+        tree.children = Some(
+            variables
+                .into_iter()
+                .map(|variable| {
+                    let children = VariableTree::new(variable.variables_reference);
+                    (variable, children)
+                })
+                .collect(),
+        );
+        Ok(())
+    }
+
+    pub fn add_watch(&self, expression: String) {
+        let mut watches = self.watches.lock();
+        if !watches.iter().any(|watch| watch.expression == expression) {
+            watches.push(Watch::new(expression));
+        }
+    }
+
+    /// Replaces the expression of a watch, keeping its position in the list.
+    pub fn edit_watch(&self, expression: &str, new_expression: String) {
+        let mut watches = self.watches.lock();
+        if watches
+            .iter()
+            .any(|watch| watch.expression == new_expression)
+        {
+            watches.retain(|watch| watch.expression != expression);
+        } else if let Some(watch) = watches
+            .iter_mut()
+            .find(|watch| watch.expression == expression)
+        {
+            *watch = Watch::new(new_expression);
+        }
+    }
+
+    /// Replaces all watches, e.g. with the ones that were persisted for the project.
+    /// Watches that are kept keep the result of their last evaluation.
+    pub fn set_watches(&self, expressions: impl IntoIterator<Item = String>) {
+        let mut watches = self.watches.lock();
+        let mut previous_watches = std::mem::take(&mut *watches);
+        for expression in expressions {
+            if watches.iter().any(|watch| watch.expression == expression) {
+                continue;
+            }
+            let watch = previous_watches
+                .iter()
+                .position(|watch| watch.expression == expression)
+                .map(|ix| previous_watches.swap_remove(ix))
+                .unwrap_or_else(|| Watch::new(expression));
+            watches.push(watch);
+        }
+    }
+
+    pub fn remove_watch(&self, expression: &str) {
+        self.watches
+            .lock()
+            .retain(|watch| watch.expression != expression);
+    }
+
+    /// The expressions that are evaluated by [`Self::evaluate_watches`].
+    pub fn watches(&self) -> Vec<String> {
+        self.watches
+            .lock()
+            .iter()
+            .map(|watch| watch.expression.clone())
+            .collect()
+    }
+
+    /// Evaluates all watch expressions in the context of the given stack frame.
+    pub async fn evaluate_watches(
+        &self,
+        frame_id: Option<u64>,
+    ) -> Vec<(String, Result<ExpandableValue>)> {
+        let expressions = self.watches();
+
+        let mut results = Vec::with_capacity(expressions.len());
+        for expression in expressions {
+            let result = self
+                .evaluate_expandable(
+                    expression.clone(),
+                    frame_id,
+                    Some(EvaluateArgumentsContext::Watch),
+                )
+                .await;
+            results.push((expression, result));
+        }
+
+        let mut watches = self.watches.lock();
+        for (expression, result) in &results {
+            if let Some(watch) = watches
+                .iter_mut()
+                .find(|watch| &watch.expression == expression)
+            {
+                watch.failures = if result.is_ok() {
+                    0
+                } else {
+                    watch.failures + 1
+                };
+                watch.result = Some(
+                    result
+                        .as_ref()
+                        .cloned()
+                        .map_err(|error| format!("{error:#}")),
+                );
+            }
+        }
+
+        results
+    }
+
+    /// The watches with the results of their last evaluation, in the order they were added.
+    pub fn watch_results(&self) -> Vec<WatchResult> {
+        self.watches
+            .lock()
+            .iter()
+            .map(|watch| WatchResult {
+                expression: watch.expression.clone(),
+                result: watch.result.clone(),
+            })
+            .collect()
+    }
+
+    /// Like [`Self::evaluate_watches`], but removes the watches that failed to evaluate
+    /// `failure_threshold` times in a row, e.g. because they went out of scope for good.
+    pub async fn evaluate_watch_removing_failed(
+        &self,
+        frame_id: Option<u64>,
+        failure_threshold: usize,
+    ) -> Vec<(String, Result<ExpandableValue>)> {
+        let mut results = self.evaluate_watches(frame_id).await;
+
+        let mut removed = Vec::new();
+        self.watches.lock().retain(|watch| {
+            let keep = watch.failures < failure_threshold;
+            if !keep {
+                removed.push(watch.expression.clone());
+            }
+            keep
+        });
+        results.retain(|(expression, _)| !removed.contains(expression));
+
+        results
+    }
+
+    /// Terminates the given threads of the debuggee without ending the session.
+    pub async fn terminate_threads(&self, thread_ids: Vec<u64>) -> Result<()> {
+        self.require_capability(
+            |capabilities| capabilities.supports_terminate_threads_request == Some(true),
+            "terminating threads",
+        )?;
+
+        self.request::<TerminateThreads>(TerminateThreadsArguments {
+            thread_ids: Some(thread_ids.clone()),
+        })
+        .await?;
+
+        for thread_id in thread_ids {
+            self.update_thread_state_status(thread_id, ThreadStatus::Ended);
+        }
+        Ok(())
+    }
+
+    /// Fetches the details of the exception `thread_id` is stopped on.
+    pub async fn exception_info(&self, thread_id: u64) -> Result<ExceptionInfoResponse> {
+        self.require_capability(
+            |capabilities| capabilities.supports_exception_info_request == Some(true),
+            "exception info",
+        )?;
+
+        self.request::<ExceptionInfo>(ExceptionInfoArguments { thread_id })
+            .await
+    }
+
+    /// Fetches the completions for the debug console input `text` at the given
+    /// (1-based) `column`.
+    pub async fn completions(
+        &self,
+        text: String,
+        column: u64,
+        frame_id: Option<u64>,
+    ) -> Result<Vec<CompletionItem>> {
+        self.require_capability(
+            |capabilities| capabilities.supports_completions_request == Some(true),
+            "completions",
+        )?;
+
+        Ok(self
+            .request::<Completions>(CompletionsArguments {
+                frame_id,
+                text,
+                column,
+                line: None,
+            })
+            .await?
+            .targets)
+    }
+
+    /// Whether typing the end of `text` should trigger a completion request, based on
+    /// the trigger characters of the adapter (`.` when it has none).
+    pub fn is_completion_trigger(&self, text: &str) -> bool {
+        let capabilities = self.capabilities.lock();
+        let Some(capabilities) = capabilities
+            .as_ref()
+            .filter(|capabilities| capabilities.supports_completions_request == Some(true))
+        else {
+            return false;
+        };
+
+        match &capabilities.completion_trigger_characters {
+            Some(trigger_characters) => trigger_characters
+                .iter()
+                .any(|trigger| !trigger.is_empty() && text.ends_with(trigger.as_str())),
+            None => text.ends_with('.'),
+        }
+    }
+
+    pub async fn threads(&self) -> Result<Vec<Thread>> {
+        let threads = self.request::<Threads>(()).await?.threads;
+
+        let mut thread_states = self.thread_states();
+        for thread in &threads {
+            thread_states.entry(thread.id).or_default().name = Some(thread.name.clone());
+        }
+        drop(thread_states);
+        self.notify(ClientNotification::ThreadsUpdated);
+
+        Ok(threads)
+    }
+
+    /// Restarts the debug session with the given launch or attach arguments.
+    ///
+    /// The thread and frame that were selected are selected again once a thread with the
+    /// same name stops in the same function. Otherwise the defaults are selected.
+    pub async fn restart_session_preserving_selection(&self, args: Option<Value>) -> Result<()> {
+        self.require_capability(
+            |capabilities| capabilities.supports_restart_request == Some(true),
+            "restarting",
+        )?;
+
+        let selection = self.current_thread_id().and_then(|thread_id| {
+            let thread_state = self.thread_state_by_id(thread_id);
+            let stack_frame_name = thread_state
+                .stack_frames
+                .iter()
+                .find(|frame| Some(frame.id) == thread_state.current_stack_frame_id)
+                .map(|frame| frame.name.clone());
+            Some(PendingSelection {
+                thread_name: thread_state.name?,
+                top_frame_name: thread_state.stack_frames.first()?.name.clone(),
+                stack_frame_name,
+            })
+        });
+
+        self.request::<Restart>(RestartArguments {
+            raw: match args {
+                Some(args) => json!({ "arguments": args }),
+                None => json!({}),
+            },
+        })
+        .await?;
+
+        self.thread_states().clear();
+        self.set_current_thread_id(None);
+        self.forget_session_data_breakpoints();
+        *self.pending_selection.lock() = selection;
+
+        self.resend_breakpoints().await?;
+
+        // fetch the thread names, so the selection can be matched once a thread stops
+        self.threads().await?;
+
+        Ok(())
+    }
+
+    /// Selects the thread and frame that were selected before a restart, if the given
+    /// thread matches them.
+    fn restore_pending_selection(&self, thread_id: u64, thread_state: &mut ThreadState) {
+        let mut pending_selection = self.pending_selection.lock();
+        let Some(selection) = pending_selection.as_ref() else {
+            return;
+        };
+
+        let matches = thread_state.name.as_ref() == Some(&selection.thread_name)
+            && thread_state.stack_frames.first().map(|frame| &frame.name)
+                == Some(&selection.top_frame_name);
+        if !matches {
+            return;
+        }
+
+        if let Some(stack_frame) = thread_state
+            .stack_frames
+            .iter()
+            .find(|frame| Some(&frame.name) == selection.stack_frame_name.as_ref())
+        {
+            thread_state.current_stack_frame_id = Some(stack_frame.id);
+        }
+        *self.current_thread_id.lock() = Some(thread_id);
+        *pending_selection = None;
+    }
+
+    pub async fn stack_trace(&self, thread_id: u64) -> Result<Vec<StackFrame>> {
+        let response = self
+            .request::<StackTrace>(StackTraceArguments {
+                thread_id,
+                start_frame: None,
+                levels: None,
+                format: None,
+            })
+            .await?;
+
+        self.remember_sources(
+            response
+                .stack_frames
+                .iter()
+                .filter_map(|stack_frame| stack_frame.source.as_ref()),
+        );
+
+        Ok(response.stack_frames)
+    }
+
+    /// Exports the variables of a stack frame as JSON, keyed by scope and variable name,
+    /// e.g. to copy them or to compare snapshots of them.
+    ///
+    /// Children are fetched when they weren't yet, up to a fixed depth. A variable that
+    /// refers back to one of its ancestors isn't expanded.
+    pub async fn export_variables_json(&self, thread_id: u64, frame_id: u64) -> Result<Value> {
+        let cached_scopes = self
+            .thread_states()
+            .get(&thread_id)
+            .and_then(|thread_state| thread_state.scopes.get(&frame_id).cloned());
+        let scopes = match cached_scopes {
+            Some(scopes) => scopes,
+            None => self.scopes(frame_id).await?,
+        };
+
+        let mut exported = serde_json::Map::new();
+        for scope in scopes {
+            let variables = self
+                .export_variables(
+                    thread_id,
+                    scope.variables_reference,
+                    EXPORTED_VARIABLES_DEPTH,
+                    &mut Vec::new(),
+                )
+                .await?;
+            exported.insert(scope.name, variables);
+        }
+        Ok(Value::Object(exported))
+    }
+
+    fn export_variables<'a>(
+        &'a self,
+        thread_id: u64,
+        variables_reference: u64,
+        depth: usize,
+        ancestors: &'a mut Vec<u64>,
+    ) -> BoxFuture<'a, Result<Value>> {
+        async move {
+            let cached_variables = self
+                .thread_states()
+                .get(&thread_id)
+                .and_then(|thread_state| thread_state.variables.get(&variables_reference).cloned());
+            let variables = match cached_variables {
+                Some(variables) => variables,
+                None => self.variables(variables_reference, None).await?,
+            };
+
+            ancestors.push(variables_reference);
+            let mut exported = serde_json::Map::new();
+            for variable in variables {
+                let mut entry = serde_json::Map::new();
+                entry.insert("value".into(), variable.value.into());
+                if let Some(type_) = variable.type_ {
+                    entry.insert("type".into(), type_.into());
+                }
+
+                let reference = variable.variables_reference;
+                if reference > 0 && depth > 1 && !ancestors.contains(&reference) {
+                    let children = self
+                        .export_variables(thread_id, reference, depth - 1, ancestors)
+                        .await?;
+                    entry.insert("variables".into(), children);
+                }
+                exported.insert(variable.name, Value::Object(entry));
+            }
+            ancestors.pop();
+
+            Ok(Value::Object(exported))
+        }
+        .boxed()
+    }
+
+    pub async fn scopes(&self, frame_id: u64) -> Result<Vec<Scope>> {
+        Ok(self
+            .request::<Scopes>(ScopesArguments { frame_id })
+            .await?
+            .scopes)
+    }
+
+    /// Fetches the child variables of a `variables_reference`.
+    ///
+    /// When no `format` is given, the format the parent variable was fetched with is used,
+    /// so an expanded subtree renders consistently.
+    pub async fn variables(
+        &self,
+        variables_reference: u64,
+        format: Option<ValueFormat>,
+    ) -> Result<Vec<Variable>> {
+        self.fetch_variables(variables_reference, None, None, format)
+            .await
+    }
+
+    /// Fetches `count` indexed children of a `variables_reference` starting at `start`,
+    /// e.g. the next page of the elements of a large array as it is scrolled through.
+    pub async fn variables_page(
+        &self,
+        variables_reference: u64,
+        start: u64,
+        count: u64,
+    ) -> Result<Vec<Variable>> {
+        self.fetch_variables(
+            variables_reference,
+            Some(VariablesArgumentsFilter::Indexed),
+            Some((start, count)),
+            None,
+        )
+        .await
+    }
+
+    /// Fetches the children of a variable. When it has more indexed children than fit
+    /// on a page, only its named children and the first page of indexed children are
+    /// fetched, the others can be fetched with [`Self::variables_page`].
+    pub async fn child_variables(&self, variable: &Variable) -> Result<Vec<Variable>> {
+        let reference = variable.variables_reference;
+        if variable.indexed_variables.unwrap_or_default() <= VARIABLES_PAGE_SIZE {
+            return self.variables(reference, None).await;
+        }
+
+        let mut variables = self
+            .fetch_variables(reference, Some(VariablesArgumentsFilter::Named), None, None)
+            .await?;
+        variables.extend(
+            self.variables_page(reference, 0, VARIABLES_PAGE_SIZE)
+                .await?,
+        );
+        Ok(variables)
+    }
+
+    async fn fetch_variables(
+        &self,
+        variables_reference: u64,
+        filter: Option<VariablesArgumentsFilter>,
+        page: Option<(u64, u64)>, // start, count
+        format: Option<ValueFormat>,
+    ) -> Result<Vec<Variable>> {
+        let format = {
+            let mut variable_formats = self.variable_formats.lock();
+            match format {
+                Some(format) => {
+                    variable_formats.insert(variables_reference, format.clone());
+                    Some(format)
+                }
+                None => variable_formats.get(&variables_reference).cloned(),
+            }
+        };
+
+        let variables = self
+            .request::<VariablesWithLocations>(VariablesArguments {
+                variables_reference,
+                filter,
+                start: page.map(|(start, _)| start),
+                count: page.map(|(_, count)| count),
+                format: format.clone(),
+            })
+            .await?
+            .variables;
+
+        let variables = {
+            let mut declaration_locations = self.declaration_locations.lock();
+            // a part of the children leaves the locations of the other children alone
+            if filter.is_none() && page.is_none() {
+                declaration_locations.retain(|(reference, _), _| *reference != variables_reference);
+            }
+            variables
+                .into_iter()
+                .map(|located| {
+                    if let Some(location_reference) = located
+                        .declaration_location_reference
+                        .filter(|reference| *reference > 0)
+                    {
+                        declaration_locations.insert(
+                            (variables_reference, located.variable.name.clone()),
+                            location_reference,
+                        );
+                    }
+                    located.variable
+                })
+                .collect::<Vec<_>>()
+        };
+
+        if let Some(format) = format {
+            let mut variable_formats = self.variable_formats.lock();
+            for variable in &variables {
+                if variable.variables_reference > 0 {
+                    variable_formats.insert(variable.variables_reference, format.clone());
+                }
+            }
+        }
+
+        Ok(variables)
+    }
+
+    /// Resolves where the variable `name`, one of the fetched children of
+    /// `variables_reference`, is declared.
+    ///
+    /// Returns `None` when the adapter didn't send a declaration location for it.
+    pub async fn variable_declaration_location(
+        &self,
+        variables_reference: u64,
+        name: &str,
+    ) -> Result<Option<DeclarationLocation>> {
+        let Some(location_reference) = self
+            .declaration_locations
+            .lock()
+            .get(&(variables_reference, name.to_owned()))
+            .copied()
+        else {
+            return Ok(None);
+        };
+
+        let location = self
+            .request::<Locations>(LocationsArguments { location_reference })
+            .await?;
+        let source = self.resolve_source(&location.source);
+        let content = match source.source_reference.filter(|reference| *reference > 0) {
+            Some(source_reference) if source.path.is_none() => {
+                Some(self.source(source_reference).await?.content)
+            }
+            _ => None,
+        };
+
+        Ok(Some(DeclarationLocation {
+            source,
+            line: location.line,
+            column: location.column,
+            content,
+        }))
+    }
+
+    /// Sets the value of the variable at the dotted `path` (e.g. `user.address.city`),
+    /// resolved from the children of `variables_reference`, usually those of a scope.
+    pub async fn set_variable_by_path(
+        &self,
+        variables_reference: u64,
+        path: &str,
+        value: String,
+    ) -> Result<SetVariableResponse> {
+        self.require_capability(
+            |capabilities| capabilities.supports_set_variable == Some(true),
+            "setting variables",
+        )?;
+
+        let mut segments = path.split('.').peekable();
+        let mut parent_reference = variables_reference;
+        while let Some(name) = segments.next() {
+            let variable = self
+                .variables(parent_reference, None)
+                .await?
+                .into_iter()
+                .find(|variable| variable.name == name)
+                .with_context(|| format!("no variable `{name}` in `{path}`"))?;
+
+            if segments.peek().is_some() {
+                if variable.variables_reference == 0 {
+                    return Err(anyhow!("variable `{name}` in `{path}` has no children"));
+                }
+                parent_reference = variable.variables_reference;
+                continue;
+            }
+
+            let read_only = variable
+                .presentation_hint
+                .as_ref()
+                .and_then(|hint| hint.attributes.as_ref())
+                .is_some_and(|attributes| {
+                    attributes.contains(&VariablePresentationHintAttributes::ReadOnly)
+                });
+            if read_only {
+                return Err(anyhow!("variable `{path}` is read-only"));
+            }
+
+            return self
+                .request::<SetVariable>(SetVariableArguments {
+                    variables_reference: parent_reference,
+                    name: variable.name,
+                    value,
+                    format: None,
+                })
+                .await;
+        }
+
+        Err(anyhow!("empty variable path"))
+    }
+
+    /// Re-fetches the stack frames of a stopped thread, re-selects its top frame
+    /// (unless the current selection is still valid) and fetches the scopes of the
+    /// selected frame.
+    ///
+    /// When the thread is the current one, the watches are re-evaluated in the
+    /// selected frame.
+    pub async fn refresh_thread(&self, thread_id: u64) -> Result<()> {
+        let status = self
+            .thread_states()
+            .get(&thread_id)
+            .map(|thread_state| thread_state.status);
+        if status != Some(ThreadStatus::Stopped) {
+            return Err(anyhow!("thread {thread_id} is not stopped"));
+        }
+
+        let stack_frames = self.stack_trace(thread_id).await?;
+
+        let current_stack_frame_id = {
+            let mut thread_states = self.thread_states();
+            let thread_state = thread_states.entry(thread_id).or_default();
+
+            let current_stack_frame_id = thread_state
+                .current_stack_frame_id
+                .filter(|id| stack_frames.iter().any(|frame| frame.id == *id))
+                .or_else(|| stack_frames.first().map(|frame| frame.id));
+
+            thread_state.stack_frames = stack_frames;
+            thread_state.current_stack_frame_id = current_stack_frame_id;
+            thread_state.scopes.clear();
+            thread_state.variables.clear();
+            self.restore_pending_selection(thread_id, thread_state);
+
+            thread_state.current_stack_frame_id
+        };
+        self.variable_formats.lock().clear();
+
+        self.notify(ClientNotification::StackFramesUpdated { thread_id });
+
+        let Some(stack_frame_id) = current_stack_frame_id else {
+            return Ok(());
+        };
+
+        self.refresh_stack_frame(thread_id, stack_frame_id).await
+    }
+
+    /// Selects a stack frame of a stopped thread, e.g. in the call stack, fetching its
+    /// scopes and returning where its source should be shown, if it has one.
+    pub async fn select_stack_frame(
+        &self,
+        thread_id: u64,
+        stack_frame_id: u64,
+    ) -> Result<Option<StackFrameLocation>> {
+        let stack_frame = {
+            let mut thread_states = self.thread_states();
+            let thread_state = thread_states
+                .get_mut(&thread_id)
+                .with_context(|| format!("unknown thread {thread_id}"))?;
+            let stack_frame = thread_state
+                .stack_frames
+                .iter()
+                .find(|frame| frame.id == stack_frame_id)
+                .cloned()
+                .with_context(|| {
+                    format!("thread {thread_id} has no stack frame {stack_frame_id}")
+                })?;
+            thread_state.current_stack_frame_id = Some(stack_frame_id);
+            stack_frame
+        };
+        self.set_current_thread_id(Some(thread_id));
+        self.notify(ClientNotification::StackFrameSelected {
+            thread_id,
+            stack_frame_id,
+        });
+
+        let has_scopes = self
+            .thread_state_by_id(thread_id)
+            .scopes
+            .contains_key(&stack_frame_id);
+        if !has_scopes {
+            self.refresh_stack_frame(thread_id, stack_frame_id).await?;
+        }
+
+        let Some(source) = stack_frame
+            .source
+            .map(|source| self.resolve_source(&source))
+        else {
+            return Ok(None);
+        };
+        let content = match source.source_reference.filter(|reference| *reference > 0) {
+            Some(source_reference) if source.path.is_none() => {
+                Some(self.source(source_reference).await?.content)
+            }
+            _ => None,
+        };
+
+        Ok(Some(StackFrameLocation {
+            source,
+            row: dap_line_to_row(stack_frame.line),
+            column: dap_column_to_editor_column(stack_frame.column),
+            content,
+        }))
+    }
+
+    /// Re-fetches the state the adapter said went stale, limited to the stack frame or
+    /// thread of the event when it names one.
+    ///
+    /// Invalidated stacks re-fetch the stack frames of the stopped threads, like
+    /// [`Self::refresh_thread`]. Invalidated variables re-fetch the scopes of the
+    /// selected frames, so expanded variables are fetched again.
+    pub async fn handle_invalidated_event(&self, event: &InvalidatedEvent) -> Result<()> {
+        let areas = event.areas.as_deref().unwrap_or_default();
+        let invalidates = |area: InvalidatedAreas| {
+            areas.is_empty()
+                || areas.iter().any(|invalidated| {
+                    matches!(
+                        invalidated,
+                        InvalidatedAreas::All | InvalidatedAreas::Unknown
+                    ) || *invalidated == area
+                })
+        };
+
+        if invalidates(InvalidatedAreas::Threads) && event.stack_frame_id.is_none() {
+            self.threads().await?;
+        }
+
+        // the stack frame id wins over the thread id
+        let thread_ids = {
+            let thread_states = self.thread_states();
+            thread_states
+                .iter()
+                .filter(|(thread_id, thread_state)| {
+                    thread_state.status == ThreadStatus::Stopped
+                        && match event.stack_frame_id {
+                            Some(stack_frame_id) => thread_state
+                                .stack_frames
+                                .iter()
+                                .any(|frame| frame.id == stack_frame_id),
+                            None => event.thread_id.map_or(true, |id| id == **thread_id),
+                        }
+                })
+                .map(|(thread_id, _)| *thread_id)
+                .collect::<Vec<_>>()
+        };
+
+        if invalidates(InvalidatedAreas::Stacks) {
+            for thread_id in thread_ids {
+                self.refresh_thread(thread_id).await?;
+            }
+        } else if invalidates(InvalidatedAreas::Variables) {
+            for thread_id in thread_ids {
+                let stack_frame_id = {
+                    let mut thread_states = self.thread_states();
+                    let Some(thread_state) = thread_states.get_mut(&thread_id) else {
+                        continue;
+                    };
+                    match event.stack_frame_id {
+                        Some(stack_frame_id) => {
+                            thread_state.scopes.remove(&stack_frame_id);
+                        }
+                        None => thread_state.scopes.clear(),
+                    }
+                    thread_state.variables.clear();
+                    thread_state.current_stack_frame_id.filter(|id| {
+                        event
+                            .stack_frame_id
+                            .map_or(true, |frame_id| frame_id == *id)
+                    })
+                };
+                self.variable_formats.lock().clear();
+                if let Some(stack_frame_id) = stack_frame_id {
+                    self.refresh_stack_frame(thread_id, stack_frame_id).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the scopes of a stack frame and, when the thread is the current one,
+    /// re-evaluates the watches in it.
+    async fn refresh_stack_frame(&self, thread_id: u64, stack_frame_id: u64) -> Result<()> {
+        let scopes = self.scopes(stack_frame_id).await?;
+
+        if let Some(thread_state) = self.thread_states().get_mut(&thread_id) {
+            thread_state.scopes.insert(stack_frame_id, scopes);
+        }
+
+        self.notify(ClientNotification::ScopesUpdated {
+            thread_id,
+            stack_frame_id,
+        });
+
+        let is_current_thread = self
+            .current_thread_id()
+            .map_or(true, |current_thread_id| current_thread_id == thread_id);
+        if is_current_thread && !self.watches.lock().is_empty() {
+            self.evaluate_watches(Some(stack_frame_id)).await;
+            self.notify(ClientNotification::WatchesUpdated);
+        }
+
+        Ok(())
+    }
+}
+
+/// A debug adapter for tests, connected to a [`DebugAdapterClient`] through in-memory
+/// pipes, that responds to the requests of the client and sends it events.
+#[cfg(any(test, feature = "test-support"))]
+pub struct FakeAdapter {
+    /// The payloads the client sent, in order.
+    pub requests: Receiver<Payload>,
+    /// Sends payloads to the client.
+    pub responses: Sender<Payload>,
+    _tasks: Vec<Task<()>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl DebugAdapterClient {
+    /// Creates a client connected to a [`FakeAdapter`] instead of an adapter process.
+    pub fn new_fake(
+        id: DebugAdapterClientId,
+        config: DebugAdapterConfig,
+        executor: BackgroundExecutor,
+    ) -> (Self, FakeAdapter) {
+        let (client_stdin, adapter_stdin) = transport::pipe();
+        let (adapter_stdout, client_stdout) = transport::pipe();
+
+        let (server_tx, server_rx, tasks) = Self::handle_transport(
+            Box::new(client_stdout),
+            Box::new(client_stdin),
+            None,
+            Default::default(),
+            config.log_messages,
+            &executor,
+        );
+        let client = Self::new_internal(id, config, server_tx, server_rx, tasks, executor.clone());
+
+        let (requests_tx, requests) = unbounded();
+        let (responses, responses_rx) = unbounded();
+        let adapter = FakeAdapter {
+            requests,
+            responses,
+            _tasks: vec![
+                executor.spawn(async move {
+                    transport::handle_input(Box::new(adapter_stdin), requests_tx, false)
+                        .await
+                        .log_err();
+                }),
+                executor.spawn(async move {
+                    transport::handle_output(Box::new(adapter_stdout), responses_rx, false)
+                        .await
+                        .log_err();
+                }),
+            ],
+        };
+
+        (client, adapter)
+    }
+
+    /// Creates a client connected to a [`FakeAdapter`], as if the adapter had already
+    /// responded to `initialize` with `capabilities`.
+    pub fn with_capabilities(
+        capabilities: Capabilities,
+        executor: BackgroundExecutor,
+    ) -> (Self, FakeAdapter) {
+        let (client, adapter) = Self::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig::default(),
+            executor,
+        );
+        *client.capabilities.lock() = Some(capabilities);
+        (client, adapter)
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl FakeAdapter {
+    /// Waits for the next request of the client, which must be an `R` request, and
+    /// responds to it with `body`, returning the arguments of the request.
+    pub async fn respond<R: dap_types::requests::Request>(&self, body: R::Response) -> Value {
+        let Ok(Payload::Request(request)) = self.requests.recv().await else {
+            panic!("expected a {} request", R::COMMAND);
+        };
+        assert_eq!(request.command, R::COMMAND);
+
+        self.responses
+            .send(Payload::Response(Response {
+                seq: 0,
+                request_seq: request.seq,
+                success: true,
+                command: request.command,
+                message: None,
+                body: Some(serde_json::to_value(body).unwrap()),
+            }))
+            .await
+            .unwrap();
+
+        request.arguments.unwrap_or_default()
+    }
+
+    /// Waits for the next request of the client and fails it with `message`.
+    pub async fn respond_with_error(&self, message: &str) {
+        let Ok(Payload::Request(request)) = self.requests.recv().await else {
+            panic!("expected a request");
+        };
+
+        self.responses
+            .send(Payload::Response(Response {
+                seq: 0,
+                request_seq: request.seq,
+                success: false,
+                command: request.command,
+                message: Some(message.to_string()),
+                body: None,
+            }))
+            .await
+            .unwrap();
+    }
+
+    pub async fn send_event(&self, event: Events) {
+        self.responses
+            .send(Payload::Event(Box::new(event)))
+            .await
+            .unwrap();
+    }
+}
+
+/// Converts a line of the adapter to a zero-based row of the editor. Adapters are
+/// initialized with `linesStartAt1` and `columnsStartAt1`, so their lines and columns
+/// are one-based.
+pub fn dap_line_to_row(line: u64) -> u64 {
+    line.saturating_sub(1)
+}
+
+/// Converts a zero-based row of the editor to a line of the adapter, see [`dap_line_to_row`].
+pub fn row_to_dap_line(row: u64) -> u64 {
+    row + 1
+}
+
+/// Converts a column of the adapter to a zero-based column of the editor, see
+/// [`dap_line_to_row`].
+pub fn dap_column_to_editor_column(column: u64) -> u64 {
+    column.saturating_sub(1)
+}
+
+/// The locale of the system as a BCP 47 language tag, e.g. `de-CH`, read from the
+/// environment variables POSIX systems use for the language of messages.
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| posix_locale_to_language_tag(&value))
+}
+
+/// Converts a POSIX locale like `de_CH.UTF-8` to a language tag like `de-CH`. The `C`
+/// and `POSIX` locales don't name a language.
+fn posix_locale_to_language_tag(locale: &str) -> Option<String> {
+    let locale = locale.split(['.', '@']).next()?;
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return None;
+    }
+    Some(locale.replace('_', "-"))
+}
+
+/// The location of [`DebugAdapterClient::breakpoint_locations`] that is closest to the
+/// line, to snap a breakpoint the user set to a line where it can be hit. Later lines
+/// are preferred over earlier ones at the same distance, as adapters move breakpoints
+/// forward.
+pub fn nearest_breakpoint_location(
+    locations: &[BreakpointLocation],
+    line: u64,
+) -> Option<&BreakpointLocation> {
+    locations
+        .iter()
+        .min_by_key(|location| (location.line.abs_diff(line), location.line < line))
+}
+
+/// Quotes an argument for a POSIX shell.
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// How a completion of the debug console changes its input, in byte offsets of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionEdit {
+    /// The text that is replaced by `new_text`.
+    pub range: Range<usize>,
+    pub new_text: String,
+    /// What is selected once the completion was applied, in the new input.
+    pub selection: Range<usize>,
+}
+
+/// The edit that applies a completion item for the input `text`, for which completions
+/// were requested at the one-based `column`. Items without a start replace the word in
+/// front of the cursor.
+pub fn completion_edit(text: &str, column: u64, item: &CompletionItem) -> CompletionEdit {
+    // positions are measured in UTF-16 code units
+    let cursor = advance_utf16(text, 0, column.saturating_sub(1));
+    let range = match item.start {
+        Some(start) => {
+            let start = advance_utf16(text, 0, start.saturating_sub(1));
+            start..advance_utf16(text, start, item.length.unwrap_or_default())
+        }
+        None => {
+            let word_start = text[..cursor]
+                .char_indices()
+                .rev()
+                .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+                .last()
+                .map_or(cursor, |(ix, _)| ix);
+            word_start..cursor
+        }
+    };
+
+    let new_text = item
+        .text
+        .clone()
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| item.label.clone());
+    let selection_start = item
+        .selection_start
+        .map_or(new_text.len(), |start| advance_utf16(&new_text, 0, start));
+    let selection_end = advance_utf16(
+        &new_text,
+        selection_start,
+        item.selection_length.unwrap_or_default(),
+    );
+
+    CompletionEdit {
+        selection: range.start + selection_start..range.start + selection_end,
+        range,
+        new_text,
+    }
+}
+
+/// The byte offset that is `units` UTF-16 code units after `offset`, at most the end of
+/// the text.
+fn advance_utf16(text: &str, offset: usize, units: u64) -> usize {
+    let mut remaining = units;
+    for (ix, c) in text[offset..].char_indices() {
+        if remaining == 0 {
+            return offset + ix;
+        }
+        remaining = remaining.saturating_sub(c.len_utf16() as u64);
+    }
+    text.len()
+}
+
+fn breakpoint_path(breakpoint: &Breakpoint) -> Option<&str> {
+    breakpoint.source.as_ref()?.path.as_deref()
+}
+
+/// Checks that a hit condition is a number, optionally preceded by a comparison
+/// operator or `%`, which is what adapters commonly accept.
+fn validate_hit_condition(hit_condition: &str) -> Result<()> {
+    let count = ["==", ">=", "<=", ">", "<", "=", "%"]
+        .iter()
+        .find_map(|operator| hit_condition.strip_prefix(operator))
+        .unwrap_or(hit_condition)
+        .trim();
+
+    count
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| anyhow!("invalid hit condition: {hit_condition}"))
+}
+
+/// Converts an offset for a memory request. Although the protocol allows
+/// negative offsets, `dap_types` only represents positive ones.
+fn memory_offset(offset: Option<i64>) -> Result<Option<u64>> {
+    offset
+        .map(|offset| {
+            u64::try_from(offset).map_err(|_| anyhow!("negative memory offsets are not supported"))
+        })
+        .transpose()
+}
+
+/// Whether two sources sent by the adapter refer to the same source.
+fn is_same_source(a: &Source, b: &Source) -> bool {
+    match (a.source_reference, b.source_reference) {
+        (Some(a), Some(b)) if a > 0 && b > 0 => a == b,
+        _ => a.path.is_some() && a.path == b.path,
+    }
+}
+
+/// Applies the capabilities that are set in `update` on top of `current`.
+///
+/// Adapters only send the capabilities that changed, so unset ones keep their values.
+fn merge_capabilities(current: Capabilities, update: &Capabilities) -> Capabilities {
+    let (Ok(Value::Object(mut merged)), Ok(Value::Object(update))) =
+        (serde_json::to_value(&current), serde_json::to_value(update))
+    else {
+        return current;
+    };
+
+    merged.extend(update.into_iter().filter(|(_, value)| !value.is_null()));
+    serde_json::from_value(Value::Object(merged)).unwrap_or(current)
+}
+
+/// Removes the ANSI escape sequences (colors, cursor movement, hyperlinks, ...) from `text`.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '\x1b' {
+            stripped.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI sequences end with a byte in `@`..=`~`
+            Some('[') => {
+                for char in chars.by_ref() {
+                    if ('@'..='~').contains(&char) {
+                        break;
+                    }
+                }
+            }
+            // OSC sequences end with BEL or ST (`ESC \`)
+            Some(']') => {
+                while let Some(char) = chars.next() {
+                    if char == '\x07' {
+                        break;
+                    }
+                    if char == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // the other escape sequences are two characters long
+            _ => {}
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{breakpoint_store::StoredBreakpoint, config::DebugConsoleKind};
+    use dap_types::{
+        BreakpointLocationsResponse, CapabilitiesEvent, CompletionItemType, CompletionsResponse,
+        ContinueResponse, ContinuedEvent, DataBreakpointAccessType, DisassembleResponse,
+        ExceptionBreakMode, ExceptionDetails, ExitedEvent, LoadedSourcesResponse, ModulesResponse,
+        ProgressEndEvent, ProgressStartEvent, ProgressUpdateEvent, ReadMemoryResponse, ThreadEvent,
+        ThreadsResponse, VariablePresentationHint, VariablesResponse,
+    };
+    use futures::FutureExt;
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    fn initialized_fake_client(
+        capabilities: Capabilities,
+        cx: &TestAppContext,
+    ) -> (Arc<DebugAdapterClient>, FakeAdapter) {
+        let (client, adapter) = DebugAdapterClient::with_capabilities(capabilities, cx.executor());
+        (Arc::new(client), adapter)
+    }
+
+    fn fake_client(cx: &TestAppContext) -> (Arc<DebugAdapterClient>, FakeAdapter) {
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig::default(),
+            cx.executor(),
+        );
+        (Arc::new(client), adapter)
+    }
+
+    /// Handles the events of `client`, returning a channel that receives a message
+    /// after every handled event.
+    fn handle_events(client: &Arc<DebugAdapterClient>) -> (Task<Result<()>>, Receiver<()>) {
+        let (handled_tx, handled_rx) = unbounded();
+        let task = client
+            .executor
+            .spawn(DebugAdapterClient::handle_events(client.clone(), {
+                move |_| handled_tx.try_send(()).unwrap()
+            }));
+        (task, handled_rx)
+    }
+
+    fn thread_started_event(thread_id: u64) -> Events {
+        Events::Thread(ThreadEvent {
+            reason: ThreadEventReason::Started,
+            thread_id,
+        })
+    }
+
+    fn stopped_event(thread_id: u64, reason: StoppedEventReason) -> StoppedEvent {
+        StoppedEvent {
+            reason,
+            description: None,
+            thread_id: Some(thread_id),
+            preserve_focus_hint: None,
+            text: None,
+            all_threads_stopped: None,
+            hit_breakpoint_ids: None,
+        }
+    }
+
+    fn output_event(category: OutputEventCategory, output: &str) -> OutputEvent {
+        OutputEvent {
+            category: Some(category),
+            output: output.into(),
+            group: None,
+            variables_reference: None,
+            source: None,
+            line: None,
+            column: None,
+            data: None,
+        }
+    }
+
+    fn source(path: &str) -> Source {
+        Source {
+            name: None,
+            path: Some(path.into()),
+            source_reference: None,
+            presentation_hint: None,
+            origin: None,
+            sources: None,
+            adapter_data: None,
+            checksums: None,
+        }
+    }
+
+    fn stack_frame(id: u64, name: &str) -> StackFrame {
+        StackFrame {
+            id,
+            name: name.into(),
+            source: None,
+            line: 1,
+            column: 1,
+            end_line: None,
+            end_column: None,
+            can_restart: None,
+            instruction_pointer_reference: None,
+            module_id: None,
+            presentation_hint: None,
+        }
+    }
+
+    fn scope(name: &str, variables_reference: u64) -> Scope {
+        Scope {
+            name: name.into(),
+            presentation_hint: None,
+            variables_reference,
+            named_variables: None,
+            indexed_variables: None,
+            expensive: false,
+            source: None,
+            line: None,
+            column: None,
+            end_line: None,
+            end_column: None,
+        }
+    }
+
+    fn sh(script: &str) -> DebugAdapterBinary {
+        DebugAdapterBinary {
+            command: "sh".into(),
+            arguments: vec!["-c".into(), script.into()],
+            env: None,
+        }
+    }
+
+    fn evaluate_response(result: &str, variables_reference: u64) -> EvaluateResponse {
+        EvaluateResponse {
+            result: result.into(),
+            type_: None,
+            presentation_hint: None,
+            variables_reference,
+            named_variables: None,
+            indexed_variables: None,
+            memory_reference: None,
+        }
+    }
+
+    fn variable(name: &str, value: &str, variables_reference: u64) -> Variable {
+        Variable {
+            name: name.into(),
+            value: value.into(),
+            type_: None,
+            presentation_hint: None,
+            evaluate_name: None,
+            variables_reference,
+            named_variables: None,
+            indexed_variables: None,
+            memory_reference: None,
+        }
+    }
+
+    #[gpui::test]
+    async fn test_refresh_thread(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(stopped_event(
+                1,
+                StoppedEventReason::Breakpoint,
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+
+        let (result, _) = futures::join!(client.refresh_thread(1), async {
+            adapter
+                .respond::<StackTrace>(dap_types::StackTraceResponse {
+                    stack_frames: vec![stack_frame(10, "main"), stack_frame(11, "start")],
+                    total_frames: None,
+                })
+                .await;
+            adapter
+                .respond::<Scopes>(dap_types::ScopesResponse {
+                    scopes: vec![scope("Locals", 100)],
+                })
+                .await;
+        });
+        result.unwrap();
+
+        let thread_state = client.thread_state_by_id(1);
+        assert_eq!(
+            thread_state.stop_reason,
+            Some(StoppedEventReason::Breakpoint)
+        );
+        assert_eq!(thread_state.stack_frames.len(), 2);
+        assert_eq!(thread_state.current_stack_frame_id, Some(10));
+        assert_eq!(thread_state.scopes[&10][0].name, "Locals");
+
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadStopped {
+                thread_id: Some(1),
+                all_threads_stopped: false,
+                preserve_focus: false,
+            }
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::StackFramesUpdated { thread_id: 1 }
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ScopesUpdated {
+                thread_id: 1,
+                stack_frame_id: 10
+            }
+        );
+
+        // The selection is kept when the selected frame is still on the stack.
+        client
+            .thread_states()
+            .get_mut(&1)
+            .unwrap()
+            .current_stack_frame_id = Some(11);
+        let (result, _) = futures::join!(client.refresh_thread(1), async {
+            adapter
+                .respond::<StackTrace>(dap_types::StackTraceResponse {
+                    stack_frames: vec![stack_frame(10, "main"), stack_frame(11, "start")],
+                    total_frames: None,
+                })
+                .await;
+            let arguments = adapter
+                .respond::<Scopes>(dap_types::ScopesResponse { scopes: vec![] })
+                .await;
+            assert_eq!(arguments["frameId"], 11);
+        });
+        result.unwrap();
+        assert_eq!(
+            client.thread_state_by_id(1).current_stack_frame_id,
+            Some(11)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_invalidated_event(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        client.thread_states().insert(
+            1,
+            ThreadState {
+                status: ThreadStatus::Stopped,
+                stack_frames: vec![stack_frame(10, "main"), stack_frame(11, "start")],
+                scopes: HashMap::from_iter([(10, vec![scope("Locals", 100)])]),
+                current_stack_frame_id: Some(10),
+                ..Default::default()
+            },
+        );
+        client.thread_states().insert(
+            2,
+            ThreadState {
+                status: ThreadStatus::Running,
+                ..Default::default()
+            },
+        );
+
+        // only the scopes of the invalidated frame are fetched again
+        let event = InvalidatedEvent {
+            areas: Some(vec![InvalidatedAreas::Variables]),
+            thread_id: None,
+            stack_frame_id: Some(10),
+        };
+        let (result, _) = futures::join!(client.handle_invalidated_event(&event), async {
+            let arguments = adapter
+                .respond::<Scopes>(dap_types::ScopesResponse {
+                    scopes: vec![scope("Globals", 101)],
+                })
+                .await;
+            assert_eq!(arguments["frameId"], 10);
+        });
+        result.unwrap();
+        assert_eq!(client.thread_state_by_id(1).scopes[&10][0].name, "Globals");
+
+        // the thread isn't stopped, so it has no stack to fetch
+        let event = InvalidatedEvent {
+            areas: Some(vec![InvalidatedAreas::Stacks]),
+            thread_id: Some(2),
+            stack_frame_id: None,
+        };
+        client.handle_invalidated_event(&event).await.unwrap();
+        assert!(adapter.requests.is_empty());
+
+        // without areas everything is fetched again
+        let event = InvalidatedEvent {
+            areas: None,
+            thread_id: None,
+            stack_frame_id: None,
+        };
+        let (result, _) = futures::join!(client.handle_invalidated_event(&event), async {
+            adapter
+                .respond::<Threads>(dap_types::ThreadsResponse {
+                    threads: vec![Thread {
+                        id: 1,
+                        name: "main".into(),
+                    }],
+                })
+                .await;
+            adapter
+                .respond::<StackTrace>(dap_types::StackTraceResponse {
+                    stack_frames: vec![stack_frame(12, "main")],
+                    total_frames: None,
+                })
+                .await;
+            adapter
+                .respond::<Scopes>(dap_types::ScopesResponse { scopes: vec![] })
+                .await;
+        });
+        result.unwrap();
+        let thread_state = client.thread_state_by_id(1);
+        assert_eq!(thread_state.name.as_deref(), Some("main"));
+        assert_eq!(thread_state.current_stack_frame_id, Some(12));
+    }
+
+    #[gpui::test]
+    async fn test_memory_updates(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+        let stack_updates = client.memory_updates("0x1000");
+        let heap_updates = client.memory_updates("0x2000");
+        drop(client.memory_updates("0x3000"));
+
+        for memory_reference in ["0x1000", "0x3000"] {
+            adapter
+                .send_event(Events::Memory(MemoryEvent {
+                    memory_reference: memory_reference.into(),
+                    offset: 8,
+                    count: 4,
+                }))
+                .await;
+            handled_rx.recv().await.unwrap();
+        }
+
+        let update = stack_updates.try_recv().unwrap();
+        assert_eq!(
+            update,
+            MemoryUpdate {
+                offset: 8,
+                count: 4
+            }
+        );
+        assert!(update.overlaps(0, 9));
+        assert!(!update.overlaps(12, 4));
+        assert!(heap_updates.try_recv().is_err());
+        assert!(!client.memory_subscribers.lock().contains_key("0x3000"));
+    }
+
+    #[gpui::test]
+    async fn test_telemetry_output_is_not_shown_in_console(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let telemetry_events = client.telemetry_events();
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter
+            .send_event(Events::Output(OutputEvent {
+                data: Some(json!({ "adapterVersion": "1.0.0" })),
+                ..output_event(OutputEventCategory::Telemetry, "launch")
+            }))
+            .await;
+        adapter
+            .send_event(Events::Output(output_event(
+                OutputEventCategory::Stdout,
+                "hello world\n",
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+
+        let telemetry_event = telemetry_events.try_recv().unwrap();
+        assert_eq!(telemetry_event.output, "launch");
+        assert_eq!(
+            telemetry_event.data,
+            Some(json!({ "adapterVersion": "1.0.0" }))
+        );
+        assert!(telemetry_events.try_recv().is_err());
+
+        let console_output = client.console_output();
+        assert_eq!(console_output.len(), 1);
+        assert_eq!(console_output[0].event.output, "hello world\n");
+        assert_eq!(console_output[0].kind, ConsoleOutputKind::Stdout);
+        assert!(!console_output[0].has_ansi);
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::OutputReceived
+        );
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_ansi_console_output(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter
+            .send_event(Events::Output(output_event(
+                OutputEventCategory::Stderr,
+                "\x1b[1;31merror\x1b[0m: see \x1b]8;;file:///main.rs\x07main.rs\x1b]8;;\x1b\\\n",
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        let console_output = client.console_output();
+        assert!(console_output[0].has_ansi);
+        assert_eq!(console_output[0].plain_text(), "error: see main.rs\n");
+    }
+
+    #[gpui::test]
+    async fn test_threads_updated(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        handled_rx.recv().await.unwrap();
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+
+        let (result, _) = futures::join!(
+            client.threads(),
+            adapter.respond::<Threads>(ThreadsResponse {
+                threads: vec![Thread {
+                    id: 1,
+                    name: "main".into(),
+                }],
+            })
+        );
+        result.unwrap();
+        assert_eq!(client.thread_state_by_id(1).name.as_deref(), Some("main"));
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+
+        adapter
+            .send_event(Events::Thread(ThreadEvent {
+                reason: ThreadEventReason::Exited,
+                thread_id: 1,
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        assert!(client.thread_state_by_id(1).status.is_finished());
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+    }
+
+    #[gpui::test]
+    async fn test_stop_all_threads(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter.send_event(thread_started_event(2)).await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+
+        let (result, _) = futures::join!(client.stop_all_threads(), async {
+            for thread_id in [1, 2] {
+                let arguments = adapter.respond::<Pause>(()).await;
+                assert_eq!(arguments["threadId"], thread_id);
+                assert_eq!(
+                    client.thread_state_by_id(thread_id).status,
+                    ThreadStatus::Running
+                );
+
+                adapter
+                    .send_event(Events::Stopped(stopped_event(
+                        thread_id,
+                        StoppedEventReason::Pause,
+                    )))
+                    .await;
+            }
+        });
+        result.unwrap();
+
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+        assert_eq!(client.thread_state_by_id(2).status, ThreadStatus::Stopped);
+    }
+
+    #[gpui::test]
+    async fn test_stop_all_threads_skips_stopped_threads(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        for thread_id in [1, 2, 3, 4] {
+            adapter.send_event(thread_started_event(thread_id)).await;
+            handled_rx.recv().await.unwrap();
+        }
+        adapter
+            .send_event(Events::Stopped(stopped_event(
+                2,
+                StoppedEventReason::Breakpoint,
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        let (result, _) = futures::join!(client.stop_all_threads(), async {
+            // the adapter only stops the paused thread
+            let arguments = adapter.respond::<Pause>(()).await;
+            assert_eq!(arguments["threadId"], 1);
+            adapter
+                .send_event(Events::Stopped(stopped_event(1, StoppedEventReason::Pause)))
+                .await;
+
+            // the stopped thread 2 is skipped, and the stop of thread 3 stops all
+            let arguments = adapter.respond::<Pause>(()).await;
+            assert_eq!(arguments["threadId"], 3);
+            adapter
+                .send_event(Events::Stopped(StoppedEvent {
+                    all_threads_stopped: Some(true),
+                    ..stopped_event(3, StoppedEventReason::Pause)
+                }))
+                .await;
+        });
+        result.unwrap();
+
+        assert!(adapter.requests.try_recv().is_err());
+        for thread_id in [1, 2, 3, 4] {
+            assert_eq!(
+                client.thread_state_by_id(thread_id).status,
+                ThreadStatus::Stopped
+            );
+        }
+    }
+
+    #[gpui::test]
+    async fn test_stop_all_threads_pauses_once_when_all_threads_stop(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        for thread_id in [1, 2, 3] {
+            adapter.send_event(thread_started_event(thread_id)).await;
+            handled_rx.recv().await.unwrap();
+        }
+
+        let (result, _) = futures::join!(client.stop_all_threads(), async {
+            let arguments = adapter.respond::<Pause>(()).await;
+            assert_eq!(arguments["threadId"], 1);
+            adapter
+                .send_event(Events::Stopped(StoppedEvent {
+                    all_threads_stopped: Some(true),
+                    ..stopped_event(1, StoppedEventReason::Pause)
+                }))
+                .await;
+        });
+        result.unwrap();
+
+        assert!(adapter.requests.try_recv().is_err());
+        for thread_id in [1, 2, 3] {
+            assert_eq!(
+                client.thread_state_by_id(thread_id).status,
+                ThreadStatus::Stopped
+            );
+        }
+    }
+
+    #[gpui::test]
+    async fn test_select_stack_frame(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        client.thread_states().insert(
+            1,
+            ThreadState {
+                status: ThreadStatus::Stopped,
+                current_stack_frame_id: Some(10),
+                stack_frames: vec![
+                    StackFrame {
+                        source: Some(source("/project/main.rs")),
+                        line: 5,
+                        column: 3,
+                        ..stack_frame(10, "main")
+                    },
+                    StackFrame {
+                        source: Some(Source {
+                            path: None,
+                            source_reference: Some(4),
+                            ..source("")
+                        }),
+                        line: 2,
+                        column: 1,
+                        ..stack_frame(11, "<eval>")
+                    },
+                ],
+                ..Default::default()
+            },
+        );
+
+        let (location, _) = futures::join!(client.select_stack_frame(1, 11), async {
+            let arguments = adapter
+                .respond::<Scopes>(dap_types::ScopesResponse {
+                    scopes: vec![scope("Locals", 100)],
+                })
+                .await;
+            assert_eq!(arguments["frameId"], 11);
+            adapter
+                .respond::<dap_types::requests::Source>(SourceResponse {
+                    content: "1 + 1".into(),
+                    mime_type: None,
+                })
+                .await;
+        });
+        let location = location.unwrap().unwrap();
+        assert_eq!((location.row, location.column), (1, 0));
+        assert_eq!(location.content.as_deref(), Some("1 + 1"));
+
+        let thread_state = client.thread_state_by_id(1);
+        assert_eq!(thread_state.current_stack_frame_id, Some(11));
+        assert_eq!(thread_state.scopes[&11][0].name, "Locals");
+        assert_eq!(client.current_thread_id(), Some(1));
+
+        // the scopes of frames are only fetched when they are not cached yet
+        client
+            .thread_states()
+            .get_mut(&1)
+            .unwrap()
+            .scopes
+            .insert(10, Vec::new());
+        let notifications = client.notifications();
+        let location = client.select_stack_frame(1, 10).await.unwrap().unwrap();
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::StackFrameSelected {
+                thread_id: 1,
+                stack_frame_id: 10
+            }
+        );
+        assert_eq!(location.source.path.as_deref(), Some("/project/main.rs"));
+        assert_eq!((location.row, location.column), (4, 2));
+        assert!(location.content.is_none());
+        assert!(adapter.requests.try_recv().is_err());
+
+        assert!(client.select_stack_frame(1, 12).await.is_err());
+    }
+
+    #[gpui::test]
+    async fn test_execution_marker(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+        client.thread_states().insert(
+            1,
+            ThreadState {
+                status: ThreadStatus::Stopped,
+                stack_frames: vec![
+                    StackFrame {
+                        source: Some(source("/project/lib.rs")),
+                        line: 8,
+                        ..stack_frame(10, "parse")
+                    },
+                    StackFrame {
+                        source: Some(source("/project/main.rs")),
+                        line: 3,
+                        ..stack_frame(11, "main")
+                    },
+                ],
+                ..Default::default()
+            },
+        );
+        assert_eq!(client.execution_marker(), None);
+
+        client.set_current_thread_id(Some(1));
+        assert_eq!(
+            client.execution_marker(),
+            Some(ExecutionMarker {
+                path: Path::new("/project/lib.rs").into(),
+                row: 7,
+                is_top_frame: true,
+            })
+        );
+
+        client
+            .thread_states()
+            .get_mut(&1)
+            .unwrap()
+            .current_stack_frame_id = Some(11);
+        assert_eq!(
+            client.execution_marker(),
+            Some(ExecutionMarker {
+                path: Path::new("/project/main.rs").into(),
+                row: 2,
+                is_top_frame: false,
+            })
+        );
+
+        adapter
+            .send_event(Events::Continued(ContinuedEvent {
+                thread_id: 1,
+                all_threads_continued: None,
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        assert_eq!(client.execution_marker(), None);
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::ThreadContinued {
+                thread_id: 1,
+                all_threads_continued: true
+            }
+        );
+    }
+
+    #[gpui::test]
+    async fn test_goto_refreshes_stack(cx: &mut TestAppContext) {
+        let (client, _adapter) = fake_client(cx);
+        assert!(client
+            .goto_targets(source("main.rs"), 10)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("does not support goto targets"));
+
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_goto_targets_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(stopped_event(
+                1,
+                StoppedEventReason::Breakpoint,
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+
+        let (targets, _) = futures::join!(client.goto_targets(source("main.rs"), 10), async {
+            let arguments = adapter
+                .respond::<GotoTargets>(dap_types::GotoTargetsResponse {
+                    targets: vec![GotoTarget {
+                        id: 7,
+                        label: "line 10".into(),
+                        line: 10,
+                        column: None,
+                        end_line: None,
+                        end_column: None,
+                        instruction_pointer_reference: None,
+                    }],
+                })
+                .await;
+            assert_eq!(arguments["line"], 10);
+        });
+        let targets = targets.unwrap();
+        assert_eq!(targets.len(), 1);
+
+        let (result, _) = futures::join!(client.goto(1, targets[0].id), async {
+            let arguments = adapter.respond::<Goto>(()).await;
+            assert_eq!(arguments["targetId"], 7);
+            adapter
+                .send_event(Events::Stopped(stopped_event(1, StoppedEventReason::Goto)))
+                .await;
+            adapter
+                .respond::<StackTrace>(dap_types::StackTraceResponse {
+                    stack_frames: vec![stack_frame(20, "main")],
+                    total_frames: None,
+                })
+                .await;
+            adapter
+                .respond::<Scopes>(dap_types::ScopesResponse { scopes: vec![] })
+                .await;
+        });
+        result.unwrap();
+
+        let thread_state = client.thread_state_by_id(1);
+        assert_eq!(thread_state.current_stack_frame_id, Some(20));
+    }
+
+    #[gpui::test]
+    async fn test_sources_are_retrievable_by_reference(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (stack_frames, _) = futures::join!(client.stack_trace(1), async {
+            adapter
+                .respond::<StackTrace>(dap_types::StackTraceResponse {
+                    stack_frames: vec![
+                        StackFrame {
+                            source: Some(Source {
+                                name: Some("<eval>".into()),
+                                path: None,
+                                source_reference: Some(42),
+                                adapter_data: Some(json!({ "scriptId": "17" })),
+                                ..source("")
+                            }),
+                            ..stack_frame(10, "eval")
+                        },
+                        stack_frame(11, "main"),
+                    ],
+                    total_frames: None,
+                })
+                .await;
+        });
+        assert_eq!(stack_frames.unwrap().len(), 2);
+
+        let source = client.source_by_reference(42).unwrap();
+        assert_eq!(source.name.as_deref(), Some("<eval>"));
+        assert_eq!(source.adapter_data, Some(json!({ "scriptId": "17" })));
+        assert!(client.source_by_reference(43).is_none());
+    }
+
+    #[gpui::test]
+    async fn test_evaluate_expandable(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (value, _) = futures::join!(
+            client.evaluate_expandable(
+                "user".into(),
+                Some(1),
+                Some(EvaluateArgumentsContext::Watch)
+            ),
+            async {
+                let arguments = adapter
+                    .respond::<Evaluate>(evaluate_response("User { .. }", 12))
+                    .await;
+                assert_eq!(arguments["context"], "watch");
+            }
+        );
+        let value = value.unwrap();
+        assert_eq!(value.value, "User { .. }");
+        assert_eq!(value.variables_reference, 12);
+        assert!(value.has_children);
+
+        let (value, _) = futures::join!(
+            client.evaluate_expandable("1 + 1".into(), None, None),
+            adapter.respond::<Evaluate>(evaluate_response("2", 0))
+        );
+        assert!(!value.unwrap().has_children);
+    }
+
+    #[gpui::test]
+    async fn test_expand_variable_tree(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (value, _) = futures::join!(
+            client.evaluate_expandable("user".into(), None, None),
+            adapter.respond::<Evaluate>(evaluate_response("User { .. }", 12))
+        );
+        let mut tree = value.unwrap().variable_tree();
+        assert!(tree.is_expandable());
+        assert!(!tree.is_expanded());
+
+        let (result, arguments) = futures::join!(
+            client.expand_variable_tree(&mut tree),
+            adapter.respond::<Variables>(VariablesResponse {
+                variables: vec![
+                    variable("name", "\"ann\"", 0),
+                    variable("address", "{..}", 13)
+                ],
+            })
+        );
+        result.unwrap();
+        assert_eq!(arguments["variablesReference"], 12);
+        let children = tree.children.as_mut().unwrap();
+        assert_eq!(children.len(), 2);
+        assert!(!children[0].1.is_expandable());
+        // grandchildren are only fetched once their parent is expanded
+        assert!(!children[1].1.is_expanded());
+
+        // expanding an expanded tree doesn't fetch the children again
+        client.expand_variable_tree(&mut tree).await.unwrap();
+        tree.collapse();
+        assert!(!tree.is_expanded());
+
+        let mut tree = VariableTree::new(0);
+        client.expand_variable_tree(&mut tree).await.unwrap();
+        assert!(!tree.is_expanded());
+    }
+
+    #[gpui::test]
+    async fn test_exited_and_terminated_events(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Exited(ExitedEvent { exit_code: 3 }))
+            .await;
+        adapter.send_event(Events::Terminated(None)).await;
+        for _ in 0..3 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        assert_eq!(client.exit_code(), Some(3));
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Ended);
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::SessionEnded
+        );
+    }
+
+    #[gpui::test]
+    async fn test_configuration_done_is_skipped_when_unsupported(cx: &mut TestAppContext) {
+        let (client, adapter) =
+            DebugAdapterClient::with_capabilities(Capabilities::default(), cx.executor());
+        let mut store = BreakpointStore::default();
+        store.toggle(Path::new("/project/main.rs"), 3);
+
+        let (result, arguments) = futures::join!(
+            client.configure(&store),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+        );
+        result.unwrap();
+        assert_eq!(arguments["source"]["path"], "/project/main.rs");
+        assert!(adapter.requests.is_empty());
+        assert_eq!(
+            client.configuration_phase(),
+            ConfigurationPhase::ConfigurationDone
+        );
+    }
+
+    #[gpui::test]
+    async fn test_breakpoints_set_during_startup(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_configuration_done_request: Some(true),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let client = Arc::new(client);
+        let (_events, handled_rx) = handle_events(&client);
+        let mut store = BreakpointStore::default();
+        store.toggle(Path::new("/project/main.rs"), 3);
+
+        // a breakpoint toggled before the adapter sent the `initialized` event
+        let (result, _) = futures::join!(
+            client.set_breakpoints(Path::new("/project/lib.rs").into(), Some(Vec::new())),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+        );
+        result.unwrap();
+
+        let (result, _) = futures::join!(
+            client.start(Some(json!({ "program": "main" })), &store),
+            async {
+                let Ok(Payload::Request(launch)) = adapter.requests.recv().await else {
+                    panic!("expected a request");
+                };
+                assert_eq!(launch.command, "launch");
+                assert!(adapter.requests.is_empty());
+
+                adapter.send_event(Events::Initialized(None)).await;
+                handled_rx.recv().await.unwrap();
+                let arguments = adapter
+                    .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                        breakpoints: Vec::new(),
+                    })
+                    .await;
+                assert_eq!(arguments["source"]["path"], "/project/main.rs");
+                adapter.respond::<ConfigurationDone>(()).await;
+
+                adapter
+                    .responses
+                    .send(Payload::Response(Response {
+                        seq: 0,
+                        request_seq: launch.seq,
+                        success: true,
+                        command: launch.command,
+                        message: None,
+                        body: None,
+                    }))
+                    .await
+                    .unwrap();
+            }
+        );
+        result.unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_start_configures_the_adapter_once_it_is_initialized(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_configuration_done_request: Some(true),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let client = Arc::new(client);
+        let (_events, handled_rx) = handle_events(&client);
+        let mut store = BreakpointStore::default();
+        store.toggle(Path::new("/project/main.rs"), 3);
+
+        let (result, _) = futures::join!(
+            client.start(Some(json!({ "program": "main" })), &store),
+            async {
+                let Ok(Payload::Request(launch)) = adapter.requests.recv().await else {
+                    panic!("expected a request");
+                };
+                assert_eq!(launch.command, "launch");
+                assert!(adapter.requests.is_empty());
+
+                adapter.send_event(Events::Initialized(None)).await;
+                handled_rx.recv().await.unwrap();
+                adapter
+                    .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                        breakpoints: Vec::new(),
+                    })
+                    .await;
+                adapter.respond::<ConfigurationDone>(()).await;
+
+                adapter
+                    .responses
+                    .send(Payload::Response(Response {
+                        seq: 0,
+                        request_seq: launch.seq,
+                        success: true,
+                        command: launch.command,
+                        message: None,
+                        body: None,
+                    }))
+                    .await
+                    .unwrap();
+            }
+        );
+        result.unwrap();
+        assert_eq!(
+            client.configuration_phase(),
+            ConfigurationPhase::ConfigurationDone
+        );
+    }
+
+    #[gpui::test]
+    async fn test_start_when_the_adapter_is_never_initialized(cx: &mut TestAppContext) {
+        let mut store = BreakpointStore::default();
+        store.toggle(Path::new("/project/main.rs"), 3);
+
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                initialized_timeout: Some(50),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let (result, _) = futures::join!(client.start(None, &store), async {
+            adapter.requests.recv().await.unwrap();
+            cx.executor().advance_clock(Duration::from_millis(50));
+        });
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<StartupTimeout>().unwrap().phase,
+            StartupPhase::Initialized
+        );
+        assert!(adapter.requests.is_empty());
+
+        // the adapter is configured anyway when the config asks for it
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(1),
+            DebugAdapterConfig {
+                initialized_timeout: Some(50),
+                configure_without_initialized: true,
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let (result, _) = futures::join!(client.start(None, &store), async {
+            let Ok(Payload::Request(launch)) = adapter.requests.recv().await else {
+                panic!("expected a request");
+            };
+            cx.executor().advance_clock(Duration::from_millis(50));
+            let arguments = adapter
+                .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                    breakpoints: Vec::new(),
+                })
+                .await;
+            assert_eq!(arguments["breakpoints"][0]["line"], 4);
+            adapter
+                .responses
+                .send(Payload::Response(Response {
+                    seq: 0,
+                    request_seq: launch.seq,
+                    success: true,
+                    command: launch.command,
+                    message: None,
+                    body: None,
+                }))
+                .await
+                .unwrap();
+        });
+        result.unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_ending_the_session_fails_pending_requests(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        let (result, _) = futures::join!(client.threads(), async {
+            adapter.requests.recv().await.unwrap();
+            adapter.send_event(Events::Terminated(None)).await;
+            handled_rx.recv().await.unwrap();
+        });
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "debug session ended before the adapter responded to the threads request"
+        );
+
+        // requests sent after the session ended are still answered
+        let (result, _) = futures::join!(
+            client.disconnect(None, None, None),
+            adapter.respond::<Disconnect>(())
+        );
+        result.unwrap();
+    }
+
+    #[gpui::test]
+    async fn test_launch_sends_the_console_kind(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                console: DebugConsoleKind::IntegratedTerminal,
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+
+        let (result, arguments) = futures::join!(
+            client.launch(Some(json!({ "program": "main.py" }))),
+            adapter.respond::<Launch>(())
+        );
+        result.unwrap();
+        assert_eq!(arguments["console"], "integratedTerminal");
+
+        let (result, arguments) = futures::join!(
+            client.launch(Some(json!({ "console": "internalConsole" }))),
+            adapter.respond::<Launch>(())
+        );
+        result.unwrap();
+        assert_eq!(arguments["console"], "internalConsole");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("main.py"), "'main.py'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_posix_locale_to_language_tag() {
+        assert_eq!(
+            posix_locale_to_language_tag("de_CH.UTF-8").as_deref(),
+            Some("de-CH")
+        );
+        assert_eq!(
+            posix_locale_to_language_tag("sr_RS@latin").as_deref(),
+            Some("sr-RS")
+        );
+        assert_eq!(posix_locale_to_language_tag("fr").as_deref(), Some("fr"));
+        assert_eq!(posix_locale_to_language_tag("C.UTF-8"), None);
+        assert_eq!(posix_locale_to_language_tag("POSIX"), None);
+    }
+
+    #[gpui::test]
+    async fn test_initialize_sends_the_locale(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                locale: Some("de-CH".into()),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let (result, arguments) = futures::join!(
+            client.initialize(),
+            adapter.respond::<Initialize>(Capabilities::default())
+        );
+        result.unwrap();
+        assert_eq!(arguments["locale"], "de-CH");
+        assert_eq!(arguments["clientID"], CLIENT_ID);
+        assert_eq!(arguments["clientName"], CLIENT_NAME);
+        assert_eq!(arguments["supportsRunInTerminalRequest"], true);
+    }
+
+    #[test]
+    fn test_line_and_column_conversions() {
+        assert_eq!(dap_line_to_row(1), 0);
+        assert_eq!(row_to_dap_line(dap_line_to_row(42)), 42);
+        // adapters that don't know the position of a frame report line and column 0
+        assert_eq!(dap_line_to_row(0), 0);
+        assert_eq!(dap_column_to_editor_column(0), 0);
+        assert_eq!(dap_column_to_editor_column(5), 4);
+    }
+
+    #[gpui::test]
+    async fn test_terminate_threads(cx: &mut TestAppContext) {
+        let (client, _adapter) =
+            DebugAdapterClient::with_capabilities(Capabilities::default(), cx.executor());
+        assert_eq!(
+            client
+                .terminate_threads(vec![2])
+                .await
+                .unwrap_err()
+                .to_string(),
+            "debug adapter does not support terminating threads"
+        );
+
+        let (client, adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_terminate_threads_request: Some(true),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        client.update_thread_state_status(1, ThreadStatus::Stopped);
+        client.update_thread_state_status(2, ThreadStatus::Running);
+        let (result, arguments) = futures::join!(
+            client.terminate_threads(vec![2]),
+            adapter.respond::<TerminateThreads>(())
+        );
+        result.unwrap();
+        assert_eq!(arguments["threadIds"], json!([2]));
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+        assert_eq!(client.thread_state_by_id(2).status, ThreadStatus::Ended);
+    }
+
+    #[gpui::test]
+    async fn test_process_event(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                id: "debugpy".into(),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let client = Arc::new(client);
+        let (_events, handled_rx) = handle_events(&client);
+        assert_eq!(client.session_title(), "Debugging debugpy");
+
+        adapter
+            .send_event(Events::Process(ProcessEvent {
+                name: "/usr/bin/python".into(),
+                system_process_id: Some(1234),
+                is_local_process: Some(true),
+                start_method: Some(dap_types::ProcessEventStartMethod::Attach),
+                pointer_size: None,
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        let process = client.debuggee_process().unwrap();
+        assert_eq!(process.is_local_process, Some(true));
+        assert_eq!(
+            process.start_method,
+            Some(dap_types::ProcessEventStartMethod::Attach)
+        );
+        assert_eq!(client.session_title(), "Debugging python (pid 1234)");
+    }
+
+    #[gpui::test]
+    async fn test_configuration_phases(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        assert_eq!(client.configuration_phase(), ConfigurationPhase::NotStarted);
+        let (result, _) = futures::join!(
+            client.initialize(),
+            adapter.respond::<Initialize>(Capabilities {
+                supports_configuration_done_request: Some(true),
+                ..Default::default()
+            })
+        );
+        result.unwrap();
+        assert_eq!(
+            client.configuration_phase(),
+            ConfigurationPhase::InitializeSent
+        );
+
+        let client = Arc::new(client);
+        let (_events, handled_rx) = handle_events(&client);
+
+        // breakpoints set outside of the handshake don't change the phase
+        let (result, _) = futures::join!(
+            client.set_breakpoints(Path::new("/project/lib.rs").into(), Some(Vec::new())),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+        );
+        result.unwrap();
+        assert_eq!(
+            client.configuration_phase(),
+            ConfigurationPhase::InitializeSent
+        );
+
+        adapter.send_event(Events::Initialized(None)).await;
+        handled_rx.recv().await.unwrap();
+
+        let mut store = BreakpointStore::default();
+        store.toggle(Path::new("/project/main.rs"), 3);
+        let (result, _) = futures::join!(client.configure(&store), async {
+            adapter
+                .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                    breakpoints: Vec::new(),
+                })
+                .await;
+            adapter.respond::<ConfigurationDone>(()).await;
+        });
+        result.unwrap();
+
+        assert_eq!(
+            client.configuration_phases(),
+            [
+                ConfigurationPhase::InitializeSent,
+                ConfigurationPhase::InitializedReceived,
+                ConfigurationPhase::BreakpointsSent,
+                ConfigurationPhase::ConfigurationDone,
+            ]
+        );
+        assert_eq!(
+            client.configuration_phase(),
+            ConfigurationPhase::ConfigurationDone
+        );
+
+        // every step is announced, so a startup progress view can follow the handshake
+        let announced_phases = std::iter::from_fn(|| notifications.try_recv().ok())
+            .filter_map(|notification| match notification {
+                ClientNotification::ConfigurationPhaseChanged(phase) => Some(phase),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(announced_phases, client.configuration_phases());
+    }
+
+    #[gpui::test]
+    async fn test_breakpoints_of_modified_sources(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let main_rs: Arc<Path> = Path::new("/project/main.rs").into();
+        let set_breakpoints = || async {
+            let (result, arguments) = futures::join!(
+                client.set_breakpoints(main_rs.clone(), Some(Vec::new())),
+                adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                    breakpoints: Vec::new(),
+                })
+            );
+            result.unwrap();
+            arguments["sourceModified"].clone()
+        };
+
+        assert_eq!(set_breakpoints().await, Value::Null);
+        client.mark_source_modified(Path::new("/project/lib.rs").into());
+        assert_eq!(set_breakpoints().await, Value::Null);
+        client.mark_source_modified(main_rs.clone());
+        assert_eq!(set_breakpoints().await, true);
+        // the adapter resolved the lines of the edited file again
+        assert_eq!(set_breakpoints().await, Value::Null);
+    }
+
+    #[gpui::test]
+    async fn test_dropping_the_client_ends_event_handling(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        handled_rx.recv().await.unwrap();
+
+        drop(client);
+        assert!(events.await.is_ok());
+    }
+
+    #[gpui::test]
+    async fn test_breakpoint_events_update_breakpoints(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        let path: Arc<Path> = Path::new("/project/main.rs").into();
+        let breakpoint = |id, verified, line| Breakpoint {
+            id: Some(id),
+            verified,
+            message: None,
+            source: None,
+            line: Some(line),
+            column: None,
+            end_line: None,
+            end_column: None,
+            instruction_reference: None,
+            offset: None,
+            reason: None,
+        };
+        let source_breakpoint = |line| SourceBreakpoint {
+            line,
+            column: None,
+            condition: None,
+            hit_condition: None,
+            log_message: None,
+            mode: None,
+        };
+
+        let (response, _) = futures::join!(
+            client.set_breakpoints(
+                path.clone(),
+                Some(vec![source_breakpoint(3), source_breakpoint(8)])
+            ),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: vec![breakpoint(1, false, 3), breakpoint(2, false, 8)],
+            })
+        );
+        response.unwrap();
+        assert!(!client.breakpoint_by_id(1).unwrap().verified);
+
+        // The adapter verifies the first breakpoint after snapping it to the next
+        // line with code and removes the second one.
+        adapter
+            .send_event(Events::Breakpoint(BreakpointEvent {
+                reason: BreakpointEventReason::Changed,
+                breakpoint: breakpoint(1, true, 4),
+            }))
+            .await;
+        adapter
+            .send_event(Events::Breakpoint(BreakpointEvent {
+                reason: BreakpointEventReason::Removed,
+                breakpoint: breakpoint(2, false, 8),
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+
+        let breakpoint = client.breakpoint_by_id(1).unwrap();
+        assert!(breakpoint.verified);
+        assert_eq!(breakpoint.line, Some(4));
+        assert!(client.breakpoint_by_id(2).is_none());
+
+        let breakpoints = client.breakpoints_for_path(&path);
+        assert_eq!(breakpoints.len(), 1);
+        assert_eq!(breakpoints[0].id, Some(1));
+
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::BreakpointUpdated { breakpoint_id: 1 }
+        );
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::BreakpointUpdated { breakpoint_id: 2 }
+        );
+    }
+
+    #[cfg(unix)]
+    #[gpui::test]
+    async fn test_adapter_exit_fails_the_session(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        let client = DebugAdapterClient::new(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                transport: DebugAdapterKind::STDIO,
+                ..Default::default()
+            },
+            &sh("read line; echo boom >&2; sleep 0.1; exit 3"),
+            std::env::temp_dir(),
+            &cx.to_async(),
+        )
+        .await
+        .unwrap();
+        let notifications = client.notifications();
+
+        client.threads().await.unwrap_err();
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::SessionFailed {
+                error: "debug adapter exited with exit status: 3:\nboom".into()
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[gpui::test]
+    async fn test_adapter_env_and_cwd(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        let cwd = std::env::temp_dir().canonicalize().unwrap();
+        let client = DebugAdapterClient::new(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                transport: DebugAdapterKind::STDIO,
+                env: [("GREETING".to_string(), "hello".to_string())]
+                    .into_iter()
+                    .collect(),
+                cwd: Some(cwd.clone()),
+                ..Default::default()
+            },
+            &DebugAdapterBinary {
+                env: Some(
+                    [("GREETING", "hi"), ("TARGET", "world")]
+                        .into_iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                ),
+                ..sh("read line; echo \"$GREETING $TARGET $(pwd -P)\" >&2; sleep 0.1")
+            },
+            PathBuf::from("/"),
+            &cx.to_async(),
+        )
+        .await
+        .unwrap();
+        let notifications = client.notifications();
+
+        client.threads().await.unwrap_err();
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::SessionFailed {
+                error: format!(
+                    "debug adapter exited with exit status: 0:\nhello world {}",
+                    cwd.display()
+                )
+            }
+        );
+    }
+
+    #[gpui::test]
+    async fn test_startup_timeouts_report_their_phase(cx: &mut TestAppContext) {
+        cx.executor().allow_parking();
+        let localhost = Ipv4Addr::new(127, 0, 0, 1);
+        let port = DebugAdapterClient::get_port(localhost).await.unwrap();
+        let executor = cx.executor();
+        let (result, _) = futures::join!(
+            DebugAdapterClient::connect_to_adapter(
+                SocketAddrV4::new(localhost, port),
+                Duration::from_millis(300),
+                &executor,
+            ),
+            async { executor.advance_clock(Duration::from_millis(300)) }
+        );
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<StartupTimeout>().unwrap().phase,
+            StartupPhase::Connect
+        );
+
+        let (server_tx, _requests) = unbounded();
+        let (_responses, server_rx) = unbounded();
+        let client = DebugAdapterClient::new_internal(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                initialize_timeout: Some(50),
+                ..Default::default()
+            },
+            server_tx,
+            server_rx,
+            Vec::new(),
+            cx.executor(),
+        );
+        let (result, _) = futures::join!(client.initialize(), async {
+            cx.executor().advance_clock(Duration::from_millis(50))
+        });
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<StartupTimeout>().unwrap().phase,
+            StartupPhase::Initialize
+        );
+        assert!(error.to_string().contains("initialize"));
+    }
+
+    #[gpui::test]
+    async fn test_last_error_is_cleared_by_next_successful_request(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        assert_eq!(client.last_error(), None);
+
+        let (result, _) = futures::join!(
+            client.threads(),
+            adapter.respond_with_error("no process running")
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            client.last_error().as_deref(),
+            Some("threads request failed: no process running")
+        );
+
+        let (result, _) = futures::join!(
+            client.threads(),
+            adapter.respond::<Threads>(ThreadsResponse {
+                threads: Vec::new()
+            })
+        );
+        assert!(result.is_ok());
+        assert_eq!(client.last_error(), None);
+    }
+
+    #[gpui::test]
+    async fn test_supported_actions_follow_capabilities(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_step_back: Some(true),
+                supports_restart_frame: Some(false),
+                supports_read_memory_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+        assert_eq!(
+            client.supported_actions(),
+            [SessionAction::StepBack, SessionAction::ReadMemory]
+        );
+
+        adapter
+            .send_event(Events::Capabilities(CapabilitiesEvent {
+                capabilities: Capabilities {
+                    supports_restart_frame: Some(true),
+                    supports_step_back: Some(false),
+                    ..Default::default()
+                },
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        assert_eq!(
+            notifications.try_recv().unwrap(),
+            ClientNotification::CapabilitiesUpdated
+        );
+        assert_eq!(
+            client.supported_actions(),
+            [SessionAction::RestartFrame, SessionAction::ReadMemory]
+        );
+    }
+
+    #[gpui::test]
+    fn test_features(cx: &mut TestAppContext) {
+        let (client, _adapter) = fake_client(cx);
+        assert_eq!(client.features(), None);
+
+        let (client, _adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_step_back: Some(true),
+                supports_conditional_breakpoints: Some(true),
+                supports_log_points: Some(false),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        assert_eq!(
+            client.features(),
+            Some(DebugFeatures {
+                step_back: true,
+                conditional_breakpoints: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[gpui::test]
+    async fn test_capabilities_event_updates_capabilities(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_configuration_done_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter
+            .send_event(Events::Capabilities(CapabilitiesEvent {
+                capabilities: Capabilities {
+                    supports_goto_targets_request: Some(true),
+                    ..Default::default()
+                },
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        let capabilities = client.capabilities().unwrap();
+        assert_eq!(capabilities.supports_configuration_done_request, Some(true));
+        assert_eq!(capabilities.supports_goto_targets_request, Some(true));
+    }
+
+    #[gpui::test]
+    async fn test_progress_events(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter
+            .send_event(Events::ProgressEnd(ProgressEndEvent {
+                progress_id: "orphan".into(),
+                message: None,
+            }))
+            .await;
+        adapter
+            .send_event(Events::ProgressStart(ProgressStartEvent {
+                progress_id: "index".into(),
+                title: "Indexing".into(),
+                request_id: None,
+                cancellable: None,
+                message: Some("main.rs".into()),
+                percentage: Some(0),
+            }))
+            .await;
+        adapter
+            .send_event(Events::ProgressUpdate(ProgressUpdateEvent {
+                progress_id: "index".into(),
+                message: None,
+                percentage: Some(50),
+            }))
+            .await;
+        for _ in 0..3 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        assert_eq!(
+            client.progress_by_id("index"),
+            Some(Progress {
+                title: "Indexing".into(),
+                message: Some("main.rs".into()),
+                percentage: Some(50),
+                cancellable: false,
+            })
+        );
+
+        adapter
+            .send_event(Events::ProgressEnd(ProgressEndEvent {
+                progress_id: "index".into(),
+                message: None,
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        assert_eq!(client.progress_by_id("index"), None);
+        let progress_id = ClientNotification::ProgressUpdated {
+            progress_id: "index".into(),
+        };
+        for _ in 0..3 {
+            assert_eq!(notifications.try_recv().unwrap(), progress_id);
+        }
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_child_variables_inherit_format(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (variables, arguments) = futures::join!(
+            client.variables(5, Some(ValueFormat { hex: Some(true) })),
+            adapter.respond::<Variables>(VariablesResponse {
+                variables: vec![variable("point", "0x1", 6), variable("len", "0x2", 0)],
+            })
+        );
+        assert_eq!(variables.unwrap().len(), 2);
+        assert_eq!(arguments["format"], json!({ "hex": true }));
+
+        let (variables, arguments) = futures::join!(
+            client.variables(6, None),
+            adapter.respond::<Variables>(VariablesResponse {
+                variables: vec![variable("x", "0x3", 0)],
+            })
+        );
+        assert_eq!(variables.unwrap().len(), 1);
+        assert_eq!(arguments["format"], json!({ "hex": true }));
+    }
+
+    #[gpui::test]
+    async fn test_variable_paging(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let small = variable("small", "[..]", 5);
+        let (variables, arguments) = futures::join!(
+            client.child_variables(&small),
+            adapter.respond::<Variables>(VariablesResponse {
+                variables: vec![variable("[0]", "1", 0)],
+            })
+        );
+        assert_eq!(variables.unwrap().len(), 1);
+        assert_eq!(arguments["filter"], Value::Null);
+        assert_eq!(arguments["start"], Value::Null);
+
+        let large = Variable {
+            indexed_variables: Some(100_000),
+            ..variable("large", "[..]", 6)
+        };
+        let (variables, _) = futures::join!(client.child_variables(&large), async {
+            let arguments = adapter
+                .respond::<Variables>(VariablesResponse {
+                    variables: vec![variable("len", "100000", 0)],
+                })
+                .await;
+            assert_eq!(arguments["filter"], "named");
+            let arguments = adapter
+                .respond::<Variables>(VariablesResponse {
+                    variables: vec![variable("[0]", "1", 0), variable("[1]", "2", 0)],
+                })
+                .await;
+            assert_eq!(arguments["filter"], "indexed");
+            assert_eq!(arguments["start"], 0);
+            assert_eq!(arguments["count"], VARIABLES_PAGE_SIZE);
+        });
+        assert_eq!(variables.unwrap().len(), 3);
+
+        let (variables, arguments) = futures::join!(
+            client.variables_page(6, 100, 100),
+            adapter.respond::<Variables>(VariablesResponse {
+                variables: vec![variable("[100]", "101", 0)],
+            })
+        );
+        assert_eq!(variables.unwrap()[0].name, "[100]");
+        assert_eq!(arguments["start"], 100);
+        assert_eq!(arguments["variablesReference"], 6);
+    }
+
+    #[gpui::test]
+    async fn test_export_variables_json(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (exported, _) = futures::join!(client.export_variables_json(1, 7), async {
+            adapter
+                .respond::<Scopes>(dap_types::ScopesResponse {
+                    scopes: vec![scope("Locals", 1)],
+                })
+                .await;
+            adapter
+                .respond::<Variables>(VariablesResponse {
+                    variables: vec![
+                        Variable {
+                            type_: Some("User".into()),
+                            ..variable("user", "User", 2)
+                        },
+                        variable("count", "3", 0),
+                    ],
+                })
+                .await;
+            adapter
+                .respond::<Variables>(VariablesResponse {
+                    variables: vec![variable("name", "\"ada\"", 0), variable("this", "User", 2)],
+                })
+                .await;
+        });
+
+        assert_eq!(
+            exported.unwrap(),
+            json!({
+                "Locals": {
+                    "user": {
+                        "value": "User",
+                        "type": "User",
+                        "variables": {
+                            "name": { "value": "\"ada\"" },
+                            "this": { "value": "User" },
+                        },
+                    },
+                    "count": { "value": "3" },
+                },
+            })
+        );
+    }
+
+    #[gpui::test]
+    async fn test_capability_gated_request(cx: &mut TestAppContext) {
+        let (client, _adapter) =
+            DebugAdapterClient::with_capabilities(Capabilities::default(), cx.executor());
+        let error = client.exception_info(1).await.unwrap_err();
+        assert!(error.to_string().contains("exception info"), "{error}");
+
+        let (client, adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_exception_info_request: Some(true),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let (response, arguments) = futures::join!(
+            client.exception_info(1),
+            adapter.respond::<ExceptionInfo>(ExceptionInfoResponse {
+                exception_id: "ValueError".into(),
+                description: None,
+                break_mode: ExceptionBreakMode::Always,
+                details: None,
+            })
+        );
+        assert_eq!(response.unwrap().exception_id, "ValueError");
+        assert_eq!(arguments, json!({ "threadId": 1 }));
+    }
+
+    #[gpui::test]
+    async fn test_evaluate_hover(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_cancel_request: Some(true),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        client.thread_states().insert(
+            1,
+            ThreadState {
+                status: ThreadStatus::Running,
+                current_stack_frame_id: Some(10),
+                ..Default::default()
+            },
+        );
+        client.set_current_thread_id(Some(1));
+
+        // Running threads aren't evaluated.
+        let (value, _) = futures::join!(client.evaluate_hover("count".into()), async {
+            cx.executor().advance_clock(HOVER_DEBOUNCE)
+        });
+        assert!(value.unwrap().is_none());
+        assert!(adapter.requests.try_recv().is_err());
+
+        client.update_thread_state_status(1, ThreadStatus::Stopped);
+        let (value, arguments) = futures::join!(client.evaluate_hover("count".into()), async {
+            cx.executor().advance_clock(HOVER_DEBOUNCE);
+            adapter.respond::<Evaluate>(evaluate_response("3", 0)).await
+        });
+        assert_eq!(value.unwrap().unwrap().value, "3");
+        assert_eq!(arguments["context"], "hover");
+        assert_eq!(arguments["frameId"], 10);
+
+        // Moving away cancels the hover that is being evaluated.
+        let (value, _) = futures::join!(client.evaluate_hover("user".into()), async {
+            cx.executor().advance_clock(HOVER_DEBOUNCE);
+            let Ok(Payload::Request(evaluate)) = adapter.requests.recv().await else {
+                panic!("expected an evaluate request");
+            };
+            let (_, cancel) = futures::join!(client.cancel_hover(), adapter.respond::<Cancel>(()));
+            assert_eq!(cancel["requestId"], evaluate.seq);
+        });
+        assert!(value.unwrap().is_none());
+    }
+
+    #[gpui::test]
+    async fn test_variable_declaration_location(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (variables, _) = futures::join!(
+            client.variables(100, None),
+            adapter.respond::<VariablesWithLocations>(VariablesWithLocationsResponse {
+                variables: vec![
+                    VariableWithLocations {
+                        variable: variable("counter", "1", 0),
+                        declaration_location_reference: Some(7),
+                    },
+                    VariableWithLocations {
+                        variable: variable("temp", "2", 0),
+                        declaration_location_reference: None,
+                    },
+                ],
+            })
+        );
+        assert_eq!(variables.unwrap().len(), 2);
+        assert!(client
+            .variable_declaration_location(100, "temp")
+            .await
+            .unwrap()
+            .is_none());
+
+        let (location, (arguments, _)) = futures::join!(
+            client.variable_declaration_location(100, "counter"),
+            async {
+                let arguments = adapter
+                    .respond::<Locations>(LocationsResponse {
+                        source: Source {
+                            path: None,
+                            source_reference: Some(3),
+                            ..source("")
+                        },
+                        line: 12,
+                        column: Some(5),
+                    })
+                    .await;
+                let source = adapter
+                    .respond::<dap_types::requests::Source>(SourceResponse {
+                        content: "let counter = 1;".into(),
+                        mime_type: None,
+                    })
+                    .await;
+                (arguments, source)
+            }
+        );
+        assert_eq!(arguments, json!({ "locationReference": 7 }));
+
+        let location = location.unwrap().unwrap();
+        assert_eq!(location.source.source_reference, Some(3));
+        assert_eq!((location.line, location.column), (12, Some(5)));
+        assert_eq!(location.content.as_deref(), Some("let counter = 1;"));
+    }
+
+    #[gpui::test]
+    async fn test_set_variable_by_path(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_set_variable: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        let (response, arguments) = futures::join!(
+            client.set_variable_by_path(1, "user.age", "42".into()),
+            async {
+                adapter
+                    .respond::<Variables>(VariablesResponse {
+                        variables: vec![variable("age", "7", 0), variable("user", "User", 2)],
+                    })
+                    .await;
+                adapter
+                    .respond::<Variables>(VariablesResponse {
+                        variables: vec![variable("name", "\"ada\"", 0), variable("age", "36", 0)],
+                    })
+                    .await;
+                adapter
+                    .respond::<SetVariable>(SetVariableResponse {
+                        value: "42".into(),
+                        type_: None,
+                        variables_reference: None,
+                        named_variables: None,
+                        indexed_variables: None,
+                        memory_reference: None,
+                    })
+                    .await
+            }
+        );
+        assert_eq!(response.unwrap().value, "42");
+        assert_eq!(arguments["variablesReference"], 2);
+        assert_eq!(arguments["name"], "age");
+        assert_eq!(arguments["value"], "42");
+
+        let (error, _) = futures::join!(client.set_variable_by_path(1, "len", "3".into()), async {
+            adapter
+                .respond::<Variables>(VariablesResponse {
+                    variables: vec![Variable {
+                        presentation_hint: Some(VariablePresentationHint {
+                            kind: None,
+                            attributes: Some(vec![VariablePresentationHintAttributes::ReadOnly]),
+                            visibility: None,
+                            lazy: None,
+                        }),
+                        ..variable("len", "2", 0)
+                    }],
+                })
+                .await
+        });
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "variable `len` is read-only"
+        );
+        assert!(adapter.requests.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_stepping_granularity(cx: &mut TestAppContext) {
+        for (supports_stepping_granularity, expected_granularity) in
+            [(None, Value::Null), (Some(true), json!("instruction"))]
+        {
+            let (client, adapter) = DebugAdapterClient::with_capabilities(
+                Capabilities {
+                    supports_stepping_granularity,
+                    ..Default::default()
+                },
+                cx.executor(),
+            );
+            // the step doesn't complete before the thread stopped again
+            let arguments = smol::future::or(
+                async {
+                    client
+                        .step_over(1, SteppingGranularity::Instruction)
+                        .await
+                        .unwrap();
+                    unreachable!()
+                },
+                adapter.respond::<Next>(()),
+            )
+            .await;
+            assert_eq!(arguments["granularity"], expected_granularity);
+        }
+    }
+
+    #[gpui::test]
+    async fn test_control_tasks(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (result, _) = futures::join!(
+            cx.update(|cx| client.pause_task(1, cx)),
+            adapter.respond::<Pause>(())
+        );
+        result.unwrap();
+
+        let (result, _) = futures::join!(
+            cx.update(|cx| client.continue_thread_task(1, cx)),
+            adapter.respond_with_error("thread 1 is not paused")
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("thread 1 is not paused"));
+    }
+
+    #[gpui::test]
+    async fn test_thread_is_busy_while_stepping(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(stopped_event(1, StoppedEventReason::Step)))
+            .await;
+        for _ in 0..2 {
+            handled_rx.recv().await.unwrap();
+        }
+        assert!(!client.thread_is_busy(1));
+
+        let (result, _) =
+            futures::join!(client.step_over(1, SteppingGranularity::Statement), async {
+                adapter.respond::<Next>(()).await;
+                assert!(client.thread_is_busy(1));
+
+                adapter
+                    .send_event(Events::Stopped(stopped_event(1, StoppedEventReason::Step)))
+                    .await;
+                handled_rx.recv().await.unwrap();
+                adapter
+                    .respond::<StackTrace>(dap_types::StackTraceResponse {
+                        stack_frames: vec![stack_frame(1, "main")],
+                        total_frames: None,
+                    })
+                    .await;
+                adapter
+                    .respond::<Scopes>(dap_types::ScopesResponse { scopes: Vec::new() })
+                    .await;
+            });
+        result.unwrap();
+
+        assert!(!client.thread_is_busy(1));
+    }
+
+    #[gpui::test]
+    async fn test_modules_request(cx: &mut TestAppContext) {
+        let module = |id, name: &str| Module {
+            id: ModuleId::Number(id),
+            name: name.into(),
+            path: None,
+            is_optimized: None,
+            is_user_code: None,
+            version: None,
+            symbol_status: None,
+            symbol_file_path: None,
+            date_time_stamp: None,
+            address_range: None,
+        };
+
+        let (client, _adapter) = fake_client(cx);
+        assert!(client.modules(None, None).await.is_err());
+
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_modules_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let (_events, handled_rx) = handle_events(&client);
+        for (reason, module) in [
+            (ModuleEventReason::New, module(3, "libm.so.6")),
+            (ModuleEventReason::New, module(4, "libz.so.1")),
+            (ModuleEventReason::Removed, module(4, "libz.so.1")),
+        ] {
+            adapter
+                .send_event(Events::Module(ModuleEvent { reason, module }))
+                .await;
+            handled_rx.recv().await.unwrap();
+        }
+
+        let (modules, arguments) = futures::join!(
+            client.modules(Some(0), Some(2)),
+            adapter.respond::<Modules>(ModulesResponse {
+                modules: vec![module(1, "app"), module(2, "libc.so.6")],
+                total_modules: Some(3),
+            })
+        );
+        assert_eq!(modules.unwrap().len(), 2);
+        assert_eq!(arguments["startModule"], 0);
+        assert_eq!(arguments["moduleCount"], 2);
+        assert_eq!(client.total_modules(), Some(3));
+        assert_eq!(
+            client
+                .module_list()
+                .into_iter()
+                .map(|module| module.name)
+                .collect::<Vec<_>>(),
+            ["app", "libc.so.6", "libm.so.6"]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_loaded_sources(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_loaded_sources_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        let eval_source = Source {
+            name: Some("<eval>".into()),
+            path: None,
+            source_reference: Some(7),
+            ..source("")
+        };
+        let (sources, _) = futures::join!(
+            client.loaded_sources(),
+            adapter.respond::<LoadedSources>(LoadedSourcesResponse {
+                sources: vec![source("/project/main.rs"), eval_source.clone()],
+            })
+        );
+        assert_eq!(sources.unwrap().len(), 2);
+        assert!(client.source_by_reference(7).is_some());
+
+        let loaded_source_event =
+            |reason, source| Events::LoadedSource(LoadedSourceEvent { reason, source });
+        adapter
+            .send_event(loaded_source_event(
+                LoadedSourceEventReason::New,
+                source("/project/lib.rs"),
+            ))
+            .await;
+        adapter
+            .send_event(loaded_source_event(
+                LoadedSourceEventReason::Removed,
+                eval_source,
+            ))
+            .await;
+        for _ in 0..2 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        let paths = client
+            .loaded_source_list()
+            .into_iter()
+            .map(|source| source.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                Some("/project/main.rs".into()),
+                Some("/project/lib.rs".into())
+            ]
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                notifications.try_recv().unwrap(),
+                ClientNotification::LoadedSourcesUpdated
+            );
+        }
+    }
+
+    #[gpui::test]
+    async fn test_unverified_breakpoint_messages(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let path: Arc<Path> = Path::new("/project/main.rs").into();
+        let source_breakpoint = |line| SourceBreakpoint {
+            line,
+            column: None,
+            condition: None,
+            hit_condition: None,
+            log_message: None,
+            mode: None,
+        };
+        let breakpoint = |verified, line, message: Option<&str>| Breakpoint {
+            id: None,
+            verified,
+            message: message.map(Into::into),
+            source: None,
+            line: Some(line),
+            column: None,
+            end_line: None,
+            end_column: None,
+            instruction_reference: None,
+            offset: None,
+            reason: None,
+        };
+
+        let (response, _) = futures::join!(
+            client.set_breakpoints(
+                path.clone(),
+                Some(vec![source_breakpoint(2), source_breakpoint(5)])
+            ),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: vec![
+                    breakpoint(true, 3, None),
+                    breakpoint(false, 5, Some("no executable code on this line")),
+                ],
+            })
+        );
+        response.unwrap();
+
+        assert_eq!(
+            client.breakpoint_for_requested_line(&path, 2).unwrap().line,
+            Some(3)
+        );
+        assert_eq!(client.breakpoint_message(&path, 2), None);
+        assert_eq!(
+            client.breakpoint_message(&path, 5).as_deref(),
+            Some("no executable code on this line")
+        );
+        assert_eq!(client.breakpoint_message(&path, 7), None);
+    }
+
+    #[gpui::test]
+    async fn test_watches_failing_repeatedly_are_removed(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        client.add_watch("count".into());
+        client.add_watch("request".into());
+
+        for stop in 0..2 {
+            let (results, _) =
+                futures::join!(client.evaluate_watch_removing_failed(Some(1), 2), async {
+                    adapter
+                        .respond::<Evaluate>(EvaluateResponse {
+                            result: "3".into(),
+                            type_: None,
+                            presentation_hint: None,
+                            variables_reference: 0,
+                            named_variables: None,
+                            indexed_variables: None,
+                            memory_reference: None,
+                        })
+                        .await;
+                    adapter
+                        .respond_with_error("`request` is not in scope")
+                        .await;
+                });
+
+            if stop == 0 {
+                assert_eq!(results.len(), 2);
+                assert!(results[1].1.is_err());
+                assert_eq!(client.watches(), ["count", "request"]);
+            } else {
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].1.as_ref().unwrap().value, "3");
+                assert_eq!(client.watches(), ["count"]);
+            }
+        }
+    }
+
+    #[gpui::test]
+    async fn test_watches_are_evaluated_when_the_current_thread_stops(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        client.set_watches(["count".to_string(), "request".to_string()]);
+        client.add_watch("user".into());
+        client.edit_watch("user", "user.name".into());
+        client.remove_watch("request");
+        client.add_watch("request".into());
+        assert_eq!(client.watches(), ["count", "user.name", "request"]);
+
+        client.thread_states().insert(
+            1,
+            ThreadState {
+                status: ThreadStatus::Stopped,
+                ..Default::default()
+            },
+        );
+        let (result, _) = futures::join!(client.refresh_thread(1), async {
+            adapter
+                .respond::<StackTrace>(dap_types::StackTraceResponse {
+                    stack_frames: vec![stack_frame(10, "main")],
+                    total_frames: None,
+                })
+                .await;
+            adapter
+                .respond::<Scopes>(dap_types::ScopesResponse { scopes: Vec::new() })
+                .await;
+            let arguments = adapter.respond::<Evaluate>(evaluate_response("3", 0)).await;
+            assert_eq!(arguments["context"], "watch");
+            assert_eq!(arguments["frameId"], 10);
+            adapter
+                .respond::<Evaluate>(evaluate_response("\"Ada\"", 0))
+                .await;
+            adapter
+                .respond_with_error("`request` is not in scope")
+                .await;
+        });
+        result.unwrap();
+
+        let results = client.watch_results();
+        assert_eq!(results[0].expression, "count");
+        assert_eq!(
+            results[0].result.as_ref().unwrap().as_ref().unwrap().value,
+            "3"
+        );
+        assert_eq!(results[1].expression, "user.name");
+        assert_eq!(
+            results[2].result,
+            Some(Err(
+                "evaluate request failed: `request` is not in scope".into()
+            ))
+        );
+        assert_eq!(
+            std::iter::from_fn(|| notifications.try_recv().ok()).last(),
+            Some(ClientNotification::WatchesUpdated)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_watches_are_evaluated_when_a_breakpoint_is_hit(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+        client.add_watch("count".into());
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(stopped_event(
+                1,
+                StoppedEventReason::Breakpoint,
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+
+        adapter
+            .respond::<StackTrace>(dap_types::StackTraceResponse {
+                stack_frames: vec![stack_frame(10, "main")],
+                total_frames: None,
+            })
+            .await;
+        adapter
+            .respond::<Scopes>(dap_types::ScopesResponse { scopes: Vec::new() })
+            .await;
+        let arguments = adapter.respond::<Evaluate>(evaluate_response("3", 0)).await;
+        assert_eq!(arguments["frameId"], 10);
+
+        while notifications.recv().await.unwrap() != ClientNotification::WatchesUpdated {}
+        assert_eq!(
+            client.watch_results()[0]
+                .result
+                .as_ref()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .value,
+            "3"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_source_content_by_reference(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter
+            .send_event(Events::LoadedSource(LoadedSourceEvent {
+                reason: LoadedSourceEventReason::New,
+                source: Source {
+                    name: Some("<eval>".into()),
+                    path: None,
+                    source_reference: Some(9),
+                    ..source("")
+                },
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        let (response, arguments) = futures::join!(
+            client.source(9),
+            adapter.respond::<dap_types::requests::Source>(SourceResponse {
+                content: "1 + 1".into(),
+                mime_type: Some("text/javascript".into()),
+            })
+        );
+        let response = response.unwrap();
+        assert_eq!(response.content, "1 + 1");
+        assert_eq!(response.mime_type.as_deref(), Some("text/javascript"));
+        assert_eq!(arguments["sourceReference"], 9);
+        assert_eq!(arguments["source"]["name"], "<eval>");
+    }
+
+    #[gpui::test]
+    async fn test_evaluate_repl_command(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (response, arguments) = futures::join!(
+            client.evaluate_repl_command("user".into(), Some(1)),
+            async {
+                adapter
+                    .respond::<Evaluate>(EvaluateResponse {
+                        result: "User { name: \"ada\" }".into(),
+                        type_: None,
+                        presentation_hint: None,
+                        variables_reference: 4,
+                        named_variables: None,
+                        indexed_variables: None,
+                        memory_reference: None,
+                    })
+                    .await
+            }
+        );
+        response.unwrap();
+        assert_eq!(arguments["context"], "repl");
+        assert_eq!(arguments["frameId"], 1);
+
+        let (response, _) = futures::join!(
+            client.evaluate_repl_command("usr".into(), Some(1)),
+            adapter.respond_with_error("name 'usr' is not defined")
+        );
+        response.unwrap_err();
+
+        let console_output = client
+            .console_output()
+            .into_iter()
+            .map(|output| {
+                (
+                    output.kind,
+                    output.event.category.unwrap(),
+                    output.event.output,
+                    output.event.variables_reference,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            console_output,
+            [
+                (
+                    ConsoleOutputKind::Command,
+                    OutputEventCategory::Console,
+                    "> user\n".to_string(),
+                    None
+                ),
+                (
+                    ConsoleOutputKind::Result,
+                    OutputEventCategory::Console,
+                    "User { name: \"ada\" }\n".to_string(),
+                    Some(4)
+                ),
+                (
+                    ConsoleOutputKind::Command,
+                    OutputEventCategory::Console,
+                    "> usr\n".to_string(),
+                    None
+                ),
+                (
+                    ConsoleOutputKind::Stderr,
+                    OutputEventCategory::Stderr,
+                    "evaluate request failed: name 'usr' is not defined\n".to_string(),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(client.repl_history(), ["user", "usr"]);
+    }
+
+    #[gpui::test]
+    async fn test_source_content_is_cached(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+        let eval_source = Source {
+            name: Some("<eval>".into()),
+            path: None,
+            source_reference: Some(9),
+            ..source("")
+        };
+
+        let (response, _) = futures::join!(
+            client.source(9),
+            adapter.respond::<dap_types::requests::Source>(SourceResponse {
+                content: "1 + 1".into(),
+                mime_type: None,
+            })
+        );
+        assert_eq!(response.unwrap().content, "1 + 1");
+
+        // served from the cache, without a request to respond to
+        assert_eq!(client.source(9).await.unwrap().content, "1 + 1");
+
+        adapter
+            .send_event(Events::LoadedSource(LoadedSourceEvent {
+                reason: LoadedSourceEventReason::Changed,
+                source: eval_source,
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        let (response, _) = futures::join!(
+            client.source(9),
+            adapter.respond::<dap_types::requests::Source>(SourceResponse {
+                content: "2 + 2".into(),
+                mime_type: None,
+            })
+        );
+        assert_eq!(response.unwrap().content, "2 + 2");
+    }
+
+    #[gpui::test]
+    async fn test_run_in_terminal_reverse_request(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        client.set_run_in_terminal_handler(Arc::new(|arguments| {
+            async move {
+                assert_eq!(arguments.args, ["python", "main.py"]);
+                Ok(RunInTerminalResponse {
+                    process_id: Some(42),
+                    shell_process_id: Some(41),
+                })
+            }
+            .boxed()
+        }));
+
+        adapter
+            .responses
+            .send(Payload::Request(Request {
+                seq: 7,
+                command: "runInTerminal".into(),
+                arguments: Some(json!({
+                    "kind": "integrated",
+                    "cwd": "/project",
+                    "args": ["python", "main.py"],
+                })),
+            }))
+            .await
+            .unwrap();
+
+        let Ok(Payload::Response(response)) = adapter.requests.recv().await else {
+            panic!("expected a runInTerminal response");
+        };
+        assert!(response.success);
+        assert_eq!(response.request_seq, 7);
+        assert_eq!(
+            response.body,
+            Some(json!({ "processId": 42, "shellProcessId": 41 }))
+        );
+    }
+
+    #[test]
+    fn test_completion_edit() {
+        let item = |text: &str, start, length| CompletionItem {
+            label: "label".into(),
+            text: Some(text.into()),
+            sort_text: None,
+            detail: None,
+            type_: None,
+            start,
+            length,
+            selection_start: None,
+            selection_length: None,
+        };
+        let apply = |input: &str, edit: &CompletionEdit| {
+            let mut input = input.to_string();
+            input.replace_range(edit.range.clone(), &edit.new_text);
+            input
+        };
+
+        // the item replaces the span it names
+        let edit = completion_edit("len(sel", 8, &item("self.items", Some(5), Some(3)));
+        assert_eq!(edit.range, 4..7);
+        assert_eq!(apply("len(sel", &edit), "len(self.items");
+        assert_eq!(edit.selection, 14..14);
+
+        // the word in front of the cursor is replaced without a start
+        let edit = completion_edit("self.na + 1", 8, &item("name", None, None));
+        assert_eq!(edit.range, 5..7);
+        assert_eq!(apply("self.na + 1", &edit), "self.name + 1");
+
+        // positions are UTF-16 code units
+        let edit = completion_edit("größe.l", 8, &item("len()", Some(7), Some(1)));
+        assert_eq!(apply("größe.l", &edit), "größe.len()");
+
+        let edit = completion_edit(
+            "v.",
+            3,
+            &CompletionItem {
+                selection_start: Some(4),
+                selection_length: Some(1),
+                ..item("get(0)", None, None)
+            },
+        );
+        assert_eq!(edit.range, 2..2);
+        assert_eq!(edit.selection, 6..7);
+    }
+
+    async fn send_run_in_terminal(adapter: &FakeAdapter) -> Response {
+        adapter
+            .responses
+            .send(Payload::Request(Request {
+                seq: 7,
+                command: "runInTerminal".into(),
+                arguments: Some(json!({
+                    "cwd": std::env::temp_dir(),
+                    "args": ["true"],
+                })),
+            }))
+            .await
+            .unwrap();
+        let Ok(Payload::Response(response)) = adapter.requests.recv().await else {
+            panic!("expected a runInTerminal response");
+        };
+        response
+    }
+
+    #[cfg(unix)]
+    #[gpui::test]
+    async fn test_run_in_terminal_in_background(cx: &mut TestAppContext) {
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig {
+                run_in_terminal_in_background: true,
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let response = send_run_in_terminal(&adapter).await;
+        assert!(response.success);
+        assert!(response.body.unwrap()["processId"].as_u64().is_some());
+        drop(client);
+
+        let (_client, adapter) = DebugAdapterClient::new_fake(
+            DebugAdapterClientId(0),
+            DebugAdapterConfig::default(),
+            cx.executor(),
+        );
+        let response = send_run_in_terminal(&adapter).await;
+        assert!(!response.success);
+    }
+
+    #[gpui::test]
+    async fn test_breakpoint_changed_callback(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        client.on_breakpoint_changed({
+            let changes = changes.clone();
+            move |change| changes.lock().push(change.clone())
+        });
+
+        let path: Arc<Path> = Path::new("/project/main.rs").into();
+        let breakpoint = |verified, line| Breakpoint {
+            id: Some(4),
+            verified,
+            message: None,
+            source: None,
+            line: Some(line),
+            column: None,
+            end_line: None,
+            end_column: None,
+            instruction_reference: None,
+            offset: None,
+            reason: None,
+        };
+
+        let (response, _) = futures::join!(
+            client.set_breakpoints(
+                path.clone(),
+                Some(vec![SourceBreakpoint {
+                    line: 10,
+                    column: None,
+                    condition: None,
+                    hit_condition: None,
+                    log_message: None,
+                    mode: None,
+                }])
+            ),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: vec![breakpoint(false, 10)],
+            })
+        );
+        response.unwrap();
+
+        for _ in 0..2 {
+            adapter
+                .send_event(Events::Breakpoint(BreakpointEvent {
+                    reason: BreakpointEventReason::Changed,
+                    breakpoint: breakpoint(true, 11),
+                }))
+                .await;
+            handled_rx.recv().await.unwrap();
+        }
+
+        let changes = changes.lock();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].marker,
+            Some(BreakpointMarker {
+                path: path.clone(),
+                line: 10
+            })
+        );
+        assert!(changes[0].breakpoint.verified);
+        assert_eq!(changes[0].breakpoint.line, Some(11));
+    }
+
+    #[gpui::test]
+    async fn test_read_memory(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_read_memory_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        assert!(client.supports_memory());
+
+        let (memory, arguments) = futures::join!(
+            client.read_memory("0x1000".into(), Some(4), 8),
+            adapter.respond::<ReadMemory>(ReadMemoryResponse {
+                address: "0x1004".into(),
+                unreadable_bytes: Some(4),
+                data: Some(base64::encode([0xde, 0xad, 0xbe, 0xef])),
+            })
+        );
+        assert_eq!(
+            memory.unwrap(),
+            MemoryContents {
+                address: "0x1004".into(),
+                data: vec![0xde, 0xad, 0xbe, 0xef],
+                unreadable_bytes: 4,
+            }
+        );
+        assert_eq!(arguments["memoryReference"], "0x1000");
+        assert_eq!(arguments["offset"], 4);
+        assert_eq!(arguments["count"], 8);
+
+        assert!(client
+            .read_memory("0x1000".into(), Some(-4), 8)
+            .await
+            .is_err());
+    }
+
+    #[gpui::test]
+    async fn test_read_memory_without_capability(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(Capabilities::default(), cx);
+        assert!(!client.supports_memory());
+
+        let error = client
+            .read_memory("0x1000".into(), None, 8)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "debug adapter does not support reading memory"
+        );
+        assert!(adapter.requests.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_disassembly_is_grouped_by_source_line(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_disassemble_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        let instruction = |address: &str, location: Option<Source>, line| DisassembledInstruction {
+            address: address.into(),
+            instruction_bytes: None,
+            instruction: "nop".into(),
+            symbol: None,
+            location,
+            line,
+            column: None,
+            end_line: None,
+            end_column: None,
+            presentation_hint: None,
+        };
+
+        let (groups, _) = futures::join!(
+            client.disassemble_by_line("0x1000".into(), None, None, 5),
+            adapter.respond::<Disassemble>(DisassembleResponse {
+                instructions: vec![
+                    instruction("0x1000", Some(source("/project/main.c")), Some(3)),
+                    instruction("0x1001", None, None),
+                    instruction("0x1002", None, Some(4)),
+                    instruction("0x1003", Some(source("/project/util.c")), Some(4)),
+                    instruction("0x1004", None, Some(4)),
+                ],
+            })
+        );
+
+        let groups = groups
+            .unwrap()
+            .into_iter()
+            .map(|group| {
+                (
+                    group.source.and_then(|source| source.path),
+                    group.line,
+                    group
+                        .instructions
+                        .into_iter()
+                        .map(|instruction| instruction.address)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            [
+                (
+                    Some("/project/main.c".to_string()),
+                    Some(3),
+                    vec!["0x1000".to_string(), "0x1001".to_string()]
+                ),
+                (
+                    Some("/project/main.c".to_string()),
+                    Some(4),
+                    vec!["0x1002".to_string()]
+                ),
+                (
+                    Some("/project/util.c".to_string()),
+                    Some(4),
+                    vec!["0x1003".to_string(), "0x1004".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_restart_preserves_selection(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_restart_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let (_events, handled_rx) = handle_events(&client);
+
+        let thread = |id| Thread {
+            id,
+            name: "worker".into(),
+        };
+        let stop_and_refresh = |thread_id, frame_ids: [u64; 2]| {
+            let client = client.clone();
+            let adapter = &adapter;
+            let handled_rx = &handled_rx;
+            async move {
+                adapter
+                    .send_event(Events::Stopped(stopped_event(
+                        thread_id,
+                        StoppedEventReason::Breakpoint,
+                    )))
+                    .await;
+                handled_rx.recv().await.unwrap();
+
+                let (result, _) = futures::join!(client.refresh_thread(thread_id), async {
+                    adapter
+                        .respond::<StackTrace>(dap_types::StackTraceResponse {
+                            stack_frames: vec![
+                                stack_frame(frame_ids[0], "process"),
+                                stack_frame(frame_ids[1], "run"),
+                            ],
+                            total_frames: None,
+                        })
+                        .await;
+                    adapter
+                        .respond::<Scopes>(dap_types::ScopesResponse { scopes: vec![] })
+                        .await;
+                });
+                result.unwrap();
+            }
+        };
+
+        adapter.send_event(thread_started_event(1)).await;
+        handled_rx.recv().await.unwrap();
+        let (threads, _) = futures::join!(
+            client.threads(),
+            adapter.respond::<Threads>(ThreadsResponse {
+                threads: vec![thread(1)],
+            })
+        );
+        threads.unwrap();
+        stop_and_refresh(1, [10, 11]).await;
+        client.set_current_thread_id(Some(1));
+        client
+            .thread_states()
+            .get_mut(&1)
+            .unwrap()
+            .current_stack_frame_id = Some(11);
+
+        let (result, _) =
+            futures::join!(client.restart_session_preserving_selection(None), async {
+                adapter.respond::<Restart>(()).await;
+                adapter
+                    .respond::<Threads>(ThreadsResponse {
+                        threads: vec![thread(5)],
+                    })
+                    .await;
+            });
+        result.unwrap();
+        assert_eq!(client.current_thread_id(), None);
+
+        stop_and_refresh(5, [20, 21]).await;
+        assert_eq!(client.current_thread_id(), Some(5));
+        assert_eq!(
+            client.thread_state_by_id(5).current_stack_frame_id,
+            Some(21)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_write_memory(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_write_memory_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        let (response, arguments) = futures::join!(
+            client.write_memory("0x1000".into(), None, vec![0xca, 0xfe], true),
+            adapter.respond::<WriteMemory>(WriteMemoryResponse {
+                offset: None,
+                bytes_written: Some(1),
+            })
+        );
+        assert_eq!(response.unwrap().bytes_written, Some(1));
+        assert_eq!(arguments["memoryReference"], "0x1000");
+        assert_eq!(arguments["data"], base64::encode([0xca, 0xfe]));
+        assert_eq!(arguments["allowPartial"], true);
+    }
+
+    #[gpui::test]
+    async fn test_disassemble_with_fewer_instructions(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_disassemble_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        let (instructions, arguments) = futures::join!(
+            client.disassemble("0x2000".into(), Some(16), Some(-2), 4),
+            adapter.respond::<Disassemble>(DisassembleResponse {
+                instructions: vec![DisassembledInstruction {
+                    address: "0x2010".into(),
+                    instruction_bytes: None,
+                    instruction: "ret".into(),
+                    symbol: None,
+                    location: None,
+                    line: None,
+                    column: None,
+                    end_line: None,
+                    end_column: None,
+                    presentation_hint: None,
+                }],
+            })
+        );
+        let instructions = instructions.unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].instruction, "ret");
+        assert_eq!(arguments["offset"], 16);
+        assert_eq!(arguments["instructionOffset"], -2);
+        assert_eq!(arguments["instructionCount"], 4);
+    }
+
+    #[gpui::test]
+    async fn test_breakpoint_hit_conditions(cx: &mut TestAppContext) {
+        let path: Arc<Path> = Path::new("/project/main.rs").into();
+        let source_breakpoint = |line| SourceBreakpoint {
+            line,
+            column: None,
+            condition: None,
+            hit_condition: None,
+            log_message: None,
+            mode: None,
+        };
+        let breakpoint = |line| Breakpoint {
+            id: None,
+            verified: true,
+            message: None,
+            source: None,
+            line: Some(line),
+            column: None,
+            end_line: None,
+            end_column: None,
+            instruction_reference: None,
+            offset: None,
+            reason: None,
+        };
+
+        for supported in [true, false] {
+            let (client, adapter) = initialized_fake_client(
+                Capabilities {
+                    supports_hit_conditional_breakpoints: Some(supported),
+                    ..Default::default()
+                },
+                cx,
+            );
+
+            let (response, _) = futures::join!(
+                client.set_breakpoints(
+                    path.clone(),
+                    Some(vec![source_breakpoint(3), source_breakpoint(8)])
+                ),
+                adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                    breakpoints: vec![breakpoint(3), breakpoint(8)],
+                })
+            );
+            response.unwrap();
+
+            assert!(client
+                .set_breakpoint_hit_condition(path.clone(), 8, Some("often".into()))
+                .await
+                .is_err());
+
+            let (response, arguments) = futures::join!(
+                client.set_breakpoint_hit_condition(path.clone(), 8, Some(">= 5".into())),
+                adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                    breakpoints: vec![breakpoint(3), breakpoint(8)],
+                })
+            );
+            response.unwrap();
+
+            let breakpoints = arguments["breakpoints"].as_array().unwrap();
+            assert_eq!(breakpoints.len(), 2);
+            assert_eq!(breakpoints[0]["line"], 3);
+            assert!(breakpoints[0]["hitCondition"].is_null());
+            if supported {
+                assert_eq!(breakpoints[1]["hitCondition"], ">= 5");
+            } else {
+                assert!(breakpoints[1]["hitCondition"].is_null());
+            }
+        }
+    }
+
+    #[gpui::test]
+    async fn test_thread_states_snapshot(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(2)).await;
+        adapter.send_event(thread_started_event(1)).await;
+        for _ in 0..2 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        {
+            let mut thread_states = client.thread_states();
+            let thread_state = thread_states.get_mut(&1).unwrap();
+            thread_state.status = ThreadStatus::Stopped;
+            thread_state.stop_reason = Some(StoppedEventReason::Step);
+            thread_state.stack_frames = vec![
+                StackFrame {
+                    source: Some(source("/project/main.rs")),
+                    ..stack_frame(10, "parse")
+                },
+                stack_frame(11, "main"),
+            ];
+            thread_state.scopes.insert(10, vec![scope("Locals", 5)]);
+        }
+
+        assert_eq!(
+            client.thread_states_snapshot(),
+            [
+                ThreadSummary {
+                    thread_id: 1,
+                    name: None,
+                    status: ThreadStatus::Stopped,
+                    stop_reason: Some(StoppedEventReason::Step),
+                    top_frame: Some(StackFrameSummary {
+                        id: 10,
+                        name: "parse".into(),
+                        path: Some("/project/main.rs".into()),
+                        line: 1,
+                    }),
+                },
+                ThreadSummary {
+                    thread_id: 2,
+                    name: None,
+                    status: ThreadStatus::Running,
+                    stop_reason: None,
+                    top_frame: None,
+                },
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_data_breakpoints_that_cannot_persist(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_data_breakpoints: Some(true),
+                supports_restart_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        for (name, can_persist) in [("global", true), ("local", false)] {
+            let (info, arguments) = futures::join!(
+                client.data_breakpoint_info(Some(3), name.into()),
+                adapter.respond::<DataBreakpointInfo>(DataBreakpointInfoResponse {
+                    data_id: Some(format!("{name}-id")),
+                    description: name.into(),
+                    access_types: Some(vec![DataBreakpointAccessType::Write]),
+                    can_persist: Some(can_persist),
+                })
+            );
+            assert_eq!(info.unwrap().data_id, Some(format!("{name}-id")));
+            assert_eq!(arguments["name"], name);
+            assert_eq!(arguments["variablesReference"], 3);
+        }
+
+        let data_breakpoint = |data_id: &str| DataBreakpoint {
+            data_id: data_id.into(),
+            access_type: Some(DataBreakpointAccessType::Write),
+            condition: None,
+            hit_condition: None,
+        };
+        let (response, arguments) = futures::join!(
+            client.set_data_breakpoints(vec![
+                data_breakpoint("global-id"),
+                data_breakpoint("local-id"),
+            ]),
+            adapter.respond::<SetDataBreakpoints>(SetDataBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+        );
+        response.unwrap();
+        assert_eq!(arguments["breakpoints"][1]["accessType"], "write");
+        assert_eq!(client.data_breakpoints().len(), 2);
+
+        let (result, _) =
+            futures::join!(client.restart_session_preserving_selection(None), async {
+                adapter.respond::<Restart>(()).await;
+                let arguments = adapter
+                    .respond::<SetDataBreakpoints>(SetDataBreakpointsResponse {
+                        breakpoints: Vec::new(),
+                    })
+                    .await;
+                assert_eq!(arguments["breakpoints"].as_array().unwrap().len(), 1);
+                adapter
+                    .respond::<Threads>(ThreadsResponse {
+                        threads: Vec::new(),
+                    })
+                    .await;
+            });
+        result.unwrap();
+
+        let data_ids = client
+            .data_breakpoints()
+            .into_iter()
+            .map(|breakpoint| breakpoint.data_id)
+            .collect::<Vec<_>>();
+        assert_eq!(data_ids, ["global-id"]);
+    }
+
+    #[gpui::test]
+    async fn test_completions(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_completions_request: Some(true),
+                completion_trigger_characters: Some(vec![".".into(), "::".into()]),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        assert!(client.is_completion_trigger("user."));
+        assert!(client.is_completion_trigger("std::"));
+        assert!(!client.is_completion_trigger("user"));
+
+        let (items, arguments) = futures::join!(
+            client.completions("user.na".into(), 8, Some(1)),
+            adapter.respond::<Completions>(CompletionsResponse {
+                targets: vec![CompletionItem {
+                    label: "name".into(),
+                    text: None,
+                    sort_text: None,
+                    detail: None,
+                    type_: Some(CompletionItemType::Field),
+                    start: None,
+                    length: None,
+                    selection_start: None,
+                    selection_length: None,
+                }],
+            })
+        );
+        let items = items.unwrap();
+        assert_eq!(items[0].label, "name");
+        assert_eq!(items[0].type_, Some(CompletionItemType::Field));
+        assert_eq!(arguments["text"], "user.na");
+        assert_eq!(arguments["column"], 8);
+        assert_eq!(arguments["frameId"], 1);
+    }
+
+    #[gpui::test]
+    async fn test_exception_stop(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_exception_info_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                text: Some("ValueError: invalid literal".into()),
+                ..stopped_event(1, StoppedEventReason::Exception)
+            }))
+            .await;
+        for _ in 0..2 {
+            handled_rx.recv().await.unwrap();
+        }
+        assert_eq!(
+            client
+                .thread_state_by_id(1)
+                .exception_description
+                .as_deref(),
+            Some("ValueError: invalid literal")
+        );
+
+        let (response, arguments) = futures::join!(
+            client.exception_info(1),
+            adapter.respond::<ExceptionInfo>(ExceptionInfoResponse {
+                exception_id: "ValueError".into(),
+                description: Some("invalid literal".into()),
+                break_mode: ExceptionBreakMode::Unhandled,
+                details: Some(ExceptionDetails {
+                    message: None,
+                    type_name: Some("ValueError".into()),
+                    full_type_name: None,
+                    evaluate_name: None,
+                    stack_trace: Some("main.py:3".into()),
+                    inner_exception: None,
+                }),
+            })
+        );
+        let response = response.unwrap();
+        assert_eq!(response.exception_id, "ValueError");
+        assert_eq!(
+            response.details.unwrap().stack_trace.as_deref(),
+            Some("main.py:3")
+        );
+        assert_eq!(arguments["threadId"], 1);
+
+        adapter
+            .send_event(Events::Stopped(stopped_event(1, StoppedEventReason::Step)))
+            .await;
+        handled_rx.recv().await.unwrap();
+        assert_eq!(client.thread_state_by_id(1).exception_description, None);
+    }
+
+    #[gpui::test]
+    fn test_updating_the_status_of_an_unknown_thread(cx: &mut TestAppContext) {
+        let (client, _adapter) = fake_client(cx);
+        client.update_thread_state_status(7, ThreadStatus::Stopped);
+        assert_eq!(
+            client.thread_states().get(&7).map(|thread| thread.status),
+            Some(ThreadStatus::Stopped)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_stopped_event_of_an_unknown_thread(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter
+            .send_event(Events::Stopped(stopped_event(
+                7,
+                StoppedEventReason::Breakpoint,
+            )))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        assert!(client.thread_states().contains_key(&7));
+        let thread_state = client.thread_state_by_id(7);
+        assert_eq!(thread_state.status, ThreadStatus::Stopped);
+        assert_eq!(thread_state.stop_count, 1);
+        assert_eq!(
+            thread_state.stop_reason,
+            Some(StoppedEventReason::Breakpoint)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_stopped_event_without_thread_id(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                thread_id: None,
+                ..stopped_event(1, StoppedEventReason::Pause)
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+
+        adapter
+            .respond::<Threads>(dap_types::ThreadsResponse {
+                threads: vec![
+                    Thread {
+                        id: 1,
+                        name: "main".into(),
+                    },
+                    Thread {
+                        id: 2,
+                        name: "worker".into(),
+                    },
+                ],
+            })
+            .await;
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadStopped {
+                thread_id: Some(1),
+                all_threads_stopped: true,
+                preserve_focus: false,
+            }
+        );
+        assert!(notifications.try_recv().is_err());
+        let thread_state = client.thread_state_by_id(2);
+        assert_eq!(thread_state.status, ThreadStatus::Stopped);
+        assert_eq!(thread_state.stop_reason, Some(StoppedEventReason::Pause));
+        assert_eq!(client.current_thread_id(), Some(1));
+    }
+
+    #[gpui::test]
+    async fn test_stopped_event_without_thread_id_when_threads_fail(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        handled_rx.recv().await.unwrap();
+        client.set_current_thread_id(Some(1));
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                thread_id: None,
+                ..stopped_event(1, StoppedEventReason::Pause)
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+
+        adapter.respond_with_error("no process running").await;
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadStopped {
+                thread_id: Some(1),
+                all_threads_stopped: true,
+                preserve_focus: false,
+            }
+        );
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+    }
+
+    #[gpui::test]
+    async fn test_stopped_event_preserving_focus(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let notifications = client.notifications();
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                preserve_focus_hint: Some(true),
+                ..stopped_event(1, StoppedEventReason::Breakpoint)
+            }))
+            .await;
+        for _ in 0..2 {
+            handled_rx.recv().await.unwrap();
+        }
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadStopped {
+                thread_id: Some(1),
+                all_threads_stopped: false,
+                preserve_focus: true,
+            }
+        );
+
+        // the current thread isn't changed when all threads stopped
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                thread_id: None,
+                preserve_focus_hint: Some(true),
+                ..stopped_event(1, StoppedEventReason::Pause)
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        adapter
+            .respond::<Threads>(dap_types::ThreadsResponse {
+                threads: vec![Thread {
+                    id: 1,
+                    name: "main".into(),
+                }],
+            })
+            .await;
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadsUpdated
+        );
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            ClientNotification::ThreadStopped {
+                thread_id: None,
+                all_threads_stopped: true,
+                preserve_focus: true,
+            }
+        );
+        assert_eq!(client.current_thread_id(), None);
+    }
+
+    #[gpui::test]
+    async fn test_hit_breakpoint_ids(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                hit_breakpoint_ids: Some(vec![4, 5]),
+                ..stopped_event(1, StoppedEventReason::Breakpoint)
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        handled_rx.recv().await.unwrap();
+        assert_eq!(
+            client.thread_stop_reason(1),
+            Some(StoppedEventReason::Breakpoint)
+        );
+        assert_eq!(client.hit_breakpoint_ids(1), [4, 5]);
+
+        adapter
+            .send_event(Events::Continued(ContinuedEvent {
+                thread_id: 1,
+                all_threads_continued: Some(false),
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        assert_eq!(client.thread_stop_reason(1), None);
+        assert!(client.hit_breakpoint_ids(1).is_empty());
+    }
+
+    #[gpui::test]
+    async fn test_breakpoint_locations(cx: &mut TestAppContext) {
+        let source = Source {
+            path: Some("/project/main.rs".into()),
+            name: None,
+            source_reference: None,
+            presentation_hint: None,
+            origin: None,
+            sources: None,
+            adapter_data: None,
+            checksums: None,
+        };
+        let location = |line| BreakpointLocation {
+            line,
+            column: None,
+            end_line: None,
+            end_column: None,
+        };
+
+        let (client, _adapter) =
+            DebugAdapterClient::with_capabilities(Capabilities::default(), cx.executor());
+        assert!(client
+            .breakpoint_locations(source.clone(), 1, None)
+            .await
+            .is_err());
+
+        let (client, adapter) = DebugAdapterClient::with_capabilities(
+            Capabilities {
+                supports_breakpoint_locations_request: Some(true),
+                ..Default::default()
+            },
+            cx.executor(),
+        );
+        let (locations, arguments) = futures::join!(
+            client.breakpoint_locations(source, 1, Some(10)),
+            adapter.respond::<BreakpointLocations>(BreakpointLocationsResponse {
+                breakpoints: vec![location(2), location(6), location(10)],
+            })
+        );
+        let locations = locations.unwrap();
+        assert_eq!(arguments["line"], 1);
+        assert_eq!(arguments["endLine"], 10);
+        assert_eq!(arguments["source"]["path"], "/project/main.rs");
+
+        let nearest_line = |line| nearest_breakpoint_location(&locations, line).map(|l| l.line);
+        assert_eq!(nearest_line(1), Some(2));
+        assert_eq!(nearest_line(5), Some(6));
+        assert_eq!(nearest_line(8), Some(10));
+        assert_eq!(nearest_line(20), Some(10));
+        assert!(nearest_breakpoint_location(&[], 1).is_none());
+    }
+
+    #[gpui::test]
+    async fn test_thread_stop_count(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        for thread_id in [1, 2] {
+            adapter.send_event(thread_started_event(thread_id)).await;
+        }
+        adapter
+            .send_event(Events::Stopped(stopped_event(
+                1,
+                StoppedEventReason::Breakpoint,
+            )))
+            .await;
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                all_threads_stopped: Some(true),
+                ..stopped_event(1, StoppedEventReason::Breakpoint)
+            }))
+            .await;
+        for _ in 0..4 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        assert_eq!(client.thread_stop_count(1), 2);
+        assert_eq!(client.thread_stop_count(2), 1);
+        assert_eq!(client.thread_stop_count(3), 0);
+    }
+
+    #[gpui::test]
+    async fn test_breakpoint_hit_count(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+        let path: Arc<Path> = Path::new("/project/main.rs").into();
+        let breakpoint = |id| Breakpoint {
+            id: Some(id),
+            verified: true,
+            message: None,
+            source: None,
+            line: Some(id),
+            column: None,
+            end_line: None,
+            end_column: None,
+            instruction_reference: None,
+            offset: None,
+            reason: None,
+        };
+        let source_breakpoint = |line| SourceBreakpoint {
+            line,
+            column: None,
+            condition: None,
+            hit_condition: None,
+            log_message: None,
+            mode: None,
+        };
+
+        let (result, _) = futures::join!(
+            client.set_breakpoints(
+                path.clone(),
+                Some(vec![source_breakpoint(4), source_breakpoint(5)])
+            ),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: vec![breakpoint(4), breakpoint(5)],
+            })
+        );
+        result.unwrap();
+
+        adapter.send_event(thread_started_event(1)).await;
+        handled_rx.recv().await.unwrap();
+        for hit_breakpoint_ids in [vec![4], vec![4, 5], vec![4]] {
+            adapter
+                .send_event(Events::Stopped(StoppedEvent {
+                    hit_breakpoint_ids: Some(hit_breakpoint_ids),
+                    ..stopped_event(1, StoppedEventReason::Breakpoint)
+                }))
+                .await;
+            handled_rx.recv().await.unwrap();
+        }
+        assert_eq!(client.breakpoint_hit_count(4), 3);
+        assert_eq!(client.breakpoint_hit_count(5), 1);
+        assert_eq!(client.breakpoint_hit_count(6), 0);
+
+        let (result, _) = futures::join!(
+            client.set_breakpoints(path.clone(), Some(vec![source_breakpoint(4)])),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: vec![breakpoint(4)],
+            })
+        );
+        result.unwrap();
+        assert_eq!(client.breakpoint_hit_count(4), 3);
+        assert_eq!(client.breakpoint_hit_count(5), 0);
+    }
+
+    #[gpui::test]
+    async fn test_breakpoint_updates_are_debounced(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let main: Arc<Path> = Path::new("/project/main.rs").into();
+        let lib: Arc<Path> = Path::new("/project/lib.rs").into();
+        let breakpoints = |rows: &[u64]| {
+            rows.iter()
+                .map(|&row| StoredBreakpoint {
+                    row,
+                    condition: None,
+                    enabled: true,
+                })
+                .map(|breakpoint| breakpoint.to_source_breakpoint())
+                .collect::<Vec<_>>()
+        };
+
+        client.update_breakpoints_debounced(main.clone(), breakpoints(&[3]));
+        client.update_breakpoints_debounced(lib.clone(), breakpoints(&[1]));
+        client.update_breakpoints_debounced(main.clone(), breakpoints(&[3, 5]));
+        client.update_breakpoints_debounced(main.clone(), breakpoints(&[5]));
+        cx.executor().advance_clock(BREAKPOINT_UPDATE_DEBOUNCE);
+
+        let mut requested = Vec::new();
+        for _ in 0..2 {
+            let arguments = adapter
+                .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                    breakpoints: Vec::new(),
+                })
+                .await;
+            requested.push((
+                arguments["source"]["path"].as_str().unwrap().to_string(),
+                arguments["breakpoints"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|breakpoint| breakpoint["line"].as_u64().unwrap())
+                    .collect::<Vec<_>>(),
+            ));
+        }
+        requested.sort();
+        assert_eq!(
+            requested,
+            [
+                ("/project/lib.rs".to_string(), vec![2]),
+                ("/project/main.rs".to_string(), vec![6]),
+            ]
+        );
+
+        cx.executor().advance_clock(BREAKPOINT_UPDATE_DEBOUNCE * 2);
+        cx.executor().run_until_parked();
+        assert!(adapter.requests.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_replay_stored_breakpoints(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let main = Path::new("/project/main.rs");
+        let lib = Path::new("/project/lib.rs");
+
+        let mut store = BreakpointStore::default();
+        assert!(store.toggle(main, 3));
+        assert!(store.toggle(main, 8));
+        assert!(store.toggle(main, 5));
+        assert!(!store.toggle(main, 5));
+        store.set_condition(main, 8, Some("i > 2".into()));
+        assert!(store.toggle(lib, 1));
+        store.set_enabled(lib, 1, false);
+
+        // the store outlives the session, e.g. by being serialized
+        let store: BreakpointStore =
+            serde_json::from_value(serde_json::to_value(&store).unwrap()).unwrap();
+
+        let (result, arguments) = futures::join!(
+            client.replay_breakpoints(&store),
+            adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+        );
+        result.unwrap();
+        assert_eq!(arguments["source"]["path"], "/project/main.rs");
+        let breakpoints = arguments["breakpoints"].as_array().unwrap();
+        assert_eq!(breakpoints.len(), 2);
+        // the rows of the editor are sent as one-based lines
+        assert_eq!(breakpoints[0]["line"], 4);
+        assert_eq!(breakpoints[0]["condition"], Value::Null);
+        assert_eq!(breakpoints[1]["line"], 9);
+        assert_eq!(breakpoints[1]["condition"], "i > 2");
+        assert!(adapter.requests.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_exception_breakpoints_are_resent_after_restart(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_restart_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        let (response, _) = futures::join!(
+            client.set_exception_breakpoints(vec!["uncaught".into()], None, None),
+            adapter.respond::<SetExceptionBreakpoints>(SetExceptionBreakpointsResponse {
+                breakpoints: None,
+            })
+        );
+        response.unwrap();
+
+        let (result, _) =
+            futures::join!(client.restart_session_preserving_selection(None), async {
+                adapter.respond::<Restart>(()).await;
+                let arguments = adapter
+                    .respond::<SetExceptionBreakpoints>(SetExceptionBreakpointsResponse {
+                        breakpoints: None,
+                    })
+                    .await;
+                assert_eq!(arguments["filters"], json!(["uncaught"]));
+                adapter
+                    .respond::<Threads>(ThreadsResponse {
+                        threads: Vec::new(),
+                    })
+                    .await;
+            });
+        result.unwrap();
+    }
+
+    #[gpui::test]
+    fn test_spawn_summary_hash(cx: &mut TestAppContext) {
+        let summary = SpawnSummary {
+            command: "node".into(),
+            args: vec!["adapter.js".into(), "--port=8000".into()],
+            env: BTreeMap::from_iter([("NODE_ENV".into(), "development".into())]),
+            cwd: PathBuf::from("/project"),
+        };
+        assert_eq!(summary.hash_value(), summary.clone().hash_value());
+
+        let mut changed_arg = summary.clone();
+        changed_arg.args[1] = "--port=8001".into();
+        assert_ne!(summary.hash_value(), changed_arg.hash_value());
+
+        let (client, _adapter) = fake_client(cx);
+        assert_eq!(client.spawn_summary_hash(), None);
+    }
+
+    #[gpui::test]
+    async fn test_requests_require_capabilities(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let error = client
+            .require_capability(
+                |capabilities| capabilities.supports_restart_request == Some(true),
+                "restarting",
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "debug adapter does not support restarting"
+        );
+
+        let error = client
+            .disassemble("0x1000".into(), None, None, 1)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "debug adapter does not support disassembling"
+        );
+
+        let error = client
+            .step_back(1, SteppingGranularity::Statement)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "debug adapter does not support stepping back"
+        );
+        let error = client.restart_frame(1, 1).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "debug adapter does not support restarting frames"
+        );
+        let error = client.terminate().await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "debug adapter does not support terminating"
+        );
+        assert!(adapter.requests.try_recv().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_continue_thread_and_wait(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_single_thread_execution_requests: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+        let (_events, handled_rx) = handle_events(&client);
+
+        adapter.send_event(thread_started_event(1)).await;
+        handled_rx.recv().await.unwrap();
+
+        let (reason, arguments) = futures::join!(
+            client.continue_thread_and_wait(1, Duration::from_secs(5)),
+            async {
+                let arguments = adapter
+                    .respond::<Continue>(ContinueResponse {
+                        all_threads_continued: Some(false),
+                    })
+                    .await;
+                adapter
+                    .send_event(Events::Stopped(stopped_event(
+                        1,
+                        StoppedEventReason::Breakpoint,
+                    )))
+                    .await;
+                arguments
+            }
+        );
+        assert_eq!(reason.unwrap(), StoppedEventReason::Breakpoint);
+        assert_eq!(arguments["threadId"], 1);
+        assert_eq!(arguments["singleThread"], true);
+        handled_rx.recv().await.unwrap();
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+
+        let continued = cx.executor().spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .continue_thread_and_wait(1, Duration::from_millis(10))
+                    .await
+            }
+        });
+        adapter
+            .respond::<Continue>(ContinueResponse {
+                all_threads_continued: Some(false),
+            })
+            .await;
+        cx.executor().run_until_parked();
+        cx.executor().advance_clock(Duration::from_millis(10));
+        assert_eq!(
+            continued.await.unwrap_err().to_string(),
+            "timed out waiting for thread 1 to stop"
+        );
+    }
+
+    #[gpui::test]
+    async fn test_stop_before_the_continue_response(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        for thread_id in [1, 2] {
+            adapter.send_event(thread_started_event(thread_id)).await;
+        }
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                all_threads_stopped: Some(true),
+                ..stopped_event(1, StoppedEventReason::Pause)
+            }))
+            .await;
+        for _ in 0..3 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        let (result, _) = futures::join!(client.continue_thread(1), async {
+            let Ok(Payload::Request(request)) = adapter.requests.recv().await else {
+                panic!("expected a request");
+            };
+            assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Running);
+
+            // the thread hits a breakpoint before the response arrives
+            adapter
+                .send_event(Events::Stopped(stopped_event(
+                    1,
+                    StoppedEventReason::Breakpoint,
+                )))
+                .await;
+            handled_rx.recv().await.unwrap();
+            adapter
+                .responses
+                .send(Payload::Response(Response {
+                    seq: 0,
+                    request_seq: request.seq,
+                    success: true,
+                    command: request.command,
+                    message: None,
+                    body: Some(json!({})),
+                }))
+                .await
+                .unwrap();
+        });
+        result.unwrap();
+
+        let thread_state = client.thread_state_by_id(1);
+        assert_eq!(thread_state.status, ThreadStatus::Stopped);
+        assert_eq!(
+            thread_state.stop_reason,
+            Some(StoppedEventReason::Breakpoint)
+        );
+        assert_eq!(client.thread_state_by_id(2).status, ThreadStatus::Running);
+
+        // a failed request leaves the thread stopped
+        let (result, _) = futures::join!(
+            client.continue_thread(1),
+            adapter.respond_with_error("not now")
+        );
+        assert!(result.is_err());
+        assert_eq!(client.thread_state_by_id(1).status, ThreadStatus::Stopped);
+    }
+
+    #[gpui::test]
+    async fn test_continue_without_all_threads_continued(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        for thread_id in [1, 2] {
+            adapter.send_event(thread_started_event(thread_id)).await;
+        }
+        adapter
+            .send_event(Events::Stopped(StoppedEvent {
+                all_threads_stopped: Some(true),
+                ..stopped_event(1, StoppedEventReason::Pause)
+            }))
+            .await;
+        for _ in 0..3 {
+            handled_rx.recv().await.unwrap();
+        }
+
+        let (result, _) = futures::join!(
+            client.continue_thread(1),
+            adapter.respond::<Continue>(ContinueResponse {
+                all_threads_continued: None,
+            })
+        );
+        result.unwrap();
+        for thread_id in [1, 2] {
+            assert_eq!(
+                client.thread_state_by_id(thread_id).status,
+                ThreadStatus::Running
+            );
+        }
+
+        let (result, _) = futures::join!(
+            client.continue_thread(1),
+            adapter.respond::<Continue>(ContinueResponse {
+                all_threads_continued: Some(false),
+            })
+        );
+        result.unwrap();
+        client.update_thread_state_status(2, ThreadStatus::Stopped);
+        adapter
+            .send_event(Events::Continued(ContinuedEvent {
+                thread_id: 1,
+                all_threads_continued: None,
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        assert_eq!(client.thread_state_by_id(2).status, ThreadStatus::Running);
+    }
+
+    #[gpui::test]
+    async fn test_evaluate_with_timeout_cancels(cx: &mut TestAppContext) {
+        let (client, adapter) = initialized_fake_client(
+            Capabilities {
+                supports_cancel_request: Some(true),
+                ..Default::default()
+            },
+            cx,
+        );
+
+        let (result, _) = futures::join!(
+            client.evaluate_with_timeout("slow()".into(), None, None, Duration::from_millis(50)),
+            async {
+                let Ok(Payload::Request(evaluate)) = adapter.requests.recv().await else {
+                    panic!("expected an evaluate request");
+                };
+                assert_eq!(evaluate.command, "evaluate");
+                cx.executor().advance_clock(Duration::from_millis(50));
+
+                let arguments = adapter.respond::<Cancel>(()).await;
+                assert_eq!(arguments["requestId"], evaluate.seq);
+            }
+        );
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+    }
+
+    #[gpui::test]
+    async fn test_responses_are_matched_by_request_seq(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+
+        let (threads, scopes, _) = futures::join!(client.threads(), client.scopes(3), async {
+            let mut requests = Vec::new();
+            for _ in 0..2 {
+                let Ok(Payload::Request(request)) = adapter.requests.recv().await else {
+                    panic!("expected a request");
+                };
+                requests.push(request);
+            }
+
+            for request in requests.into_iter().rev() {
+                let body = match request.command.as_str() {
+                    "threads" => json!({ "threads": [{ "id": 1, "name": "main" }] }),
+                    "scopes" => json!({ "scopes": [] }),
+                    command => panic!("unexpected {command} request"),
+                };
+                adapter
+                    .responses
+                    .send(Payload::Response(Response {
+                        seq: 0,
+                        request_seq: request.seq,
+                        success: true,
+                        command: request.command,
+                        message: None,
+                        body: Some(body),
+                    }))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        assert_eq!(threads.unwrap()[0].name, "main");
+        assert!(scopes.unwrap().is_empty());
+    }
+
+    #[gpui::test]
+    async fn test_frames_resolve_their_module(cx: &mut TestAppContext) {
+        let (client, adapter) = fake_client(cx);
+        let (_events, handled_rx) = handle_events(&client);
+
+        let module = |id, name: &str| Module {
+            id,
+            name: name.into(),
+            path: None,
+            is_optimized: None,
+            is_user_code: None,
+            version: None,
+            symbol_status: None,
+            symbol_file_path: None,
+            date_time_stamp: None,
+            address_range: None,
+        };
+        for module in [
+            module(ModuleId::Number(1), "app"),
+            module(ModuleId::String("libc".into()), "libc.so.6"),
+        ] {
+            adapter
+                .send_event(Events::Module(ModuleEvent {
+                    reason: ModuleEventReason::New,
+                    module,
+                }))
+                .await;
+            handled_rx.recv().await.unwrap();
+        }
+
+        let frame = StackFrame {
+            module_id: Some(ModuleId::String("libc".into())),
+            ..stack_frame(1, "read")
+        };
+        assert_eq!(client.module_for_frame(&frame).unwrap().name, "libc.so.6");
+        assert!(client.module_for_frame(&stack_frame(2, "main")).is_none());
+
+        adapter
+            .send_event(Events::Module(ModuleEvent {
+                reason: ModuleEventReason::Removed,
+                module: module(ModuleId::String("libc".into()), "libc.so.6"),
+            }))
+            .await;
+        handled_rx.recv().await.unwrap();
+        assert!(client.module_for_frame(&frame).is_none());
+    }
+}
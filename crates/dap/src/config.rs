@@ -0,0 +1,105 @@
+use collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Represents the host information of the debug adapter
+#[derive(Default, Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct TCPHost {
+    /// The port that the debug adapter is listening on
+    pub port: Option<u16>,
+    /// The host that the debug adapter is listening too
+    pub host: Option<[u8; 4]>,
+    /// The delay in ms between starting and connecting to the debug adapter
+    pub delay: Option<u64>,
+    /// The time in ms the debug adapter has to start accepting connections after it was spawned
+    pub connect_timeout: Option<u64>,
+}
+
+/// Represents the type that will determine which request to call on the debug adapter
+#[derive(Default, Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugRequestType {
+    /// Call the `launch` request on the debug adapter
+    #[default]
+    Launch,
+    /// Call the `attach` request on the debug adapter
+    Attach,
+}
+
+/// Where the output of a launched debuggee is shown, sent as `console` with the `launch`
+/// request
+#[derive(Default, Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum DebugConsoleKind {
+    /// In the debug console, through `output` events of the adapter
+    #[default]
+    InternalConsole,
+    /// In a terminal of the workspace, through a `runInTerminal` request
+    IntegratedTerminal,
+    /// In a terminal window, through a `runInTerminal` request
+    ExternalTerminal,
+}
+
+/// Represents the type of the debug adapter connection
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "lowercase", tag = "connection")]
+pub enum DebugAdapterKind {
+    /// Connect to the debug adapter via TCP
+    TCP(TCPHost),
+    /// Connect to the debug adapter via STDIO
+    STDIO,
+}
+
+impl Default for DebugAdapterKind {
+    fn default() -> Self {
+        DebugAdapterKind::TCP(TCPHost::default())
+    }
+}
+
+/// Represents the configuration for the debug adapter
+#[derive(Default, Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub struct DebugAdapterConfig {
+    /// Unique id of for the debug adapter,
+    /// that will be send with the `initialize` request
+    pub id: String,
+    /// The type of connection the adapter should use
+    #[serde(default, flatten)]
+    pub transport: DebugAdapterKind,
+    /// The type of request that should be called on the debug adapter
+    #[serde(default)]
+    pub request: DebugRequestType,
+    /// The program that you trying to debug
+    pub program: Option<String>,
+    /// Where the output of the launched program is shown
+    #[serde(default)]
+    pub console: DebugConsoleKind,
+    /// Additional initialization arguments to be sent on DAP initialization
+    pub initialize_args: Option<Value>,
+    /// The time in ms the debug adapter has to respond to the `initialize` request
+    pub initialize_timeout: Option<u64>,
+    /// The time in ms the debug adapter has to send the `initialized` event after it was
+    /// launched or attached to
+    pub initialized_timeout: Option<u64>,
+    /// Configure the debug adapter anyway when it didn't send the `initialized` event in
+    /// time, instead of failing to start the session
+    #[serde(default)]
+    pub configure_without_initialized: bool,
+    /// The locale the debug adapter should use for its messages, e.g. `de-CH`, the
+    /// locale of the system when not set
+    pub locale: Option<String>,
+    /// Environment variables set for the debug adapter on top of the inherited ones
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The working directory of the debug adapter, the project path when not set
+    pub cwd: Option<PathBuf>,
+    /// Run the debuggee as a background process, without a terminal, when the adapter
+    /// asks to run it in a terminal and no terminal is available
+    #[serde(default)]
+    pub run_in_terminal_in_background: bool,
+    /// Log every message sent to and received from the debug adapter, to diagnose issues
+    /// of the adapter
+    #[serde(default)]
+    pub log_messages: bool,
+}
@@ -0,0 +1,522 @@
+use crate::{
+    adapters::DebugAdapterBinary,
+    breakpoint_store::BreakpointStore,
+    client::{ClientNotification, ConfigurationPhase, DebugAdapterClient, DebugAdapterClientId},
+    config::DebugAdapterConfig,
+};
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use gpui::{EventEmitter, ModelContext, Task};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use util::ResultExt;
+
+/// Fired whenever the sessions of the store or the state of one of them changes, so
+/// views only have to listen to the store instead of every session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DapStoreEvent {
+    ClientAdded(DebugAdapterClientId),
+    ClientRemoved(DebugAdapterClientId),
+    ActiveClientChanged(Option<DebugAdapterClientId>),
+    /// The breakpoints of the file were changed and sent to the configured sessions.
+    BreakpointsChanged(Arc<Path>),
+    /// The watch expressions of the project changed, e.g. so they can be persisted.
+    WatchesChanged,
+    /// The state of a session changed, e.g. one of its threads stopped.
+    Client {
+        client_id: DebugAdapterClientId,
+        notification: ClientNotification,
+    },
+}
+
+/// The debug sessions of a project, e.g. the ones of a compound configuration or the
+/// child sessions an adapter started, one of which is selected to be stepped through.
+/// It also owns the breakpoints and watches of the project, which outlive the sessions.
+#[derive(Default)]
+pub struct DapStore {
+    next_client_id: usize,
+    clients: BTreeMap<DebugAdapterClientId, Arc<DebugAdapterClient>>,
+    active_client_id: Option<DebugAdapterClientId>,
+    breakpoint_store: BreakpointStore,
+    watches: Vec<String>,
+    notification_tasks: HashMap<DebugAdapterClientId, Task<()>>,
+}
+
+impl EventEmitter<DapStoreEvent> for DapStore {}
+
+impl DapStore {
+    /// Returns an id no other client of this store has, for the next client to be created.
+    pub fn next_client_id(&mut self) -> DebugAdapterClientId {
+        let id = DebugAdapterClientId(self.next_client_id);
+        self.next_client_id += 1;
+        id
+    }
+
+    /// Spawns the debug adapter of a configuration and starts a session with it, e.g.
+    /// for the `StartDebugger` action.
+    ///
+    /// The session is added as soon as the adapter runs, so it can be shown while it is
+    /// still starting, and removed again when it fails to start.
+    pub fn start_client(
+        &mut self,
+        config: DebugAdapterConfig,
+        binary: DebugAdapterBinary,
+        project_path: PathBuf,
+        args: Option<Value>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Arc<DebugAdapterClient>>> {
+        let id = self.next_client_id();
+        cx.spawn(|this, mut cx| async move {
+            let client =
+                Arc::new(DebugAdapterClient::new(id, config, &binary, project_path, &cx).await?);
+            let breakpoint_store = this.update(&mut cx, |this, cx| {
+                this.add_client(client.clone(), cx);
+                this.breakpoint_store.clone()
+            })?;
+            cx.background_executor()
+                .spawn(DebugAdapterClient::handle_events(client.clone(), |_| {}))
+                .detach();
+
+            let result = async {
+                client.initialize().await?;
+                client.start(args, &breakpoint_store).await
+            }
+            .await
+            .with_context(|| {
+                format!(
+                    "debug session failed while {}",
+                    client.configuration_phase()
+                )
+            });
+            if result.is_err() {
+                this.update(&mut cx, |this, cx| this.remove_client(id, cx))
+                    .log_err();
+            }
+            result.map(|_| client)
+        })
+    }
+
+    /// Adds a session, which becomes the active one. Its notifications are forwarded as
+    /// [`DapStoreEvent::Client`] until it is removed.
+    pub fn add_client(&mut self, client: Arc<DebugAdapterClient>, cx: &mut ModelContext<Self>) {
+        let id = client.id();
+        self.next_client_id = self.next_client_id.max(id.0 + 1);
+
+        let notifications = client.notifications();
+        self.notification_tasks.insert(
+            id,
+            cx.spawn(|this, mut cx| async move {
+                while let Ok(notification) = notifications.recv().await {
+                    let emitted = this.update(&mut cx, |_, cx| {
+                        cx.emit(DapStoreEvent::Client {
+                            client_id: id,
+                            notification,
+                        })
+                    });
+                    if emitted.is_err() {
+                        break;
+                    }
+                }
+            }),
+        );
+
+        client.set_watches(self.watches.iter().cloned());
+        self.clients.insert(id, client);
+        self.active_client_id = Some(id);
+        cx.emit(DapStoreEvent::ClientAdded(id));
+        cx.emit(DapStoreEvent::ActiveClientChanged(Some(id)));
+    }
+
+    /// Removes a session, e.g. after it ended. When it was the active one, the most
+    /// recently started session that is left becomes active.
+    pub fn remove_client(
+        &mut self,
+        id: DebugAdapterClientId,
+        cx: &mut ModelContext<Self>,
+    ) -> Option<Arc<DebugAdapterClient>> {
+        let client = self.clients.remove(&id)?;
+        self.notification_tasks.remove(&id);
+        cx.emit(DapStoreEvent::ClientRemoved(id));
+        if self.active_client_id == Some(id) {
+            self.active_client_id = self.clients.keys().next_back().copied();
+            cx.emit(DapStoreEvent::ActiveClientChanged(self.active_client_id));
+        }
+        Some(client)
+    }
+
+    pub fn client_by_id(&self, id: DebugAdapterClientId) -> Option<Arc<DebugAdapterClient>> {
+        self.clients.get(&id).cloned()
+    }
+
+    /// The sessions in the order they were started.
+    pub fn clients(&self) -> impl Iterator<Item = &Arc<DebugAdapterClient>> {
+        self.clients.values()
+    }
+
+    /// Selects the session that stepping and continuing apply to, returning whether
+    /// the store has a session with that id.
+    pub fn set_active_client(
+        &mut self,
+        id: DebugAdapterClientId,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        let exists = self.clients.contains_key(&id);
+        if exists && self.active_client_id != Some(id) {
+            self.active_client_id = Some(id);
+            cx.emit(DapStoreEvent::ActiveClientChanged(Some(id)));
+        }
+        exists
+    }
+
+    pub fn active_client(&self) -> Option<Arc<DebugAdapterClient>> {
+        self.client_by_id(self.active_client_id?)
+    }
+
+    /// The breakpoints of the project, which are sent to new sessions with
+    /// [`DebugAdapterClient::start`].
+    pub fn breakpoint_store(&self) -> &BreakpointStore {
+        &self.breakpoint_store
+    }
+
+    /// Toggles the breakpoint on the row of the file and sends the breakpoints of the
+    /// file to all sessions, returning whether the row has a breakpoint now.
+    pub fn toggle_breakpoint(
+        &mut self,
+        path: Arc<Path>,
+        row: u64,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        let added = self.breakpoint_store.toggle(&path, row);
+        self.breakpoints_changed(path, cx);
+        added
+    }
+
+    pub fn set_breakpoint_condition(
+        &mut self,
+        path: Arc<Path>,
+        row: u64,
+        condition: Option<String>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.breakpoint_store.set_condition(&path, row, condition);
+        self.breakpoints_changed(path, cx);
+    }
+
+    pub fn set_breakpoint_enabled(
+        &mut self,
+        path: Arc<Path>,
+        row: u64,
+        enabled: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.breakpoint_store.set_enabled(&path, row, enabled);
+        self.breakpoints_changed(path, cx);
+    }
+
+    /// Disables the breakpoint on the row without removing it, or enables it again, see
+    /// [`BreakpointStore::toggle_enabled`].
+    pub fn toggle_breakpoint_enabled(
+        &mut self,
+        path: Arc<Path>,
+        row: u64,
+        cx: &mut ModelContext<Self>,
+    ) -> Option<bool> {
+        let enabled = self.breakpoint_store.toggle_enabled(&path, row)?;
+        self.breakpoints_changed(path, cx);
+        Some(enabled)
+    }
+
+    /// Records that a file with breakpoints was edited, so the next breakpoints sent for
+    /// it tell the sessions to resolve their lines again.
+    pub fn mark_source_modified(&self, path: &Path) {
+        if self.breakpoint_store.breakpoints(path).next().is_none() {
+            return;
+        }
+        for client in self.clients.values() {
+            client.mark_source_modified(path.into());
+        }
+    }
+
+    /// The watch expressions of the project, which every session evaluates.
+    pub fn watches(&self) -> &[String] {
+        &self.watches
+    }
+
+    /// Restores the watches that were persisted for the project.
+    pub fn set_watches(
+        &mut self,
+        expressions: impl IntoIterator<Item = String>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.watches.clear();
+        for expression in expressions {
+            if !self.watches.contains(&expression) {
+                self.watches.push(expression);
+            }
+        }
+        self.watches_changed(cx);
+    }
+
+    pub fn add_watch(&mut self, expression: String, cx: &mut ModelContext<Self>) {
+        if !self.watches.contains(&expression) {
+            self.watches.push(expression);
+            self.watches_changed(cx);
+        }
+    }
+
+    /// Replaces the expression of a watch, keeping its position in the list.
+    pub fn edit_watch(
+        &mut self,
+        expression: &str,
+        new_expression: String,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if self.watches.contains(&new_expression) {
+            self.watches.retain(|watch| watch != expression);
+        } else if let Some(watch) = self.watches.iter_mut().find(|watch| *watch == expression) {
+            *watch = new_expression;
+        }
+        self.watches_changed(cx);
+    }
+
+    pub fn remove_watch(&mut self, expression: &str, cx: &mut ModelContext<Self>) {
+        self.watches.retain(|watch| watch != expression);
+        self.watches_changed(cx);
+    }
+
+    fn watches_changed(&self, cx: &mut ModelContext<Self>) {
+        for client in self.clients.values() {
+            client.set_watches(self.watches.iter().cloned());
+        }
+        cx.emit(DapStoreEvent::WatchesChanged);
+    }
+
+    fn breakpoints_changed(&self, path: Arc<Path>, cx: &mut ModelContext<Self>) {
+        let breakpoints = self.breakpoint_store.source_breakpoints(&path);
+        // sessions that are still starting get the breakpoints of the store when they
+        // are configured
+        for client in self
+            .clients
+            .values()
+            .filter(|client| client.configuration_phase() >= ConfigurationPhase::BreakpointsSent)
+        {
+            client.update_breakpoints_debounced(path.clone(), breakpoints.clone());
+        }
+        cx.emit(DapStoreEvent::BreakpointsChanged(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::BREAKPOINT_UPDATE_DEBOUNCE;
+    use crate::config::DebugAdapterConfig;
+    use crate::transport::Events;
+    use dap_types::{requests::SetBreakpoints, SetBreakpointsResponse};
+    use futures::StreamExt;
+    use gpui::{Context, Model, TestAppContext};
+    use serde_json::json;
+
+    fn add_client(store: &Model<DapStore>, cx: &mut TestAppContext) -> DebugAdapterClientId {
+        store.update(cx, |store, cx| {
+            let id = store.next_client_id();
+            let (client, _) = DebugAdapterClient::new_fake(
+                id,
+                DebugAdapterConfig::default(),
+                cx.background_executor().clone(),
+            );
+            store.add_client(Arc::new(client), cx);
+            id
+        })
+    }
+
+    fn active_client_id(store: &DapStore) -> Option<DebugAdapterClientId> {
+        store.active_client().map(|client| client.id())
+    }
+
+    #[gpui::test]
+    fn test_active_client(cx: &mut TestAppContext) {
+        let store = cx.new_model(|_| DapStore::default());
+        store.read_with(cx, |store, _| assert!(store.active_client().is_none()));
+
+        let first = add_client(&store, cx);
+        let second = add_client(&store, cx);
+        let third = add_client(&store, cx);
+        store.update(cx, |store, cx| {
+            assert_eq!(active_client_id(store), Some(third));
+            assert_eq!(
+                store
+                    .clients()
+                    .map(|client| client.id())
+                    .collect::<Vec<_>>(),
+                [first, second, third]
+            );
+
+            assert!(store.set_active_client(first, cx));
+            assert!(!store.set_active_client(DebugAdapterClientId(10), cx));
+            assert_eq!(active_client_id(store), Some(first));
+
+            store.remove_client(second, cx);
+            assert_eq!(active_client_id(store), Some(first));
+            store.remove_client(first, cx);
+            assert_eq!(active_client_id(store), Some(third));
+            store.remove_client(third, cx);
+            assert!(store.active_client().is_none());
+
+            assert_eq!(store.next_client_id(), DebugAdapterClientId(3));
+        });
+    }
+
+    #[gpui::test]
+    async fn test_events(cx: &mut TestAppContext) {
+        let store = cx.new_model(|_| DapStore::default());
+        let mut events = cx.events(&store);
+
+        let (client, adapter) = DebugAdapterClient::new_fake(
+            store.update(cx, |store, _| store.next_client_id()),
+            DebugAdapterConfig::default(),
+            cx.executor(),
+        );
+        let client = Arc::new(client);
+        let id = client.id();
+        client.configure(&BreakpointStore::default()).await.unwrap();
+        store.update(cx, |store, cx| store.add_client(client.clone(), cx));
+        assert_eq!(events.next().await.unwrap(), DapStoreEvent::ClientAdded(id));
+        assert_eq!(
+            events.next().await.unwrap(),
+            DapStoreEvent::ActiveClientChanged(Some(id))
+        );
+
+        let _events = cx
+            .executor()
+            .spawn(DebugAdapterClient::handle_events(client.clone(), |_| {}));
+        adapter.send_event(Events::Terminated(None)).await;
+        assert_eq!(
+            events.next().await.unwrap(),
+            DapStoreEvent::Client {
+                client_id: id,
+                notification: ClientNotification::SessionEnded,
+            }
+        );
+
+        // breakpoints are sent to every configured session
+        let (starting_client, starting_adapter) = DebugAdapterClient::new_fake(
+            store.update(cx, |store, _| store.next_client_id()),
+            DebugAdapterConfig::default(),
+            cx.executor(),
+        );
+        let starting_id = starting_client.id();
+        store.update(cx, |store, cx| {
+            store.add_client(Arc::new(starting_client), cx);
+            store.set_active_client(id, cx);
+        });
+        while let Ok(Some(_)) = events.try_next() {}
+        let main_rs: Arc<Path> = Path::new("/project/main.rs").into();
+        assert!(store.update(cx, |store, cx| store.toggle_breakpoint(
+            main_rs.clone(),
+            2,
+            cx
+        )));
+        cx.executor().advance_clock(BREAKPOINT_UPDATE_DEBOUNCE);
+        assert_eq!(
+            events.next().await.unwrap(),
+            DapStoreEvent::BreakpointsChanged(main_rs.clone())
+        );
+        let arguments = adapter
+            .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+            .await;
+        assert_eq!(arguments["breakpoints"][0]["line"], 3);
+        store.update(cx, |store, cx| {
+            assert_eq!(store.breakpoint_store().breakpoints(&main_rs).count(), 1);
+            assert!(starting_adapter.requests.is_empty());
+            store.remove_client(starting_id, cx);
+        });
+
+        // disabled breakpoints are kept, but not sent
+        store.update(cx, |store, cx| {
+            store.set_breakpoint_condition(main_rs.clone(), 2, Some("x > 1".into()), cx);
+            assert_eq!(
+                store.toggle_breakpoint_enabled(main_rs.clone(), 2, cx),
+                Some(false)
+            );
+            assert_eq!(
+                store.toggle_breakpoint_enabled(main_rs.clone(), 4, cx),
+                None
+            );
+        });
+        cx.executor().advance_clock(BREAKPOINT_UPDATE_DEBOUNCE);
+        let arguments = adapter
+            .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+            .await;
+        assert_eq!(arguments["breakpoints"], json!([]));
+        store.read_with(cx, |store, _| {
+            let breakpoint = store
+                .breakpoint_store()
+                .breakpoints(&main_rs)
+                .next()
+                .unwrap();
+            assert!(!breakpoint.enabled);
+            assert_eq!(breakpoint.condition.as_deref(), Some("x > 1"));
+        });
+
+        assert_eq!(
+            store.update(cx, |store, cx| store.toggle_breakpoint_enabled(
+                main_rs.clone(),
+                2,
+                cx
+            )),
+            Some(true)
+        );
+        cx.executor().advance_clock(BREAKPOINT_UPDATE_DEBOUNCE);
+        let arguments = adapter
+            .respond::<SetBreakpoints>(SetBreakpointsResponse {
+                breakpoints: Vec::new(),
+            })
+            .await;
+        assert_eq!(arguments["breakpoints"][0]["condition"], "x > 1");
+        while let Ok(Some(_)) = events.try_next() {}
+
+        store.update(cx, |store, cx| store.remove_client(id, cx));
+        assert_eq!(
+            events.next().await.unwrap(),
+            DapStoreEvent::ClientRemoved(id)
+        );
+        assert_eq!(
+            events.next().await.unwrap(),
+            DapStoreEvent::ActiveClientChanged(None)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_watches(cx: &mut TestAppContext) {
+        let store = cx.new_model(|_| DapStore::default());
+        let mut events = cx.events(&store);
+        store.update(cx, |store, cx| {
+            store.set_watches(["count".to_string(), "user".to_string()], cx)
+        });
+        assert_eq!(events.next().await.unwrap(), DapStoreEvent::WatchesChanged);
+
+        // new sessions evaluate the watches of the project
+        let id = add_client(&store, cx);
+        store.update(cx, |store, cx| {
+            let client = store.client_by_id(id).unwrap();
+            assert_eq!(client.watches(), ["count", "user"]);
+
+            store.add_watch("items.len()".into(), cx);
+            store.edit_watch("user", "user.name".into(), cx);
+            store.remove_watch("count", cx);
+            assert_eq!(store.watches(), ["user.name", "items.len()"]);
+            assert_eq!(client.watches(), store.watches());
+        });
+        assert!(std::iter::from_fn(|| events.try_next().ok().flatten())
+            .any(|event| event == DapStoreEvent::WatchesChanged));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    adapters::DebugAdapterBinary,
+    config::{DebugAdapterConfig, DebugRequestType},
+};
+use anyhow::{anyhow, Context, Result};
+use collections::HashSet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// A named debug configuration from a project's `.zed/debug.json`, which the user
+/// picks when starting a debug session.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct LaunchConfiguration {
+    /// The name of the configuration that is shown to the user
+    pub label: String,
+    #[serde(flatten)]
+    pub config: DebugAdapterConfig,
+    /// How to start the debug adapter, for adapters that aren't installed by Zed
+    #[serde(default)]
+    pub adapter: Option<DebugAdapterBinary>,
+}
+
+impl LaunchConfiguration {
+    /// The arguments of the `launch` or `attach` request that starts the debuggee, with
+    /// `cwd` as its working directory unless the config sets one.
+    pub fn request_args(&self, cwd: &Path) -> Value {
+        let mut args = json!({
+            "cwd": self.config.cwd.as_deref().unwrap_or(cwd),
+        });
+        if let Some(program) = &self.config.program {
+            args["program"] = json!(program);
+        }
+        args
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.label.trim().is_empty() {
+            return Err(anyhow!("`label` must not be empty"));
+        }
+        if self.config.id.trim().is_empty() {
+            return Err(anyhow!("`id` must not be empty"));
+        }
+        if self.config.request == DebugRequestType::Launch && self.config.program.is_none() {
+            return Err(anyhow!("`program` is required to launch"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the contents of a `.zed/debug.json` file, an array of launch configurations
+/// with unique labels.
+pub fn parse_launch_configurations(contents: &str) -> Result<Vec<LaunchConfiguration>> {
+    let configurations: Vec<LaunchConfiguration> =
+        serde_json_lenient::from_str(contents).context("invalid debug configurations")?;
+
+    let mut labels = HashSet::default();
+    for (ix, configuration) in configurations.iter().enumerate() {
+        configuration
+            .validate()
+            .with_context(|| format!("invalid debug configuration {ix}"))?;
+        if !labels.insert(configuration.label.as_str()) {
+            return Err(anyhow!(
+                "debug configuration `{}` is defined more than once",
+                configuration.label
+            ));
+        }
+    }
+
+    Ok(configurations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DebugAdapterKind, TCPHost};
+
+    #[test]
+    fn test_parse_launch_configurations() {
+        let configurations = parse_launch_configurations(
+            r#"[
+                // comments are allowed, as in the other .zed files
+                {
+                    "label": "Run main",
+                    "id": "debugpy",
+                    "connection": "stdio",
+                    "program": "main.py",
+                    "adapter": {
+                        "command": "python3",
+                        "arguments": ["-m", "debugpy.adapter"]
+                    }
+                },
+                {
+                    "label": "Attach",
+                    "id": "lldb",
+                    "connection": "tcp",
+                    "port": 4711,
+                    "request": "attach"
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(configurations.len(), 2);
+        assert_eq!(configurations[0].label, "Run main");
+        assert_eq!(configurations[0].config.transport, DebugAdapterKind::STDIO);
+        assert_eq!(configurations[0].config.program.as_deref(), Some("main.py"));
+        assert_eq!(
+            configurations[0].adapter,
+            Some(DebugAdapterBinary {
+                command: "python3".into(),
+                arguments: vec!["-m".into(), "debugpy.adapter".into()],
+                env: None,
+            })
+        );
+        assert_eq!(configurations[1].adapter, None);
+        assert_eq!(configurations[1].config.request, DebugRequestType::Attach);
+        assert_eq!(
+            configurations[1].config.transport,
+            DebugAdapterKind::TCP(TCPHost {
+                port: Some(4711),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_request_args() {
+        let configurations = parse_launch_configurations(
+            r#"[
+                {"label": "Run", "id": "debugpy", "connection": "stdio", "program": "main.py"},
+                {"label": "Attach", "id": "lldb", "connection": "stdio", "request": "attach", "cwd": "/src"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            configurations[0].request_args(Path::new("/project")),
+            json!({"cwd": "/project", "program": "main.py"})
+        );
+        assert_eq!(
+            configurations[1].request_args(Path::new("/project")),
+            json!({"cwd": "/src"})
+        );
+    }
+
+    #[test]
+    fn test_malformed_launch_configurations() {
+        let error =
+            |contents: &str| format!("{:#}", parse_launch_configurations(contents).unwrap_err());
+
+        assert!(error(r#"[{"label": "Run"}]"#).contains("missing field `id`"));
+        assert_eq!(
+            error(r#"[{"label": "Run", "id": "debugpy", "connection": "stdio"}]"#),
+            "invalid debug configuration 0: `program` is required to launch"
+        );
+        assert_eq!(
+            error(
+                r#"[{"label": " ", "id": "debugpy", "connection": "stdio", "request": "attach"}]"#
+            ),
+            "invalid debug configuration 0: `label` must not be empty"
+        );
+        assert_eq!(
+            error(
+                r#"[
+                    {"label": "Attach", "id": "debugpy", "connection": "stdio", "request": "attach"},
+                    {"label": "Attach", "id": "lldb", "connection": "stdio", "request": "attach"}
+                ]"#
+            ),
+            "debug configuration `Attach` is defined more than once"
+        );
+    }
+}
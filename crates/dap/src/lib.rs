@@ -0,0 +1,10 @@
+pub mod adapters;
+pub mod attach;
+pub mod breakpoint_store;
+pub mod client;
+pub mod config;
+pub mod dap_store;
+pub mod launch_config;
+pub mod transport;
+
+pub use dap_types::*;
@@ -0,0 +1,1001 @@
+use anyhow::{anyhow, Context, Result};
+use collections::{HashMap, HashSet};
+use dap_types::{
+    BreakpointEvent, Capabilities, CapabilitiesEvent, ContinuedEvent, ExitedEvent,
+    InvalidatedEvent, LoadedSourceEvent, MemoryEvent, ModuleEvent, OutputEvent, ProcessEvent,
+    ProgressEndEvent, ProgressStartEvent, ProgressUpdateEvent, StoppedEvent, TerminatedEvent,
+    ThreadEvent,
+};
+use futures::{AsyncBufRead, AsyncWrite};
+use gpui::BackgroundExecutor;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol::{
+    channel::{Receiver, Sender},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    process::Child,
+};
+use std::{
+    collections::VecDeque,
+    net::SocketAddrV4,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const CONTENT_LEN_HEADER: &str = "Content-Length: ";
+/// The name of the `Content-Length` header in lowercase, as header names are case-insensitive.
+const CONTENT_LENGTH_NAME: &str = "content-length";
+/// How many of the last lines the adapter wrote to stderr are kept, to explain a crash.
+const STDERR_TAIL_LINES: usize = 20;
+/// Logged messages are cut off after this many bytes, e.g. to not log whole source files.
+const MAX_LOGGED_MESSAGE_LEN: usize = 4096;
+/// How often [`handle_tcp_connection`] tries to reconnect after the connection dropped.
+const RECONNECT_ATTEMPTS: usize = 6;
+/// The delay before the first reconnect attempt, which doubles with every failed attempt
+/// up to [`MAX_RECONNECT_INTERVAL`].
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A message sent between the client and the debug adapter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Payload {
+    Event(Box<Events>),
+    Response(Response),
+    Request(Request),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "body")]
+#[serde(rename_all = "camelCase")]
+pub enum Events {
+    Initialized(Option<Capabilities>),
+    Stopped(StoppedEvent),
+    Continued(ContinuedEvent),
+    Exited(ExitedEvent),
+    Terminated(Option<TerminatedEvent>),
+    Thread(ThreadEvent),
+    Output(OutputEvent),
+    Breakpoint(BreakpointEvent),
+    Module(ModuleEvent),
+    LoadedSource(LoadedSourceEvent),
+    Process(ProcessEvent),
+    Capabilities(CapabilitiesEvent),
+    ProgressStart(ProgressStartEvent),
+    ProgressUpdate(ProgressUpdateEvent),
+    ProgressEnd(ProgressEndEvent),
+    Invalidated(InvalidatedEvent),
+    Memory(MemoryEvent),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub seq: u64,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(default)]
+    pub seq: u64,
+    pub request_seq: u64,
+    pub success: bool,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// The IO handles of a started debug adapter.
+pub struct TransportParams {
+    pub rx: Box<dyn AsyncBufRead + Unpin + Send>,
+    pub tx: Box<dyn AsyncWrite + Unpin + Send>,
+    pub err: Option<Box<dyn AsyncBufRead + Unpin + Send>>,
+    pub process: Option<Child>,
+    /// The address of an adapter that is connected to over TCP, to reconnect to it
+    /// when the connection drops.
+    pub address: Option<SocketAddrV4>,
+}
+
+impl TransportParams {
+    pub fn new(
+        rx: Box<dyn AsyncBufRead + Unpin + Send>,
+        tx: Box<dyn AsyncWrite + Unpin + Send>,
+        err: Option<Box<dyn AsyncBufRead + Unpin + Send>>,
+        process: Option<Child>,
+    ) -> Self {
+        TransportParams {
+            rx,
+            tx,
+            err,
+            process,
+            address: None,
+        }
+    }
+}
+
+/// Reads framed messages from the debug adapter and forwards them to `client_tx`.
+///
+/// With `log_messages`, every message is logged, see [`log_message`].
+pub(crate) async fn handle_input(
+    mut server_stdout: Box<dyn AsyncBufRead + Unpin + Send>,
+    client_tx: Sender<Payload>,
+    log_messages: bool,
+) -> Result<()> {
+    let mut buffer = String::new();
+    loop {
+        let payload = receive_server_message(&mut buffer, &mut server_stdout).await?;
+        if log_messages {
+            log_message(MessageDirection::Received, &payload);
+        }
+        client_tx.send(payload).await?;
+    }
+}
+
+/// Writes every payload received on `client_rx` to the debug adapter.
+///
+/// Payloads are written one at a time, each framed message completely, so requests
+/// that are sent concurrently can't interleave their bytes.
+pub(crate) async fn handle_output(
+    mut server_stdin: Box<dyn AsyncWrite + Unpin + Send>,
+    client_rx: Receiver<Payload>,
+    log_messages: bool,
+) -> Result<()> {
+    while let Ok(payload) = client_rx.recv().await {
+        if log_messages {
+            log_message(MessageDirection::Sent, &payload);
+        }
+        send_payload_to_server(&mut server_stdin, payload).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageDirection {
+    Sent,
+    Received,
+}
+
+/// Logs a message that was sent to or received from the adapter as pretty-printed
+/// JSON, to diagnose issues of adapters.
+fn log_message(direction: MessageDirection, payload: &Payload) {
+    log::info!("{}", format_message(direction, payload));
+}
+
+fn format_message(direction: MessageDirection, payload: &Payload) -> String {
+    let (kind, seq) = match payload {
+        Payload::Request(request) => (format!("request {}", request.command), request.seq),
+        Payload::Response(response) => (
+            format!("response {} to {}", response.command, response.request_seq),
+            response.seq,
+        ),
+        Payload::Event(_) => ("event".to_string(), 0),
+    };
+    let direction = match direction {
+        MessageDirection::Sent => "sent to",
+        MessageDirection::Received => "received from",
+    };
+
+    let mut json = serde_json::to_string_pretty(payload).unwrap_or_default();
+    if json.len() > MAX_LOGGED_MESSAGE_LEN {
+        let mut len = MAX_LOGGED_MESSAGE_LEN;
+        while !json.is_char_boundary(len) {
+            len -= 1;
+        }
+        let omitted = json.len() - len;
+        json.truncate(len);
+        json.push_str(&format!("... ({omitted} more bytes)"));
+    }
+
+    format!("{kind} (seq {seq}) {direction} debug adapter:\n{json}")
+}
+
+/// Logs everything the debug adapter writes to stderr.
+pub(crate) async fn handle_error(
+    mut server_stderr: Box<dyn AsyncBufRead + Unpin + Send>,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+) -> Result<()> {
+    let mut buffer = String::new();
+    loop {
+        buffer.clear();
+        if server_stderr.read_line(&mut buffer).await? == 0 {
+            return Err(anyhow!("debugger error stream closed"));
+        }
+        let line = buffer.trim_end();
+        log::warn!("debug adapter stderr: {line}");
+
+        let mut stderr_tail = stderr_tail.lock();
+        if stderr_tail.len() == STDERR_TAIL_LINES {
+            stderr_tail.pop_front();
+        }
+        stderr_tail.push_back(line.to_string());
+    }
+}
+
+/// The requests [`handle_tcp_connection`] needs to know about when the connection drops.
+#[derive(Default)]
+struct ConnectionState {
+    /// The requests the adapter didn't respond to yet, by their `seq`.
+    unanswered: HashMap<u64, Request>,
+    initialize: Option<Request>,
+    /// The `launch` or `attach` request.
+    start: Option<Request>,
+    /// The latest `setBreakpoints` request of every source.
+    breakpoints: HashMap<String, Request>,
+    exception_breakpoints: Option<Request>,
+    configuration_done: Option<Request>,
+    /// The `seq`s of the requests that were sent again after reconnecting. The client
+    /// already got responses to the original requests, so these aren't passed on.
+    replayed: HashSet<u64>,
+}
+
+impl ConnectionState {
+    fn sent(&mut self, payload: &Payload) {
+        let Payload::Request(request) = payload else {
+            return;
+        };
+        self.unanswered.insert(request.seq, request.clone());
+        match request.command.as_str() {
+            "initialize" => self.initialize = Some(request.clone()),
+            "launch" | "attach" => self.start = Some(request.clone()),
+            "setBreakpoints" => {
+                let source = request
+                    .arguments
+                    .as_ref()
+                    .and_then(|arguments| arguments.get("source"))
+                    .map(Value::to_string)
+                    .unwrap_or_default();
+                self.breakpoints.insert(source, request.clone());
+            }
+            "setExceptionBreakpoints" => self.exception_breakpoints = Some(request.clone()),
+            "configurationDone" => self.configuration_done = Some(request.clone()),
+            _ => {}
+        }
+    }
+
+    /// Returns whether the payload is passed on to the client.
+    fn received(&mut self, payload: &Payload) -> bool {
+        let Payload::Response(response) = payload else {
+            return true;
+        };
+        if self.replayed.remove(&response.request_seq) {
+            if !response.success {
+                log::error!(
+                    "debug adapter failed the replayed {} request: {}",
+                    response.command,
+                    response.message.as_deref().unwrap_or_default()
+                );
+            }
+            return false;
+        }
+        self.unanswered.remove(&response.request_seq);
+        true
+    }
+
+    /// Copies a request of the handshake under a fresh `seq`, to send it again.
+    fn replay(&mut self, request: &Request, request_count: &AtomicU64) -> Request {
+        let seq = request_count.fetch_add(1, Ordering::SeqCst);
+        self.replayed.insert(seq);
+        Request {
+            seq,
+            ..request.clone()
+        }
+    }
+
+    /// The requests that configure the adapter once it sent the `initialized` event.
+    fn configuration_requests(&self) -> Vec<Request> {
+        self.breakpoints
+            .values()
+            .chain(&self.exception_breakpoints)
+            .chain(&self.configuration_done)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Runs the IO loops of an adapter that is connected to over TCP, until the client
+/// closes `client_rx`.
+///
+/// When the connection drops, e.g. because the adapter restarted its server, the
+/// requests that weren't answered fail and the connection to `address` is
+/// re-established, see [`replay_handshake`] for how the adapter is set up like before.
+/// Fails when reconnecting didn't work after [`RECONNECT_ATTEMPTS`] attempts.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn handle_tcp_connection(
+    mut server_stdout: Box<dyn AsyncBufRead + Unpin + Send>,
+    mut server_stdin: Box<dyn AsyncWrite + Unpin + Send>,
+    address: SocketAddrV4,
+    client_tx: Sender<Payload>,
+    client_rx: Receiver<Payload>,
+    request_count: Arc<AtomicU64>,
+    log_messages: bool,
+    executor: BackgroundExecutor,
+) -> Result<()> {
+    let state = Mutex::new(ConnectionState::default());
+    let mut buffer = String::new();
+    loop {
+        let read = async {
+            loop {
+                let payload = receive_server_message(&mut buffer, &mut server_stdout).await?;
+                if !forward_payload(payload, &state, &client_tx, log_messages).await {
+                    return anyhow::Ok(());
+                }
+            }
+        };
+        let write = async {
+            while let Ok(payload) = client_rx.recv().await {
+                state.lock().sent(&payload);
+                if log_messages {
+                    log_message(MessageDirection::Sent, &payload);
+                }
+                send_payload_to_server(&mut server_stdin, payload).await?;
+            }
+            anyhow::Ok(())
+        };
+        let error = match smol::future::or(read, write).await {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+        log::warn!("lost the connection to the debug adapter at {address}: {error:#}");
+
+        let unanswered = {
+            let mut state = state.lock();
+            state.replayed.clear();
+            std::mem::take(&mut state.unanswered)
+        };
+        for request in unanswered.into_values() {
+            let response = Response {
+                seq: 0,
+                request_seq: request.seq,
+                success: false,
+                command: request.command,
+                message: Some("lost the connection to the debug adapter".into()),
+                body: None,
+            };
+            client_tx.send(Payload::Response(response)).await.ok();
+        }
+
+        let stream = reconnect(address, &executor)
+            .await
+            .with_context(|| format!("lost the connection to the debug adapter at {address}"))?;
+        server_stdout = Box::new(BufReader::new(stream.clone()));
+        server_stdin = Box::new(stream);
+        buffer.clear();
+
+        replay_handshake(
+            &mut buffer,
+            &mut server_stdout,
+            &mut server_stdin,
+            &state,
+            &client_tx,
+            &request_count,
+            log_messages,
+        )
+        .await?;
+    }
+}
+
+/// Sends the handshake of the session again after reconnecting, under fresh `seq`s:
+/// `initialize`, then `launch` or `attach`, and once the adapter sent the `initialized`
+/// event, the latest breakpoints and `configurationDone`. The messages the adapter
+/// sends meanwhile are passed on to the client, except for the responses to the replay.
+async fn replay_handshake(
+    buffer: &mut String,
+    server_stdout: &mut Box<dyn AsyncBufRead + Unpin + Send>,
+    server_stdin: &mut Box<dyn AsyncWrite + Unpin + Send>,
+    state: &Mutex<ConnectionState>,
+    client_tx: &Sender<Payload>,
+    request_count: &AtomicU64,
+    log_messages: bool,
+) -> Result<()> {
+    let (initialize, start, configuration) = {
+        let mut state = state.lock();
+        let Some(initialize) = state.initialize.clone() else {
+            return Ok(());
+        };
+        let initialize = state.replay(&initialize, request_count);
+        let start = state
+            .start
+            .clone()
+            .map(|start| state.replay(&start, request_count));
+        let configuration = state
+            .configuration_requests()
+            .iter()
+            .map(|request| state.replay(request, request_count))
+            .collect::<Vec<_>>();
+        (initialize, start, configuration)
+    };
+
+    let initialize_seq = initialize.seq;
+    send_request(server_stdin, initialize, log_messages).await?;
+    loop {
+        let payload = receive_server_message(buffer, server_stdout).await?;
+        let initialized = matches!(&payload, Payload::Response(response) if response.request_seq == initialize_seq);
+        forward_payload(payload, state, client_tx, log_messages).await;
+        if initialized {
+            break;
+        }
+    }
+
+    if let Some(start) = start {
+        send_request(server_stdin, start, log_messages).await?;
+    }
+    if configuration.is_empty() {
+        return Ok(());
+    }
+    loop {
+        let payload = receive_server_message(buffer, server_stdout).await?;
+        let initialized =
+            matches!(&payload, Payload::Event(event) if matches!(**event, Events::Initialized(_)));
+        forward_payload(payload, state, client_tx, log_messages).await;
+        if initialized {
+            break;
+        }
+    }
+    for request in configuration {
+        send_request(server_stdin, request, log_messages).await?;
+    }
+    Ok(())
+}
+
+/// Passes a payload the adapter sent on to the client, returning `false` once the client
+/// stopped listening.
+async fn forward_payload(
+    payload: Payload,
+    state: &Mutex<ConnectionState>,
+    client_tx: &Sender<Payload>,
+    log_messages: bool,
+) -> bool {
+    if log_messages {
+        log_message(MessageDirection::Received, &payload);
+    }
+    if !state.lock().received(&payload) {
+        return true;
+    }
+    client_tx.send(payload).await.is_ok()
+}
+
+async fn send_request(
+    server_stdin: &mut Box<dyn AsyncWrite + Unpin + Send>,
+    request: Request,
+    log_messages: bool,
+) -> Result<()> {
+    let payload = Payload::Request(request);
+    if log_messages {
+        log_message(MessageDirection::Sent, &payload);
+    }
+    send_payload_to_server(server_stdin, payload).await
+}
+
+async fn reconnect(address: SocketAddrV4, executor: &BackgroundExecutor) -> Result<TcpStream> {
+    let mut interval = RECONNECT_INTERVAL;
+    let mut attempts = 0;
+    loop {
+        executor.timer(interval).await;
+        match TcpStream::connect(address).await {
+            Ok(stream) => return Ok(stream),
+            Err(error) => {
+                attempts += 1;
+                if attempts == RECONNECT_ATTEMPTS {
+                    return Err(anyhow!("failed to reconnect {attempts} times: {error}"));
+                }
+                log::debug!("failed to reconnect to debug adapter at {address}: {error}");
+            }
+        }
+        interval = (interval * 2).min(MAX_RECONNECT_INTERVAL);
+    }
+}
+
+/// Reads the next message from the adapter.
+///
+/// Messages with an invalid `Content-Length` header, or a body that isn't a valid
+/// payload, are logged and skipped, so one malformed message doesn't break the
+/// messages that follow it.
+async fn receive_server_message(
+    buffer: &mut String,
+    server_stdout: &mut Box<dyn AsyncBufRead + Unpin + Send>,
+) -> Result<Payload> {
+    loop {
+        let content_length = read_content_length(buffer, server_stdout).await?;
+
+        let mut content = vec![0; content_length];
+        server_stdout
+            .read_exact(&mut content)
+            .await
+            .with_context(|| "reading a message body from server")?;
+
+        match serde_json::from_slice::<Payload>(&content) {
+            Ok(payload) => return Ok(payload),
+            Err(error) => log::error!(
+                "skipping an invalid debug adapter message: {error}: {}",
+                String::from_utf8_lossy(&content)
+            ),
+        }
+    }
+}
+
+/// Reads header lines up to the empty line that ends the headers of a message with
+/// a valid `Content-Length`, returning that length.
+///
+/// The body of a message with an invalid length can't be skipped, so after its headers
+/// the output is searched for the next `Content-Length` header, which then follows the
+/// rest of that body on the same line.
+async fn read_content_length(
+    buffer: &mut String,
+    server_stdout: &mut Box<dyn AsyncBufRead + Unpin + Send>,
+) -> Result<usize> {
+    let mut content_length = None;
+    let mut invalid_content_length = false;
+    let mut resyncing = false;
+    loop {
+        buffer.truncate(0);
+        if server_stdout
+            .read_line(buffer)
+            .await
+            .with_context(|| "reading a message from server")?
+            == 0
+        {
+            return Err(anyhow!("debugger reader stream closed"));
+        };
+
+        // headers end with an empty line, which some adapters end with `\n` only
+        let mut line = buffer.trim();
+        if line.is_empty() {
+            if let Some(content_length) = content_length.take() {
+                return Ok(content_length);
+            }
+            resyncing |= std::mem::take(&mut invalid_content_length);
+            continue;
+        }
+
+        if resyncing {
+            match line.to_ascii_lowercase().find(CONTENT_LENGTH_NAME) {
+                Some(ix) => line = &line[ix..],
+                None => continue,
+            }
+        }
+
+        // other headers, like `Content-Type`, are ignored
+        match line.split_once(':') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case(CONTENT_LENGTH_NAME) => {
+                resyncing = false;
+                let value = value.trim();
+                content_length = value.parse().ok();
+                if content_length.is_none() {
+                    invalid_content_length = true;
+                    log::error!("skipping a debug adapter message with content length {value:?}");
+                }
+            }
+            Some(_) => {}
+            None => log::warn!("ignoring debug adapter output that is not a header: {line}"),
+        }
+    }
+}
+
+async fn send_payload_to_server(
+    server_stdin: &mut Box<dyn AsyncWrite + Unpin + Send>,
+    payload: Payload,
+) -> Result<()> {
+    let payload = serde_json::to_string(&payload)?;
+    server_stdin
+        .write_all(format!("{CONTENT_LEN_HEADER}{}\r\n\r\n{payload}", payload.len()).as_bytes())
+        .await?;
+    server_stdin.flush().await?;
+    Ok(())
+}
+
+/// Returns the two ends of an in-memory byte stream.
+#[cfg(any(test, feature = "test-support"))]
+pub(crate) fn pipe() -> (PipeWriter, impl futures::AsyncBufRead + Unpin + Send) {
+    use futures::{StreamExt as _, TryStreamExt as _};
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    (
+        PipeWriter(tx),
+        rx.map(Ok::<Vec<u8>, std::io::Error>).into_async_read(),
+    )
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub(crate) struct PipeWriter(futures::channel::mpsc::UnboundedSender<Vec<u8>>);
+
+#[cfg(any(test, feature = "test-support"))]
+impl futures::AsyncWrite for PipeWriter {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let result = self
+            .0
+            .unbounded_send(buf.to_vec())
+            .map(|()| buf.len())
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into());
+        std::task::Poll::Ready(result)
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        self.0.close_channel();
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{stream, TryStreamExt};
+    use gpui::TestAppContext;
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    /// Feeds `input` to [`handle_input`] one byte at a time, returning the exit codes of
+    /// the `exited` events it read.
+    fn read_exit_codes(input: &str) -> Vec<u64> {
+        smol::block_on(async {
+            let chunks = input
+                .bytes()
+                .map(|byte| Ok::<_, std::io::Error>(vec![byte]))
+                .collect::<Vec<_>>();
+            let reader = stream::iter(chunks).into_async_read();
+            let (client_tx, client_rx) = smol::channel::unbounded();
+
+            handle_input(Box::new(reader), client_tx, false)
+                .await
+                .unwrap_err();
+
+            let mut exit_codes = Vec::new();
+            while let Ok(payload) = client_rx.try_recv() {
+                let Payload::Event(event) = payload else {
+                    panic!("expected an event");
+                };
+                let Events::Exited(event) = *event else {
+                    panic!("expected an exited event");
+                };
+                exit_codes.push(event.exit_code);
+            }
+            exit_codes
+        })
+    }
+
+    fn exited_event(exit_code: u64) -> String {
+        format!(r#"{{"seq":1,"type":"event","event":"exited","body":{{"exitCode":{exit_code}}}}}"#)
+    }
+
+    #[test]
+    fn test_fragmented_messages() {
+        let input = [
+            format!(
+                "content-length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}",
+                exited_event(1).len(),
+                exited_event(1)
+            ),
+            format!("Content-Length:{}\n\n{}", exited_event(2).len(), exited_event(2)),
+            format!("\r\nCONTENT-LENGTH: {}\r\n\r\n{}", exited_event(3).len(), exited_event(3)),
+        ]
+        .concat();
+
+        assert_eq!(read_exit_codes(&input), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_malformed_messages_are_skipped() {
+        let input = [
+            "Debugger listening\r\n".to_string(),
+            format!(
+                "Content-Length: {}\r\n\r\n{}",
+                exited_event(1).len(),
+                exited_event(1)
+            ),
+            format!("Content-Length: many\r\n\r\n{}", exited_event(2)),
+            format!(
+                "Content-Length: {}\r\n\r\n{}",
+                exited_event(3).len(),
+                exited_event(3)
+            ),
+            "Content-Length: 9\r\n\r\n{\"seq\":1}".to_string(),
+            format!(
+                "Content-Length: {}\r\n\r\n{}",
+                exited_event(4).len(),
+                exited_event(4)
+            ),
+        ]
+        .concat();
+
+        assert_eq!(read_exit_codes(&input), [1, 3, 4]);
+    }
+
+    #[test]
+    fn test_content_length_is_read_from_its_own_header() {
+        let input = [
+            format!(
+                "Content-Length: {}\r\nX-Note: content-length: 5\r\n\r\n{}",
+                exited_event(1).len(),
+                exited_event(1)
+            ),
+            format!(
+                "X-Content-Length: 5\r\nContent-Length: {}\r\n\r\n{}",
+                exited_event(2).len(),
+                exited_event(2)
+            ),
+        ]
+        .concat();
+
+        assert_eq!(read_exit_codes(&input), [1, 2]);
+    }
+
+    /// A writer that writes at most a few bytes at a time, like a full pipe does.
+    struct ShortWriter(PipeWriter);
+
+    impl AsyncWrite for ShortWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let len = buf.len().min(7);
+            Pin::new(&mut self.0).poll_write(cx, &buf[..len])
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_close(cx)
+        }
+    }
+
+    #[gpui::test]
+    async fn test_concurrent_payloads_are_written_intact(cx: &mut TestAppContext) {
+        let (writer, reader) = pipe();
+        let (client_tx, client_rx) = smol::channel::unbounded();
+        let output = cx.executor().spawn(handle_output(
+            Box::new(ShortWriter(writer)),
+            client_rx,
+            false,
+        ));
+
+        let senders = (0..20)
+            .map(|seq| {
+                let client_tx = client_tx.clone();
+                cx.executor().spawn(async move {
+                    client_tx
+                        .send(Payload::Request(Request {
+                            seq,
+                            command: "setBreakpoints".into(),
+                            arguments: Some(Value::String(seq.to_string().repeat(1000))),
+                        }))
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        futures::future::join_all(senders).await;
+        drop(client_tx);
+        output.await.unwrap();
+
+        let (payload_tx, payload_rx) = smol::channel::unbounded();
+        handle_input(Box::new(reader), payload_tx, false)
+            .await
+            .unwrap_err();
+
+        let mut seqs = Vec::new();
+        while let Ok(payload) = payload_rx.try_recv() {
+            let Payload::Request(request) = payload else {
+                panic!("expected a request");
+            };
+            assert_eq!(
+                request.arguments,
+                Some(Value::String(request.seq.to_string().repeat(1000)))
+            );
+            seqs.push(request.seq);
+        }
+        seqs.sort();
+        assert_eq!(seqs, (0..20).collect::<Vec<_>>());
+    }
+
+    fn request(seq: u64, command: &str) -> Payload {
+        Payload::Request(Request {
+            seq,
+            command: command.into(),
+            arguments: None,
+        })
+    }
+
+    fn success_response(request_seq: u64, command: &str) -> Payload {
+        Payload::Response(Response {
+            seq: request_seq,
+            request_seq,
+            success: true,
+            command: command.into(),
+            message: None,
+            body: None,
+        })
+    }
+
+    async fn receive_request(
+        buffer: &mut String,
+        adapter_stdout: &mut Box<dyn AsyncBufRead + Unpin + Send>,
+    ) -> (u64, String) {
+        let Payload::Request(request) = receive_server_message(buffer, adapter_stdout)
+            .await
+            .unwrap()
+        else {
+            panic!("expected a request");
+        };
+        (request.seq, request.command)
+    }
+
+    #[gpui::test]
+    async fn test_reconnecting_after_the_connection_dropped(cx: &mut TestAppContext) {
+        // the adapter is connected to over the network
+        cx.executor().allow_parking();
+        let listener = smol::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let std::net::SocketAddr::V4(address) = listener.local_addr().unwrap() else {
+            panic!("expected an ipv4 address");
+        };
+        let (server_tx, client_rx) = smol::channel::unbounded();
+        let (client_tx, server_rx) = smol::channel::unbounded();
+        let stream = TcpStream::connect(address).await.unwrap();
+        let mut connection = cx.executor().spawn(handle_tcp_connection(
+            Box::new(BufReader::new(stream.clone())),
+            Box::new(stream),
+            address,
+            client_tx,
+            client_rx,
+            Arc::new(AtomicU64::new(10)),
+            false,
+            cx.executor(),
+        ));
+
+        let (adapter, _) = listener.accept().await.unwrap();
+        let mut adapter_stdout: Box<dyn AsyncBufRead + Unpin + Send> =
+            Box::new(BufReader::new(adapter.clone()));
+        let mut adapter_stdin: Box<dyn AsyncWrite + Unpin + Send> = Box::new(adapter.clone());
+        let mut buffer = String::new();
+        let commands = [
+            "initialize",
+            "threads",
+            "launch",
+            "setBreakpoints",
+            "configurationDone",
+        ];
+        for (seq, command) in (1..).zip(commands) {
+            server_tx.send(request(seq, command)).await.unwrap();
+            receive_server_message(&mut buffer, &mut adapter_stdout)
+                .await
+                .unwrap();
+        }
+        // the adapter answers everything but the `threads` request
+        for (seq, command) in (1..).zip(commands) {
+            if command != "threads" {
+                send_payload_to_server(&mut adapter_stdin, success_response(seq, command))
+                    .await
+                    .unwrap();
+                let Payload::Response(response) = server_rx.recv().await.unwrap() else {
+                    panic!("expected a response");
+                };
+                assert_eq!(response.request_seq, seq);
+            }
+        }
+        drop((adapter, adapter_stdin, adapter_stdout));
+
+        // the request the adapter didn't answer fails
+        let Payload::Response(response) = server_rx.recv().await.unwrap() else {
+            panic!("expected a response");
+        };
+        assert_eq!((response.request_seq, response.success), (2, false));
+
+        // the handshake is sent again on the new connection, under fresh seqs
+        cx.executor().advance_clock(RECONNECT_INTERVAL);
+        let (adapter, _) = listener.accept().await.unwrap();
+        let mut adapter_stdout: Box<dyn AsyncBufRead + Unpin + Send> =
+            Box::new(BufReader::new(adapter.clone()));
+        let mut adapter_stdin: Box<dyn AsyncWrite + Unpin + Send> = Box::new(adapter.clone());
+        assert_eq!(
+            receive_request(&mut buffer, &mut adapter_stdout).await,
+            (10, "initialize".into())
+        );
+        send_payload_to_server(&mut adapter_stdin, success_response(10, "initialize"))
+            .await
+            .unwrap();
+        assert_eq!(
+            receive_request(&mut buffer, &mut adapter_stdout).await,
+            (11, "launch".into())
+        );
+
+        // breakpoints are only sent once the adapter is initialized again
+        smol::Timer::after(Duration::from_millis(50)).await;
+        assert!(server_rx.is_empty());
+        send_payload_to_server(
+            &mut adapter_stdin,
+            Payload::Event(Box::new(Events::Initialized(None))),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            receive_request(&mut buffer, &mut adapter_stdout).await,
+            (12, "setBreakpoints".into())
+        );
+        assert_eq!(
+            receive_request(&mut buffer, &mut adapter_stdout).await,
+            (13, "configurationDone".into())
+        );
+
+        // the client only sees the event, not the responses to the replayed requests
+        for (seq, command) in [(11, "launch"), (12, "setBreakpoints")] {
+            send_payload_to_server(&mut adapter_stdin, success_response(seq, command))
+                .await
+                .unwrap();
+        }
+        let Payload::Event(event) = server_rx.recv().await.unwrap() else {
+            panic!("expected an event");
+        };
+        assert!(matches!(*event, Events::Initialized(_)));
+
+        // the attempts to reconnect back off, until they give up
+        drop((listener, adapter, adapter_stdin, adapter_stdout));
+        let error = loop {
+            cx.executor().advance_clock(MAX_RECONNECT_INTERVAL);
+            let result = smol::future::or(async { Some((&mut connection).await) }, async {
+                smol::Timer::after(Duration::from_millis(10)).await;
+                None
+            })
+            .await;
+            if let Some(result) = result {
+                break result.unwrap_err();
+            }
+        };
+        assert_eq!(
+            error.to_string(),
+            format!("lost the connection to the debug adapter at {address}")
+        );
+        assert!(server_rx.is_empty());
+    }
+
+    #[test]
+    fn test_format_message() {
+        let request = Payload::Request(Request {
+            seq: 3,
+            command: "evaluate".into(),
+            arguments: Some(serde_json::json!({ "expression": "x" })),
+        });
+        assert_eq!(
+            format_message(MessageDirection::Sent, &request),
+            "request evaluate (seq 3) sent to debug adapter:\n{\n  \"type\": \"request\",\n  \"seq\": 3,\n  \"command\": \"evaluate\",\n  \"arguments\": {\n    \"expression\": \"x\"\n  }\n}"
+        );
+
+        let response = Payload::Response(Response {
+            seq: 4,
+            request_seq: 3,
+            success: true,
+            command: "source".into(),
+            message: None,
+            body: Some(serde_json::json!({ "content": "é".repeat(MAX_LOGGED_MESSAGE_LEN) })),
+        });
+        let message = format_message(MessageDirection::Received, &response);
+        assert!(message.starts_with("response source to 3 (seq 4) received from debug adapter:\n"));
+        assert!(message.ends_with(" more bytes)"));
+        assert!(message.len() < MAX_LOGGED_MESSAGE_LEN + 200);
+    }
+}
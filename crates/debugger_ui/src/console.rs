@@ -0,0 +1,221 @@
+use anyhow::Result;
+use dap::client::{
+    completion_edit, ConsoleOutput, ConsoleOutputKind, DebugAdapterClient, SessionAction,
+};
+use editor::{CompletionProvider, Editor};
+use gpui::{list, AnyElement, ListAlignment, ListState, Model, Render, Task, View, ViewContext};
+use language::{Buffer, CodeLabel, LanguageServerId, ToOffset};
+use parking_lot::RwLock;
+use project::Completion;
+use std::sync::Arc;
+use ui::prelude::*;
+
+/// The debug console of a session: the output of the debuggee and the adapter, and an
+/// input to evaluate expressions in the `repl` context.
+pub struct Console {
+    client: Arc<DebugAdapterClient>,
+    output: Vec<ConsoleOutput>,
+    output_list: ListState,
+    query_bar: View<Editor>,
+}
+
+impl Console {
+    pub fn new(client: Arc<DebugAdapterClient>, cx: &mut ViewContext<Self>) -> Self {
+        let view = cx.view().downgrade();
+        let output_list = ListState::new(0, ListAlignment::Bottom, px(1000.), move |ix, cx| {
+            view.upgrade()
+                .map(|view| view.update(cx, |console, _| console.render_output(ix)))
+                .unwrap_or_else(|| div().into_any())
+        });
+
+        let query_bar = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Evaluate an expression", cx);
+            editor.set_completion_provider(Box::new(ConsoleCompletionProvider {
+                client: client.clone(),
+            }));
+            editor
+        });
+
+        let mut this = Self {
+            client,
+            output: Vec::new(),
+            output_list,
+            query_bar,
+        };
+        this.update_output(cx);
+        this
+    }
+
+    /// Shows the output the client received since the last update. The list stays
+    /// scrolled to the bottom, unless it was scrolled up.
+    pub fn update_output(&mut self, cx: &mut ViewContext<Self>) {
+        let output = self.client.console_output();
+        let old_len = self.output.len();
+        if output.len() >= old_len {
+            self.output_list
+                .splice(old_len..old_len, output.len() - old_len);
+        } else {
+            self.output_list.reset(output.len());
+        }
+        self.output = output;
+        cx.notify();
+    }
+
+    /// Evaluates the expression of the input in the selected stack frame. The client
+    /// adds the expression and its result to the console output.
+    fn evaluate(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let expression = self.query_bar.update(cx, |editor, cx| {
+            let expression = editor.text(cx);
+            editor.clear(cx);
+            expression
+        });
+        if expression.trim().is_empty() {
+            return;
+        }
+
+        let client = self.client.clone();
+        let frame_id = current_stack_frame_id(&client);
+        cx.background_executor()
+            .spawn(async move { client.evaluate_repl_command(expression, frame_id).await })
+            // the console shows the error an expression failed with
+            .detach();
+    }
+
+    fn render_output(&self, ix: usize) -> AnyElement {
+        let Some(output) = self.output.get(ix) else {
+            return div().into_any();
+        };
+        let color = match output.kind {
+            ConsoleOutputKind::Stdout | ConsoleOutputKind::Result => Color::Default,
+            ConsoleOutputKind::Stderr => Color::Error,
+            ConsoleOutputKind::Console => Color::Muted,
+            ConsoleOutputKind::Important => Color::Warning,
+            ConsoleOutputKind::Command => Color::Accent,
+        };
+        let text = output.plain_text();
+
+        div()
+            .px_2()
+            .child(
+                Label::new(text.trim_end_matches('\n').to_string())
+                    .size(LabelSize::Small)
+                    .color(color),
+            )
+            .into_any()
+    }
+}
+
+impl Render for Console {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("DebugConsole")
+            .on_action(cx.listener(Self::evaluate))
+            .size_full()
+            .border_t_1()
+            .border_color(cx.theme().colors().border)
+            .child(
+                div().px_2().py_1().child(
+                    Label::new("Console")
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                ),
+            )
+            .child(list(self.output_list.clone()).flex_1().min_h_16())
+            .child(
+                div()
+                    .px_2()
+                    .py_1()
+                    .border_t_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(self.query_bar.clone()),
+            )
+    }
+}
+
+/// The frame that expressions typed into the console are evaluated in.
+fn current_stack_frame_id(client: &DebugAdapterClient) -> Option<u64> {
+    let thread_id = client.current_thread_id()?;
+    client.thread_state_by_id(thread_id).current_stack_frame_id
+}
+
+/// Completes the console input with the `completions` of the adapter.
+struct ConsoleCompletionProvider {
+    client: Arc<DebugAdapterClient>,
+}
+
+impl CompletionProvider for ConsoleCompletionProvider {
+    fn completions(
+        &self,
+        buffer: &Model<Buffer>,
+        buffer_position: language::Anchor,
+        cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Vec<Completion>>> {
+        let client = self.client.clone();
+        if !client
+            .supported_actions()
+            .contains(&SessionAction::Completions)
+        {
+            return Task::ready(Ok(Vec::new()));
+        }
+
+        let snapshot = buffer.read(cx).snapshot();
+        let text = snapshot.text();
+        let offset = buffer_position.to_offset(&snapshot);
+        // the adapter measures columns in UTF-16 code units
+        let column = text[..offset].encode_utf16().count() as u64 + 1;
+        let frame_id = current_stack_frame_id(&client);
+
+        cx.background_executor().spawn(async move {
+            let items = client.completions(text.clone(), column, frame_id).await?;
+            Ok(items
+                .into_iter()
+                .map(|item| {
+                    let edit = completion_edit(&text, column, &item);
+                    Completion {
+                        old_range: snapshot.anchor_before(edit.range.start)
+                            ..snapshot.anchor_after(edit.range.end),
+                        new_text: edit.new_text,
+                        label: CodeLabel::plain(item.label, None),
+                        documentation: None,
+                        server_id: LanguageServerId(0),
+                        lsp_completion: Default::default(),
+                        confirm: None,
+                        show_new_completions_on_confirm: false,
+                    }
+                })
+                .collect())
+        })
+    }
+
+    fn resolve_completions(
+        &self,
+        _buffer: Model<Buffer>,
+        _completion_indices: Vec<usize>,
+        _completions: Arc<RwLock<Box<[Completion]>>>,
+        _cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<bool>> {
+        Task::ready(Ok(false))
+    }
+
+    fn apply_additional_edits_for_completion(
+        &self,
+        _buffer: Model<Buffer>,
+        _completion: Completion,
+        _push_to_history: bool,
+        _cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Option<language::Transaction>>> {
+        Task::ready(Ok(None))
+    }
+
+    fn is_completion_trigger(
+        &self,
+        _buffer: &Model<Buffer>,
+        _position: language::Anchor,
+        text: &str,
+        _trigger_in_words: bool,
+        _cx: &mut ViewContext<Editor>,
+    ) -> bool {
+        self.client.is_completion_trigger(text)
+    }
+}
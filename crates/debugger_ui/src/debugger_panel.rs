@@ -0,0 +1,488 @@
+use crate::{
+    debugger_panel_item::DebugPanelItem, persistence::DEBUGGER_DB, update_command_palette_filter,
+    Continue, Pause, Restart, RestartFrame, StartDebugger, StepBack, StepIn, StepOut, StepOver,
+    Stop, Terminate,
+};
+use anyhow::{anyhow, Context as _, Result};
+use collections::HashMap;
+use dap::{
+    client::{ClientNotification, DebugAdapterClient, DebugAdapterClientId, RunInTerminalHandler},
+    dap_store::{DapStore, DapStoreEvent},
+    RunInTerminalRequestArguments, RunInTerminalResponse, SteppingGranularity,
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    FutureExt as _, StreamExt as _,
+};
+use gpui::{
+    actions, Action, AppContext, AsyncWindowContext, EventEmitter, FocusHandle, FocusableView,
+    Model, Render, Subscription, Task, View, ViewContext, WeakView,
+};
+use std::sync::Arc;
+use task::{RevealStrategy, SpawnInTerminal, TaskId, TerminalWorkDir};
+use terminal_view::terminal_panel::TerminalPanel;
+use ui::{prelude::*, Tooltip};
+use util::{ResultExt, TryFutureExt};
+use workspace::{
+    dock::{DockPosition, Panel, PanelEvent},
+    Workspace,
+};
+
+actions!(debug_panel, [ToggleFocus]);
+
+pub(crate) fn register(workspace: &mut Workspace) {
+    workspace
+        .register_action(|workspace, _: &ToggleFocus, cx| {
+            workspace.toggle_panel_focus::<DebugPanel>(cx);
+        })
+        .register_action(|workspace, _: &Continue, cx| {
+            if let Some((client, thread_id)) = current_thread(workspace, cx) {
+                client.continue_thread_task(thread_id, cx).detach();
+            }
+        })
+        .register_action(|workspace, _: &Pause, cx| {
+            if let Some(client) = active_client(workspace, cx) {
+                client.stop_all_threads_task(cx).detach();
+            }
+        })
+        .register_action(|workspace, _: &StepOver, cx| {
+            if let Some((client, thread_id)) = current_thread(workspace, cx) {
+                client
+                    .step_over_task(thread_id, SteppingGranularity::Statement, cx)
+                    .detach();
+            }
+        })
+        .register_action(|workspace, _: &StepIn, cx| {
+            if let Some((client, thread_id)) = current_thread(workspace, cx) {
+                client
+                    .step_in_task(thread_id, SteppingGranularity::Statement, cx)
+                    .detach();
+            }
+        })
+        .register_action(|workspace, _: &StepOut, cx| {
+            if let Some((client, thread_id)) = current_thread(workspace, cx) {
+                client
+                    .step_out_task(thread_id, SteppingGranularity::Statement, cx)
+                    .detach();
+            }
+        })
+        .register_action(|workspace, _: &StepBack, cx| {
+            if let Some((client, thread_id)) = current_thread(workspace, cx) {
+                cx.background_executor()
+                    .spawn(async move {
+                        client
+                            .step_back(thread_id, SteppingGranularity::Statement)
+                            .await
+                    })
+                    .detach_and_log_err(cx);
+            }
+        })
+        .register_action(|workspace, _: &RestartFrame, cx| {
+            let Some((client, thread_id)) = current_thread(workspace, cx) else {
+                return;
+            };
+            let stack_frame_id = client
+                .thread_states()
+                .get(&thread_id)
+                .and_then(|thread_state| thread_state.current_stack_frame_id);
+            if let Some(stack_frame_id) = stack_frame_id {
+                cx.background_executor()
+                    .spawn(async move { client.restart_frame(thread_id, stack_frame_id).await })
+                    .detach_and_log_err(cx);
+            }
+        })
+        .register_action(|workspace, _: &Restart, cx| {
+            if let Some(client) = active_client(workspace, cx) {
+                cx.background_executor()
+                    .spawn(async move { client.restart_session_preserving_selection(None).await })
+                    .detach_and_log_err(cx);
+            }
+        })
+        .register_action(|workspace, _: &Terminate, cx| {
+            if let Some(client) = active_client(workspace, cx) {
+                cx.background_executor()
+                    .spawn(async move { client.terminate().await })
+                    .detach_and_log_err(cx);
+            }
+        })
+        .register_action(|workspace, _: &Stop, cx| {
+            let Some(client) = active_client(workspace, cx) else {
+                return;
+            };
+            let dap_store = workspace.project().read(cx).dap_store().clone();
+            cx.spawn(|_, mut cx| async move {
+                client.disconnect(None, Some(true), None).await.log_err();
+                dap_store.update(&mut cx, |dap_store, cx| {
+                    dap_store.remove_client(client.id(), cx);
+                })
+            })
+            .detach_and_log_err(cx);
+        });
+}
+
+/// The session that the actions of the debugger apply to.
+fn active_client(workspace: &Workspace, cx: &AppContext) -> Option<Arc<DebugAdapterClient>> {
+    workspace
+        .project()
+        .read(cx)
+        .dap_store()
+        .read(cx)
+        .active_client()
+}
+
+/// The thread of the active session that stepping and continuing apply to.
+fn current_thread(
+    workspace: &Workspace,
+    cx: &AppContext,
+) -> Option<(Arc<DebugAdapterClient>, u64)> {
+    let client = active_client(workspace, cx)?;
+    let thread_id = client.current_thread_id()?;
+    Some((client, thread_id))
+}
+
+/// A `runInTerminal` request of a session, and where to send the launched debuggee to.
+type RunInTerminalRequest = (
+    RunInTerminalRequestArguments,
+    oneshot::Sender<Result<RunInTerminalResponse>>,
+);
+
+pub struct DebugPanel {
+    workspace: WeakView<Workspace>,
+    dap_store: Model<DapStore>,
+    items: HashMap<DebugAdapterClientId, View<DebugPanelItem>>,
+    position: DockPosition,
+    size: Option<Pixels>,
+    focus_handle: FocusHandle,
+    run_in_terminal_tx: mpsc::UnboundedSender<RunInTerminalRequest>,
+    pending_serialization: Task<Option<()>>,
+    _run_in_terminal_task: Task<()>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl DebugPanel {
+    pub fn new(workspace: &Workspace, cx: &mut ViewContext<Self>) -> Self {
+        let dap_store = workspace.project().read(cx).dap_store().clone();
+        let (run_in_terminal_tx, mut run_in_terminal_rx) =
+            mpsc::unbounded::<RunInTerminalRequest>();
+        let workspace_handle = workspace.weak_handle();
+        let run_in_terminal_task = cx.spawn(|_, mut cx| async move {
+            while let Some((arguments, response_tx)) = run_in_terminal_rx.next().await {
+                let response = run_in_terminal(workspace_handle.clone(), arguments, &mut cx).await;
+                response_tx.send(response).ok();
+            }
+        });
+
+        let mut this = Self {
+            workspace: workspace.weak_handle(),
+            dap_store: dap_store.clone(),
+            items: HashMap::default(),
+            position: DockPosition::Bottom,
+            size: None,
+            focus_handle: cx.focus_handle(),
+            run_in_terminal_tx,
+            pending_serialization: Task::ready(None),
+            _run_in_terminal_task: run_in_terminal_task,
+            _subscriptions: vec![cx.subscribe(&dap_store, Self::handle_dap_store_event)],
+        };
+        let clients = dap_store.read(cx).clients().cloned().collect::<Vec<_>>();
+        for client in clients {
+            this.add_item(client, cx);
+        }
+        this
+    }
+
+    /// Creates the panel, restoring the watches that were persisted for the project.
+    pub async fn load(
+        workspace: WeakView<Workspace>,
+        mut cx: AsyncWindowContext,
+    ) -> Result<View<Self>> {
+        let workspace_id = workspace.update(&mut cx, |workspace, _| workspace.database_id())?;
+        let watches = match workspace_id {
+            Some(workspace_id) => cx
+                .background_executor()
+                .spawn(async move { DEBUGGER_DB.get_watches(workspace_id) })
+                .await
+                .log_err()
+                .flatten()
+                .and_then(|watches| serde_json::from_str::<Vec<String>>(&watches).log_err()),
+            None => None,
+        };
+
+        workspace.update(&mut cx, |workspace, cx| {
+            let panel = cx.new_view(|cx| DebugPanel::new(workspace, cx));
+            if let Some(watches) = watches {
+                let dap_store = workspace.project().read(cx).dap_store().clone();
+                dap_store.update(cx, |dap_store, cx| dap_store.set_watches(watches, cx));
+            }
+            panel
+        })
+    }
+
+    /// Persists the watches of the project, so they are restored with the workspace.
+    fn serialize_watches(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(workspace_id) = self
+            .workspace
+            .upgrade()
+            .and_then(|workspace| workspace.read(cx).database_id())
+        else {
+            return;
+        };
+        let watches = self.dap_store.read(cx).watches().to_vec();
+        self.pending_serialization = cx.background_executor().spawn(
+            async move {
+                DEBUGGER_DB
+                    .save_watches(workspace_id, serde_json::to_string(&watches)?)
+                    .await
+            }
+            .log_err(),
+        );
+    }
+
+    fn add_item(&mut self, client: Arc<DebugAdapterClient>, cx: &mut ViewContext<Self>) {
+        client.set_run_in_terminal_handler(self.run_in_terminal_handler());
+        let workspace = self.workspace.clone();
+        let item = cx.new_view(|cx| DebugPanelItem::new(client.clone(), workspace, cx));
+        self.items.insert(client.id(), item);
+    }
+
+    /// Runs the debuggee of the `runInTerminal` requests of a session in a terminal of
+    /// the workspace. The requests come from the background, so they are handed to
+    /// the task of the panel, which can open terminals.
+    fn run_in_terminal_handler(&self) -> RunInTerminalHandler {
+        let run_in_terminal_tx = self.run_in_terminal_tx.clone();
+        Arc::new(move |arguments| {
+            let (response_tx, response_rx) = oneshot::channel();
+            let sent = run_in_terminal_tx.unbounded_send((arguments, response_tx));
+            async move {
+                sent.map_err(|_| anyhow!("the debug panel was closed"))?;
+                response_rx.await?
+            }
+            .boxed()
+        })
+    }
+
+    fn active_item(&self, cx: &AppContext) -> Option<&View<DebugPanelItem>> {
+        let client = self.dap_store.read(cx).active_client()?;
+        self.items.get(&client.id())
+    }
+
+    fn handle_dap_store_event(
+        &mut self,
+        dap_store: Model<DapStore>,
+        event: &DapStoreEvent,
+        cx: &mut ViewContext<Self>,
+    ) {
+        match event {
+            DapStoreEvent::ClientAdded(client_id) => {
+                if let Some(client) = dap_store.read(cx).client_by_id(*client_id) {
+                    self.add_item(client, cx);
+                }
+            }
+            DapStoreEvent::ClientRemoved(client_id) => {
+                self.items.remove(client_id);
+            }
+            DapStoreEvent::ActiveClientChanged(_) => {
+                let client = dap_store.read(cx).active_client();
+                update_command_palette_filter(client.as_deref(), cx);
+            }
+            DapStoreEvent::Client {
+                client_id,
+                notification,
+            } => {
+                if let Some(item) = self.items.get(client_id) {
+                    item.update(cx, |item, cx| item.handle_notification(notification, cx));
+                }
+                if *notification == ClientNotification::CapabilitiesUpdated {
+                    let client = dap_store.read(cx).active_client();
+                    if client.as_ref().map(|client| client.id()) == Some(*client_id) {
+                        update_command_palette_filter(client.as_deref(), cx);
+                    }
+                }
+            }
+            DapStoreEvent::WatchesChanged => {
+                self.serialize_watches(cx);
+                return;
+            }
+            DapStoreEvent::BreakpointsChanged(_) => return,
+        }
+        cx.notify();
+    }
+
+    /// The sessions of the project, to select the one that stepping and continuing
+    /// apply to, e.g. one of the sessions of a compound configuration.
+    fn render_session_switcher(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let dap_store = self.dap_store.read(cx);
+        let active_client_id = dap_store.active_client().map(|client| client.id());
+
+        h_flex()
+            .px_1()
+            .py_1()
+            .gap_1()
+            .border_b_1()
+            .border_color(cx.theme().colors().border)
+            .children(dap_store.clients().map(|client| {
+                let client_id = client.id();
+                Button::new(("debug-session", client_id.0), client.session_title())
+                    .style(ButtonStyle::Subtle)
+                    .label_size(LabelSize::Small)
+                    .selected(active_client_id == Some(client_id))
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.dap_store.update(cx, |dap_store, cx| {
+                            dap_store.set_active_client(client_id, cx);
+                        });
+                    }))
+            }))
+            .child(
+                IconButton::new("debug-new-session", IconName::Plus)
+                    .icon_size(IconSize::Small)
+                    .tooltip(|cx| Tooltip::for_action("Start Debugging", &StartDebugger, cx))
+                    .on_click(|_, cx| cx.dispatch_action(StartDebugger.boxed_clone())),
+            )
+    }
+
+    fn render_empty_state(&self) -> impl IntoElement {
+        v_flex()
+            .size_full()
+            .items_center()
+            .justify_center()
+            .gap_2()
+            .child(Label::new("No debug session is running").color(Color::Muted))
+            .child(
+                Button::new("start-debugger", "Start Debugging")
+                    .icon(IconName::Play)
+                    .icon_position(IconPosition::Start)
+                    .icon_color(Color::Muted)
+                    .style(ButtonStyle::Filled)
+                    .on_click(|_, cx| cx.dispatch_action(StartDebugger.boxed_clone())),
+            )
+    }
+}
+
+impl EventEmitter<PanelEvent> for DebugPanel {}
+
+impl FocusableView for DebugPanel {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for DebugPanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let content = match self.active_item(cx) {
+            Some(item) => item.clone().into_any_element(),
+            None => self.render_empty_state().into_any_element(),
+        };
+
+        v_flex()
+            .key_context("DebugPanel")
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .when(!self.items.is_empty(), |this| {
+                this.child(self.render_session_switcher(cx))
+            })
+            .child(content)
+    }
+}
+
+impl Panel for DebugPanel {
+    fn persistent_name() -> &'static str {
+        "DebugPanel"
+    }
+
+    fn position(&self, _cx: &WindowContext) -> DockPosition {
+        self.position
+    }
+
+    fn position_is_valid(&self, _position: DockPosition) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.position = position;
+        cx.notify();
+    }
+
+    fn size(&self, _cx: &WindowContext) -> Pixels {
+        self.size.unwrap_or(px(300.))
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        self.size = size;
+        cx.notify();
+    }
+
+    fn icon(&self, _cx: &WindowContext) -> Option<IconName> {
+        Some(IconName::Debug)
+    }
+
+    fn icon_tooltip(&self, _cx: &WindowContext) -> Option<&'static str> {
+        Some("Debug Panel")
+    }
+
+    fn toggle_action(&self) -> Box<dyn Action> {
+        Box::new(ToggleFocus)
+    }
+}
+
+/// Spawns the debuggee of a `runInTerminal` request in a new terminal of the terminal
+/// panel, reporting the id of its process to the adapter.
+async fn run_in_terminal(
+    workspace: WeakView<Workspace>,
+    arguments: RunInTerminalRequestArguments,
+    cx: &mut AsyncWindowContext,
+) -> Result<RunInTerminalResponse> {
+    let (command, args) = arguments
+        .args
+        .split_first()
+        .context("runInTerminal request without a command")?;
+    let env = arguments
+        .env
+        .as_ref()
+        .and_then(|env| env.as_object())
+        .map(|env| {
+            env.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| {
+                    let value = value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), ToOwned::to_owned);
+                    (key.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let title = arguments
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("Debug: {command}"));
+    let spawn_in_terminal = SpawnInTerminal {
+        id: TaskId(format!("debug-{title}")),
+        full_label: title.clone(),
+        label: title,
+        command: command.clone(),
+        args: args.to_vec(),
+        command_label: arguments.args.join(" "),
+        cwd: Some(TerminalWorkDir::Local(arguments.cwd.clone().into())),
+        env,
+        use_new_terminal: true,
+        allow_concurrent_runs: true,
+        reveal: RevealStrategy::Always,
+    };
+
+    let terminal_panel = workspace
+        .update(cx, |workspace, cx| workspace.panel::<TerminalPanel>(cx))?
+        .context("no terminal panel to run the debuggee in")?;
+    let terminal = terminal_panel
+        .update(cx, |terminal_panel, cx| {
+            terminal_panel.spawn_in_new_terminal(spawn_in_terminal, cx)
+        })?
+        .await?;
+    let pid = terminal
+        .update(cx, |terminal, _| terminal.pty_info.pid())?
+        .context("failed to get the process of the debuggee")?;
+
+    Ok(RunInTerminalResponse {
+        process_id: Some(pid.as_u32() as u64),
+        shell_process_id: None,
+    })
+}
@@ -0,0 +1,509 @@
+use crate::{
+    console::Console, Continue, Pause, Restart, RestartFrame, StepBack, StepIn, StepOut, StepOver,
+    Stop, Terminate,
+};
+use anyhow::{Context as _, Result};
+use dap::{
+    client::{
+        ClientNotification, ConfigurationPhase, DebugAdapterClient, SessionAction,
+        StackFrameLocation, ThreadStatus, ThreadSummary,
+    },
+    StoppedEventReason,
+};
+use editor::{scroll::Autoscroll, Bias, Editor};
+use gpui::{
+    Action, AppContext, AsyncWindowContext, FocusHandle, FocusableView, Render, View, ViewContext,
+    WeakView,
+};
+use language::Point;
+use std::{path::PathBuf, sync::Arc};
+use ui::{prelude::*, ListItem, Tooltip};
+use workspace::Workspace;
+
+/// The view of a single debug session in the [`DebugPanel`](crate::DebugPanel).
+pub struct DebugPanelItem {
+    client: Arc<DebugAdapterClient>,
+    workspace: WeakView<Workspace>,
+    console: View<Console>,
+    focus_handle: FocusHandle,
+}
+
+impl DebugPanelItem {
+    pub fn new(
+        client: Arc<DebugAdapterClient>,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let console = cx.new_view(|cx| Console::new(client.clone(), cx));
+        Self {
+            client,
+            workspace,
+            console,
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    pub fn handle_notification(
+        &mut self,
+        notification: &ClientNotification,
+        cx: &mut ViewContext<Self>,
+    ) {
+        match notification {
+            ClientNotification::ThreadStopped {
+                thread_id: Some(thread_id),
+                preserve_focus,
+                ..
+            } => self.handle_thread_stopped(*thread_id, *preserve_focus, cx),
+            ClientNotification::OutputReceived => {
+                self.console
+                    .update(cx, |console, cx| console.update_output(cx));
+            }
+            _ => {}
+        }
+        cx.notify();
+    }
+
+    /// Makes the thread that stopped the current one, unless the adapter asked to keep
+    /// the focus, and fetches its stack when the client doesn't do that itself.
+    fn handle_thread_stopped(
+        &mut self,
+        thread_id: u64,
+        preserve_focus: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let client = &self.client;
+        let was_current_thread = client
+            .current_thread_id()
+            .map_or(true, |current_thread_id| current_thread_id == thread_id);
+        if !preserve_focus || client.current_thread_id().is_none() {
+            client.set_current_thread_id(Some(thread_id));
+        }
+
+        // the client refreshes the current thread to re-evaluate the watches, and the
+        // thread it stepped
+        let refreshed_by_client = (was_current_thread && !client.watches().is_empty())
+            || client.thread_is_busy(thread_id);
+        if client.current_thread_id() == Some(thread_id) && !refreshed_by_client {
+            let client = client.clone();
+            cx.background_executor()
+                .spawn(async move { client.refresh_thread(thread_id).await })
+                .detach_and_log_err(cx);
+        }
+
+        // `thread` events don't name the threads they announce
+        if client.thread_state_by_id(thread_id).name.is_none() {
+            let client = client.clone();
+            cx.background_executor()
+                .spawn(async move { client.threads().await })
+                .detach_and_log_err(cx);
+        }
+    }
+
+    /// Makes a stopped thread the current one, fetching its call stack.
+    fn select_thread(&mut self, thread_id: u64, cx: &mut ViewContext<Self>) {
+        self.client.set_current_thread_id(Some(thread_id));
+        // the cached stack may be the one of an earlier stop
+        let client = self.client.clone();
+        cx.background_executor()
+            .spawn(async move { client.refresh_thread(thread_id).await })
+            .detach_and_log_err(cx);
+        cx.notify();
+    }
+
+    /// Selects a frame in the call stack, fetching its scopes and showing its source
+    /// in the editor.
+    fn select_stack_frame(
+        &mut self,
+        thread_id: u64,
+        stack_frame_id: u64,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let client = self.client.clone();
+        let workspace = self.workspace.clone();
+        cx.spawn(|this, mut cx| async move {
+            let location = client.select_stack_frame(thread_id, stack_frame_id).await;
+            this.update(&mut cx, |_, cx| cx.notify())?;
+            if let Some(location) = location? {
+                open_stack_frame_location(workspace, location, &mut cx).await?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn render_control(
+        &self,
+        id: &'static str,
+        icon: IconName,
+        tooltip: &'static str,
+        action: Box<dyn Action>,
+        disabled: bool,
+    ) -> impl IntoElement {
+        let tooltip_action = action.boxed_clone();
+        IconButton::new(id, icon)
+            .icon_size(IconSize::Small)
+            .disabled(disabled)
+            .on_click(move |_, cx| cx.dispatch_action(action.boxed_clone()))
+            .tooltip(move |cx| Tooltip::for_action(tooltip, &*tooltip_action, cx))
+    }
+
+    /// The controls of the session, leaving out the ones its adapter doesn't support.
+    fn render_controls(&self) -> impl IntoElement {
+        let supported_actions = self.client.supported_actions();
+        let stopped = self.client.current_thread_id().map_or(false, |thread_id| {
+            self.client.thread_state_by_id(thread_id).status == ThreadStatus::Stopped
+        });
+
+        h_flex()
+            .gap_1()
+            .map(|this| {
+                if stopped {
+                    this.child(self.render_control(
+                        "debug-continue",
+                        IconName::Play,
+                        "Continue",
+                        Box::new(Continue),
+                        false,
+                    ))
+                } else {
+                    this.child(self.render_control(
+                        "debug-pause",
+                        IconName::Pause,
+                        "Pause",
+                        Box::new(Pause),
+                        false,
+                    ))
+                }
+            })
+            .when(
+                supported_actions.contains(&SessionAction::StepBack),
+                |this| {
+                    this.child(self.render_control(
+                        "debug-step-back",
+                        IconName::ArrowLeft,
+                        "Step Back",
+                        Box::new(StepBack),
+                        !stopped,
+                    ))
+                },
+            )
+            .child(self.render_control(
+                "debug-step-over",
+                IconName::ArrowRight,
+                "Step Over",
+                Box::new(StepOver),
+                !stopped,
+            ))
+            .child(self.render_control(
+                "debug-step-in",
+                IconName::ArrowDown,
+                "Step In",
+                Box::new(StepIn),
+                !stopped,
+            ))
+            .child(self.render_control(
+                "debug-step-out",
+                IconName::ArrowUp,
+                "Step Out",
+                Box::new(StepOut),
+                !stopped,
+            ))
+            .when(
+                supported_actions.contains(&SessionAction::RestartFrame),
+                |this| {
+                    this.child(self.render_control(
+                        "debug-restart-frame",
+                        IconName::ArrowCircle,
+                        "Restart Frame",
+                        Box::new(RestartFrame),
+                        !stopped,
+                    ))
+                },
+            )
+            .when(
+                supported_actions.contains(&SessionAction::Restart),
+                |this| {
+                    this.child(self.render_control(
+                        "debug-restart",
+                        IconName::Rerun,
+                        "Restart",
+                        Box::new(Restart),
+                        false,
+                    ))
+                },
+            )
+            .when(
+                supported_actions.contains(&SessionAction::Terminate),
+                |this| {
+                    this.child(self.render_control(
+                        "debug-terminate",
+                        IconName::XCircle,
+                        "Terminate",
+                        Box::new(Terminate),
+                        false,
+                    ))
+                },
+            )
+            .child(self.render_control(
+                "debug-stop",
+                IconName::Close,
+                "Stop",
+                Box::new(Stop),
+                false,
+            ))
+    }
+
+    /// The threads of the session with their status, the ones that ended last and dimmed.
+    fn render_threads(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let (finished_threads, threads): (Vec<_>, Vec<_>) = self
+            .client
+            .thread_states_snapshot()
+            .into_iter()
+            .partition(|thread| thread.status.is_finished());
+        if threads.is_empty() && finished_threads.is_empty() {
+            return None;
+        }
+
+        let current_thread_id = self.client.current_thread_id();
+        Some(
+            v_flex()
+                .id("debug-threads")
+                .max_h_48()
+                .overflow_y_scroll()
+                .py_1()
+                .border_b_1()
+                .border_color(cx.theme().colors().border)
+                .child(
+                    div().px_2().child(
+                        Label::new("Threads")
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    ),
+                )
+                .children(
+                    threads
+                        .into_iter()
+                        .chain(finished_threads)
+                        .map(|thread| self.render_thread(thread, current_thread_id, cx)),
+                ),
+        )
+    }
+
+    fn render_thread(
+        &self,
+        thread: ThreadSummary,
+        current_thread_id: Option<u64>,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let thread_id = thread.thread_id;
+        let stopped = thread.status == ThreadStatus::Stopped;
+        let (icon, icon_color) = match thread.status {
+            ThreadStatus::Running => (IconName::Play, Color::Success),
+            ThreadStatus::Stopped => (IconName::Pause, Color::Warning),
+            ThreadStatus::Exited | ThreadStatus::Ended => (IconName::Dash, Color::Disabled),
+        };
+        let name = thread.name.unwrap_or_else(|| format!("Thread {thread_id}"));
+
+        ListItem::new(("debug-thread", thread_id as usize))
+            .selected(current_thread_id == Some(thread_id))
+            .start_slot(Icon::new(icon).size(IconSize::Small).color(icon_color))
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Label::new(name).size(LabelSize::Small).color(
+                        if thread.status.is_finished() {
+                            Color::Disabled
+                        } else {
+                            Color::Default
+                        },
+                    ))
+                    .children(thread.stop_reason.as_ref().map(|reason| {
+                        Label::new(stop_reason_label(reason))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                    })),
+            )
+            .when(stopped, |this| {
+                this.on_click(cx.listener(move |this, _, cx| this.select_thread(thread_id, cx)))
+            })
+    }
+
+    /// The stack frames of the current thread while it is stopped, the selected one
+    /// highlighted.
+    fn render_stack_frames(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let thread_id = self.client.current_thread_id()?;
+        let thread_state = self.client.thread_state_by_id(thread_id);
+        if thread_state.status != ThreadStatus::Stopped || thread_state.stack_frames.is_empty() {
+            return None;
+        }
+
+        Some(
+            v_flex()
+                .id("debug-stack-frames")
+                .overflow_y_scroll()
+                .py_1()
+                .child(
+                    div().px_2().child(
+                        Label::new("Call Stack")
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    ),
+                )
+                .children(thread_state.stack_frames.into_iter().map(|stack_frame| {
+                    let stack_frame_id = stack_frame.id;
+                    let location = stack_frame
+                        .source
+                        .and_then(|source| source.path.or(source.name))
+                        .map(|path| format!("{path}:{}", stack_frame.line));
+                    ListItem::new(("debug-stack-frame", stack_frame_id as usize))
+                        .selected(thread_state.current_stack_frame_id == Some(stack_frame_id))
+                        .on_click(cx.listener(move |this, _, cx| {
+                            this.select_stack_frame(thread_id, stack_frame_id, cx)
+                        }))
+                        .child(
+                            h_flex()
+                                .gap_2()
+                                .child(Label::new(stack_frame.name).size(LabelSize::Small))
+                                .children(location.map(|location| {
+                                    Label::new(location)
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted)
+                                })),
+                        )
+                })),
+        )
+    }
+
+    /// The steps of the DAP handshake, while the session is still starting.
+    fn render_startup_progress(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let reached_phases = self.client.configuration_phases();
+        let phases = [
+            ConfigurationPhase::InitializeSent,
+            ConfigurationPhase::InitializedReceived,
+            ConfigurationPhase::BreakpointsSent,
+            ConfigurationPhase::ConfigurationDone,
+        ];
+
+        v_flex()
+            .p_2()
+            .gap_1()
+            .child(Label::new("Starting debug session…"))
+            .children(phases.into_iter().enumerate().map(|(ix, phase)| {
+                let reached = reached_phases.contains(&phase);
+                let done = reached
+                    && phases
+                        .get(ix + 1)
+                        .map_or(false, |next| reached_phases.contains(next));
+                let (icon, color) = if done {
+                    (IconName::Check, Color::Success)
+                } else if reached {
+                    (IconName::ArrowRight, Color::Accent)
+                } else {
+                    (IconName::Dash, Color::Muted)
+                };
+                h_flex()
+                    .gap_2()
+                    .child(Icon::new(icon).size(IconSize::Small).color(color))
+                    .child(
+                        Label::new(phase.to_string())
+                            .size(LabelSize::Small)
+                            .color(if reached {
+                                Color::Default
+                            } else {
+                                Color::Muted
+                            }),
+                    )
+            }))
+            .border_b_1()
+            .border_color(cx.theme().colors().border)
+    }
+}
+
+impl FocusableView for DebugPanelItem {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for DebugPanelItem {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .child(
+                h_flex()
+                    .justify_between()
+                    .px_2()
+                    .py_1()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(Label::new(self.client.session_title()))
+                    .child(self.render_controls()),
+            )
+            .when(
+                self.client.configuration_phase() < ConfigurationPhase::ConfigurationDone,
+                |this| this.child(self.render_startup_progress(cx)),
+            )
+            .children(self.render_threads(cx))
+            .children(self.render_stack_frames(cx))
+            .child(self.console.clone())
+    }
+}
+
+/// Why a thread is stopped, as shown next to it in the thread list.
+fn stop_reason_label(reason: &StoppedEventReason) -> &'static str {
+    match reason {
+        StoppedEventReason::Step => "Paused on step",
+        StoppedEventReason::Breakpoint
+        | StoppedEventReason::FunctionBreakpoint
+        | StoppedEventReason::DataBreakpoint
+        | StoppedEventReason::InstructionBreakpoint => "Paused on breakpoint",
+        StoppedEventReason::Exception => "Paused on exception",
+        StoppedEventReason::Entry => "Paused on entry",
+        StoppedEventReason::Goto => "Paused after goto",
+        _ => "Paused",
+    }
+}
+
+/// Opens the source of a stack frame with the cursor at the frame's position. A source
+/// without a local file is opened read-only, with the content the adapter sent for it.
+async fn open_stack_frame_location(
+    workspace: WeakView<Workspace>,
+    location: StackFrameLocation,
+    cx: &mut AsyncWindowContext,
+) -> Result<()> {
+    let editor = match (location.source.path, location.content) {
+        (_, Some(content)) => workspace.update(cx, |workspace, cx| {
+            let project = workspace.project().clone();
+            let buffer = project.update(cx, |project, cx| {
+                project.create_local_buffer(&content, None, cx)
+            });
+            let editor = cx.new_view(|cx| {
+                let mut editor = Editor::for_buffer(buffer, Some(project), cx);
+                editor.set_read_only(true);
+                editor
+            });
+            workspace.add_item_to_active_pane(Box::new(editor.clone()), None, cx);
+            editor
+        })?,
+        (Some(path), None) => workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_abs_path(PathBuf::from(path), true, cx)
+            })?
+            .await?
+            .downcast::<Editor>()
+            .context("the source of the stack frame wasn't opened in an editor")?,
+        (None, None) => return Ok(()),
+    };
+
+    editor.update(cx, |editor, cx| {
+        let snapshot = editor.buffer().read(cx).snapshot(cx);
+        let point = snapshot.clip_point(
+            Point::new(location.row as u32, location.column as u32),
+            Bias::Left,
+        );
+        editor.change_selections(Some(Autoscroll::center()), cx, |selections| {
+            selections.select_ranges([point..point])
+        });
+    })
+}
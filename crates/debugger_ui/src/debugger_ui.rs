@@ -0,0 +1,92 @@
+mod console;
+mod debugger_panel;
+mod debugger_panel_item;
+mod persistence;
+mod start_debugger_modal;
+
+use command_palette_hooks::CommandPaletteFilter;
+use dap::client::{DebugAdapterClient, SessionAction};
+pub use debugger_panel::DebugPanel;
+use gpui::{actions, AppContext};
+use std::any::TypeId;
+use workspace::Workspace;
+
+actions!(
+    debugger,
+    [
+        StartDebugger,
+        Continue,
+        Pause,
+        Stop,
+        StepOver,
+        StepIn,
+        StepOut,
+        StepBack,
+        RestartFrame,
+        Restart,
+        Terminate,
+    ]
+);
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        debugger_panel::register(workspace);
+        start_debugger_modal::register(workspace);
+    })
+    .detach();
+
+    update_command_palette_filter(None, cx);
+}
+
+/// The actions that control a debug session, which every adapter supports.
+fn session_action_types() -> [TypeId; 6] {
+    [
+        TypeId::of::<Continue>(),
+        TypeId::of::<Pause>(),
+        TypeId::of::<Stop>(),
+        TypeId::of::<StepOver>(),
+        TypeId::of::<StepIn>(),
+        TypeId::of::<StepOut>(),
+    ]
+}
+
+/// The action that needs the adapter to support the [`SessionAction`], if there is one.
+fn adapter_action_type(action: SessionAction) -> Option<TypeId> {
+    match action {
+        SessionAction::StepBack => Some(TypeId::of::<StepBack>()),
+        SessionAction::RestartFrame => Some(TypeId::of::<RestartFrame>()),
+        SessionAction::Restart => Some(TypeId::of::<Restart>()),
+        SessionAction::Terminate => Some(TypeId::of::<Terminate>()),
+        _ => None,
+    }
+}
+
+/// Only shows the actions of the debugger in the command palette that the active session,
+/// if there is one, supports.
+pub(crate) fn update_command_palette_filter(
+    active_client: Option<&DebugAdapterClient>,
+    cx: &mut AppContext,
+) {
+    let adapter_action_types = SessionAction::ALL
+        .into_iter()
+        .filter_map(adapter_action_type)
+        .collect::<Vec<_>>();
+    let supported_action_types = active_client
+        .map(|client| {
+            client
+                .supported_actions()
+                .into_iter()
+                .filter_map(adapter_action_type)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    CommandPaletteFilter::update_global(cx, |filter, _| {
+        filter.hide_action_types(&session_action_types());
+        filter.hide_action_types(&adapter_action_types);
+        if active_client.is_some() {
+            filter.show_action_types(session_action_types().iter());
+            filter.show_action_types(supported_action_types.iter());
+        }
+    });
+}
@@ -0,0 +1,31 @@
+use db::{define_connection, query, sqlez_macros::sql};
+use workspace::{WorkspaceDb, WorkspaceId};
+
+define_connection! {
+    pub static ref DEBUGGER_DB: DebuggerDb<WorkspaceDb> =
+        &[sql!(
+            CREATE TABLE debug_watches (
+                workspace_id INTEGER PRIMARY KEY,
+                watches TEXT NOT NULL,
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+            ) STRICT;
+        )];
+}
+
+impl DebuggerDb {
+    query! {
+        pub async fn save_watches(workspace_id: WorkspaceId, watches: String) -> Result<()> {
+            INSERT OR REPLACE INTO debug_watches(workspace_id, watches)
+            VALUES (?, ?)
+        }
+    }
+
+    query! {
+        pub fn get_watches(workspace_id: WorkspaceId) -> Result<Option<String>> {
+            SELECT watches
+            FROM debug_watches
+            WHERE workspace_id = ?
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{DebugPanel, StartDebugger};
+use anyhow::{anyhow, Context as _};
+use dap::launch_config::{parse_launch_configurations, LaunchConfiguration};
+use fuzzy::{match_strings, StringMatch, StringMatchCandidate};
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model, Task, View,
+    ViewContext, VisualContext, WeakView,
+};
+use picker::{Picker, PickerDelegate};
+use project::Project;
+use std::{path::Path, sync::Arc};
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::{paths::LOCAL_DEBUG_RELATIVE_PATH, ResultExt};
+use workspace::{ModalView, Workspace};
+
+pub(crate) fn register(workspace: &mut Workspace) {
+    workspace.register_action(StartDebuggerModal::toggle);
+}
+
+/// Picks one of the configurations in the `.zed/debug.json` files of the project to
+/// start a debug session with.
+pub struct StartDebuggerModal {
+    picker: View<Picker<StartDebuggerDelegate>>,
+}
+
+impl StartDebuggerModal {
+    fn toggle(workspace: &mut Workspace, _: &StartDebugger, cx: &mut ViewContext<Workspace>) {
+        let project = workspace.project().clone();
+        let fs = workspace.app_state().fs.clone();
+        let worktree_paths = project
+            .read(cx)
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path())
+            .collect::<Vec<_>>();
+
+        cx.spawn(|workspace, mut cx| async move {
+            let mut configurations = Vec::new();
+            let mut errors = Vec::new();
+            for worktree_path in worktree_paths {
+                let path = worktree_path.join(*LOCAL_DEBUG_RELATIVE_PATH);
+                let Ok(contents) = fs.load(&path).await else {
+                    continue;
+                };
+                match parse_launch_configurations(&contents) {
+                    Ok(parsed) => configurations.extend(
+                        parsed
+                            .into_iter()
+                            .map(|configuration| (worktree_path.clone(), configuration)),
+                    ),
+                    Err(error) => errors.push(error.context(format!("failed to load {path:?}"))),
+                }
+            }
+
+            workspace.update(&mut cx, |workspace, cx| {
+                for error in errors {
+                    workspace.show_error(&error, cx);
+                }
+                let workspace_handle = cx.view().downgrade();
+                workspace.toggle_modal(cx, |cx| {
+                    Self::new(configurations, project, workspace_handle, cx)
+                });
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn new(
+        configurations: Vec<(Arc<Path>, LaunchConfiguration)>,
+        project: Model<Project>,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let delegate =
+            StartDebuggerDelegate::new(cx.view().downgrade(), configurations, project, workspace);
+        let picker = cx.new_view(|cx| Picker::uniform_list(delegate, cx));
+        Self { picker }
+    }
+}
+
+impl Render for StartDebuggerModal {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl FocusableView for StartDebuggerModal {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for StartDebuggerModal {}
+impl ModalView for StartDebuggerModal {}
+
+pub struct StartDebuggerDelegate {
+    modal: WeakView<StartDebuggerModal>,
+    /// The configurations with the worktree they were defined in.
+    configurations: Vec<(Arc<Path>, LaunchConfiguration)>,
+    project: Model<Project>,
+    workspace: WeakView<Workspace>,
+    candidates: Vec<StringMatchCandidate>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+}
+
+impl StartDebuggerDelegate {
+    fn new(
+        modal: WeakView<StartDebuggerModal>,
+        configurations: Vec<(Arc<Path>, LaunchConfiguration)>,
+        project: Model<Project>,
+        workspace: WeakView<Workspace>,
+    ) -> Self {
+        let candidates = configurations
+            .iter()
+            .enumerate()
+            .map(|(candidate_id, (_, configuration))| {
+                StringMatchCandidate::new(candidate_id, configuration.label.clone())
+            })
+            .collect();
+
+        Self {
+            modal,
+            configurations,
+            project,
+            workspace,
+            candidates,
+            matches: Vec::new(),
+            selected_index: 0,
+        }
+    }
+}
+
+impl PickerDelegate for StartDebuggerDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self, _cx: &mut WindowContext) -> Arc<str> {
+        "Select a debug configuration...".into()
+    }
+
+    fn no_matches_text(&self, _cx: &mut WindowContext) -> SharedString {
+        if self.configurations.is_empty() {
+            format!(
+                "No debug configurations, add them to {}",
+                LOCAL_DEBUG_RELATIVE_PATH.display()
+            )
+            .into()
+        } else {
+            "No matches".into()
+        }
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        if let Some(mat) = self.matches.get(self.selected_index) {
+            let (worktree_path, configuration) = self.configurations[mat.candidate_id].clone();
+            let dap_store = self.project.read(cx).dap_store().clone();
+            let workspace = self.workspace.clone();
+            cx.spawn(|_, mut cx| async move {
+                let result = async {
+                    let binary = configuration.adapter.clone().ok_or_else(|| {
+                        anyhow!(
+                            "debug configuration `{}` doesn't say how to start its adapter",
+                            configuration.label
+                        )
+                    })?;
+                    let args = configuration.request_args(&worktree_path);
+                    dap_store
+                        .update(&mut cx, |dap_store, cx| {
+                            dap_store.start_client(
+                                configuration.config.clone(),
+                                binary,
+                                worktree_path.to_path_buf(),
+                                Some(args),
+                                cx,
+                            )
+                        })?
+                        .await
+                        .with_context(|| {
+                            format!("failed to start debugging `{}`", configuration.label)
+                        })
+                }
+                .await;
+
+                if let Err(error) = result {
+                    workspace.update(&mut cx, |workspace, cx| workspace.show_error(&error, cx))?;
+                }
+                anyhow::Ok(())
+            })
+            .detach_and_log_err(cx);
+
+            self.workspace
+                .update(cx, |workspace, cx| {
+                    workspace.focus_panel::<DebugPanel>(cx);
+                })
+                .log_err();
+        }
+        self.dismissed(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.modal
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let background = cx.background_executor().clone();
+        let candidates = self.candidates.clone();
+        cx.spawn(|this, mut cx| async move {
+            let matches = if query.is_empty() {
+                candidates
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, candidate)| StringMatch {
+                        candidate_id: index,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                match_strings(
+                    &candidates,
+                    &query,
+                    false,
+                    100,
+                    &Default::default(),
+                    background,
+                )
+                .await
+            };
+
+            this.update(&mut cx, |this, cx| {
+                let delegate = &mut this.delegate;
+                delegate.matches = matches;
+                delegate.selected_index = delegate
+                    .selected_index
+                    .min(delegate.matches.len().saturating_sub(1));
+                cx.notify();
+            })
+            .log_err();
+        })
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let mat = &self.matches[ix];
+        let (_, configuration) = &self.configurations[mat.candidate_id];
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .child(HighlightedLabel::new(
+                            mat.string.clone(),
+                            mat.positions.clone(),
+                        ))
+                        .child(
+                            Label::new(configuration.config.id.clone())
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        ),
+                ),
+        )
+    }
+}
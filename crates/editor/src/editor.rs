@@ -50,6 +50,7 @@ use client::{Collaborator, ParticipantIndex};
 use clock::ReplicaId;
 use collections::{BTreeMap, Bound, HashMap, HashSet, VecDeque};
 use convert_case::{Case, Casing};
+use dap::{client::ClientNotification, dap_store::DapStoreEvent};
 use debounced_delay::DebouncedDelay;
 use display_map::*;
 pub use display_map::{DisplayPoint, FoldPlaceholder};
@@ -1700,6 +1701,28 @@ impl Editor {
                 project_subscriptions.push(cx.observe(&task_inventory, |editor, _, cx| {
                     editor.tasks_update_task = Some(editor.refresh_runnables(cx));
                 }));
+                let dap_store = project.read(cx).dap_store().clone();
+                project_subscriptions.push(cx.subscribe(&dap_store, |_, _, event, cx| {
+                    // repaint the gutter when the line the debuggee is paused at may have moved
+                    let moves_execution_marker = match event {
+                        DapStoreEvent::ActiveClientChanged(_) | DapStoreEvent::ClientRemoved(_) => {
+                            true
+                        }
+                        DapStoreEvent::Client { notification, .. } => matches!(
+                            notification,
+                            ClientNotification::ThreadStopped { .. }
+                                | ClientNotification::ThreadContinued { .. }
+                                | ClientNotification::StackFramesUpdated { .. }
+                                | ClientNotification::StackFrameSelected { .. }
+                                | ClientNotification::SessionEnded
+                                | ClientNotification::SessionFailed { .. }
+                        ),
+                        _ => false,
+                    };
+                    if moves_execution_marker {
+                        cx.notify();
+                    }
+                }));
             }
         }
 
@@ -4780,6 +4803,32 @@ impl Editor {
         }
     }
 
+    /// The row the current thread of the active debug session is paused at, when it is
+    /// in the buffer of this editor, and whether it is in the top stack frame.
+    fn execution_marker_row(&self, cx: &AppContext) -> Option<(MultiBufferRow, bool)> {
+        let project = self.project.as_ref()?.read(cx);
+        let marker = project
+            .dap_store()
+            .read(cx)
+            .active_client()?
+            .execution_marker()?;
+        let buffer = self.buffer.read(cx).as_singleton()?;
+        let abs_path = buffer.read(cx).file()?.as_local()?.abs_path(cx);
+        (abs_path.as_path() == &*marker.path)
+            .then(|| (MultiBufferRow(marker.row as u32), marker.is_top_frame))
+    }
+
+    fn render_execution_marker(&self, is_top_frame: bool) -> IconButton {
+        IconButton::new("execution_marker", ui::IconName::ArrowRight)
+            .icon_size(IconSize::XSmall)
+            .size(ui::ButtonSize::None)
+            .icon_color(if is_top_frame {
+                Color::Warning
+            } else {
+                Color::Muted
+            })
+    }
+
     fn render_run_indicator(
         &self,
         _style: &EditorStyle,
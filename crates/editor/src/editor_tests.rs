@@ -8,6 +8,10 @@ use crate::{
     },
     JoinLines,
 };
+use dap::{
+    client::{DebugAdapterClient, ThreadState, ThreadStatus},
+    StackFrame,
+};
 use futures::StreamExt;
 use gpui::{div, TestAppContext, UpdateGlobal, VisualTestContext, WindowBounds, WindowOptions};
 use indoc::indoc;
@@ -12062,6 +12066,74 @@ fn test_flap_insertion_and_rendering(cx: &mut TestAppContext) {
     assert!(!snapshot.is_line_folded(MultiBufferRow(1)));
 }
 
+#[gpui::test]
+async fn test_execution_marker(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(Default::default(), cx).await;
+    cx.set_state(indoc! {"
+        fn main() {
+            let count = 1;ˇ
+        }
+    "});
+    let (client, _adapter) =
+        DebugAdapterClient::with_capabilities(Default::default(), cx.executor());
+    let client = Arc::new(client);
+    let stack_frame = |id: u64, path: &str, line: u64| {
+        serde_json::from_value::<StackFrame>(json!({
+            "id": id,
+            "name": "main",
+            "line": line,
+            "column": 1,
+            "source": { "path": path },
+        }))
+        .unwrap()
+    };
+    client.thread_states().insert(
+        1,
+        ThreadState {
+            status: ThreadStatus::Stopped,
+            stack_frames: vec![
+                stack_frame(10, "/root/dir/file.rs", 2),
+                stack_frame(11, "/root/dir/lib.rs", 5),
+            ],
+            ..Default::default()
+        },
+    );
+    client.set_current_thread_id(Some(1));
+    cx.update_editor(|editor, cx| {
+        let dap_store = editor
+            .project
+            .as_ref()
+            .unwrap()
+            .read(cx)
+            .dap_store()
+            .clone();
+        dap_store.update(cx, |dap_store, cx| dap_store.add_client(client.clone(), cx));
+        assert_eq!(
+            editor.execution_marker_row(cx),
+            Some((MultiBufferRow(1), true))
+        );
+    });
+
+    // a frame selected in another file isn't shown
+    client
+        .thread_states()
+        .get_mut(&1)
+        .unwrap()
+        .current_stack_frame_id = Some(11);
+    cx.update_editor(|editor, cx| assert_eq!(editor.execution_marker_row(cx), None));
+
+    // the marker is cleared once the thread continues
+    client
+        .thread_states()
+        .get_mut(&1)
+        .unwrap()
+        .current_stack_frame_id = Some(10);
+    client.update_thread_state_status(1, ThreadStatus::Running);
+    cx.update_editor(|editor, cx| assert_eq!(editor.execution_marker_row(cx), None));
+}
+
 fn empty_range(row: usize, column: usize) -> Range<DisplayPoint> {
     let point = DisplayPoint::new(DisplayRow(row as u32), column as u32);
     point..point
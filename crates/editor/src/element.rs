@@ -1573,6 +1573,39 @@ impl EditorElement {
         })
     }
 
+    /// Lays out the arrow on the line the debuggee is paused at, returning the row of
+    /// the line with it.
+    fn layout_execution_marker(
+        &self,
+        line_height: Pixels,
+        scroll_pixel_position: gpui::Point<Pixels>,
+        gutter_dimensions: &GutterDimensions,
+        gutter_hitbox: &Hitbox,
+        snapshot: &EditorSnapshot,
+        cx: &mut WindowContext,
+    ) -> Option<(MultiBufferRow, AnyElement)> {
+        let editor = self.editor.read(cx);
+        let (row, is_top_frame) = editor.execution_marker_row(cx)?;
+        if snapshot.is_line_folded(row) {
+            return None;
+        }
+        let display_row = MultiBufferPoint::new(row.0, 0)
+            .to_display_point(snapshot)
+            .row();
+        let button = editor.render_execution_marker(is_top_frame);
+
+        let button = prepaint_gutter_button(
+            button,
+            display_row,
+            line_height,
+            gutter_dimensions,
+            scroll_pixel_position,
+            gutter_hitbox,
+            cx,
+        );
+        Some((row, button))
+    }
+
     fn layout_code_actions_indicator(
         &self,
         line_height: Pixels,
@@ -2869,6 +2902,10 @@ impl EditorElement {
             if let Some(indicator) = layout.code_actions_indicator.as_mut() {
                 indicator.paint(cx);
             }
+
+            if let Some(marker) = layout.execution_marker.as_mut() {
+                marker.paint(cx);
+            }
         });
     }
 
@@ -4849,6 +4886,15 @@ impl Element for EditorElement {
 
                     let gutter_settings = EditorSettings::get_global(cx).gutter;
 
+                    let execution_marker = self.layout_execution_marker(
+                        line_height,
+                        scroll_pixel_position,
+                        &gutter_dimensions,
+                        &gutter_hitbox,
+                        &snapshot,
+                        cx,
+                    );
+
                     let mut _context_menu_visible = false;
                     let mut code_actions_indicator = None;
                     if let Some(newest_selection_head) = newest_selection_head {
@@ -4880,8 +4926,13 @@ impl Element for EditorElement {
                                     let row = range.start.row;
                                     let has_test_indicator =
                                         self.editor.read(cx).tasks.contains_key(&(buffer_id, row));
+                                    // the execution marker takes the place of the indicator
+                                    let has_execution_marker =
+                                        execution_marker.as_ref().map_or(false, |(row, _)| {
+                                            row.0 == newest_selection_point.row
+                                        });
 
-                                    if !has_test_indicator {
+                                    if !has_test_indicator && !has_execution_marker {
                                         code_actions_indicator = self
                                             .layout_code_actions_indicator(
                                                 line_height,
@@ -5004,6 +5055,7 @@ impl Element for EditorElement {
                         mouse_context_menu,
                         test_indicators,
                         code_actions_indicator,
+                        execution_marker: execution_marker.map(|(_, marker)| marker),
                         gutter_fold_toggles,
                         flap_trailers,
                         tab_invisible,
@@ -5126,6 +5178,7 @@ pub struct EditorLayout {
     visible_cursors: Vec<CursorLayout>,
     selections: Vec<(PlayerColor, Vec<SelectionLayout>)>,
     code_actions_indicator: Option<AnyElement>,
+    execution_marker: Option<AnyElement>,
     test_indicators: Vec<AnyElement>,
     gutter_fold_toggles: Vec<Option<AnyElement>>,
     flap_trailers: Vec<Option<FlapTrailerLayout>>,
@@ -5,20 +5,21 @@ use crate::{
     Anchor, AnchorRangeExt, DisplayPoint, DisplayRow, Editor, EditorSettings, EditorSnapshot,
     EditorStyle, Hover, RangeToAnchorExt,
 };
+use dap::client::{DebugAdapterClient, ThreadStatus};
 use futures::{stream::FuturesUnordered, FutureExt};
 use gpui::{
-    div, px, AnyElement, CursorStyle, Hsla, InteractiveElement, IntoElement, MouseButton,
-    ParentElement, Pixels, ScrollHandle, SharedString, Size, StatefulInteractiveElement, Styled,
-    Task, ViewContext, WeakView,
+    div, px, AnyElement, AppContext, CursorStyle, Hsla, InteractiveElement, IntoElement, Model,
+    MouseButton, ParentElement, Pixels, ScrollHandle, SharedString, Size,
+    StatefulInteractiveElement, Styled, Task, ViewContext, WeakView,
 };
-use language::{markdown, DiagnosticEntry, Language, LanguageRegistry, ParsedMarkdown};
+use language::{markdown, CharKind, DiagnosticEntry, Language, LanguageRegistry, ParsedMarkdown};
 
 use lsp::DiagnosticSeverity;
 use multi_buffer::ToOffset;
-use project::{HoverBlock, HoverBlockKind, InlayHintLabelPart};
+use project::{HoverBlock, HoverBlockKind, InlayHintLabelPart, Project};
 use settings::Settings;
 use smol::stream::StreamExt;
-use std::{ops::Range, sync::Arc, time::Duration};
+use std::{mem, ops::Range, sync::Arc, time::Duration};
 use ui::{prelude::*, Tooltip};
 use util::TryFutureExt;
 use workspace::Workspace;
@@ -147,6 +148,20 @@ pub fn hide_hover(editor: &mut Editor, cx: &mut ViewContext<Editor>) -> bool {
     editor.hover_state.info_task = None;
     editor.hover_state.triggered_from = None;
 
+    // Cancel the evaluation too, so the adapter doesn't keep evaluating a hover that
+    // isn't shown anymore.
+    if mem::take(&mut editor.hover_state.evaluating_debug_hover) {
+        if let Some(client) = editor
+            .project
+            .as_ref()
+            .and_then(|project| project.read(cx).dap_store().read(cx).active_client())
+        {
+            cx.background_executor()
+                .spawn(async move { client.cancel_hover().await })
+                .detach();
+        }
+    }
+
     editor.clear_background_highlights::<HoverState>(cx);
 
     if did_hide {
@@ -226,6 +241,9 @@ fn show_hover(
         }
     }
 
+    let debug_hover = debug_hover_expression(&project, &snapshot, anchor, cx);
+    editor.hover_state.evaluating_debug_hover = debug_hover.is_some();
+
     let task = cx.spawn(|this, mut cx| {
         async move {
             // If we need to delay, delay a set amount initially before making the lsp request
@@ -251,6 +269,14 @@ fn show_hover(
                 })
             })?;
 
+            // while the debuggee is stopped, also show the value of the hovered word
+            let debug_hover_request = debug_hover.map(|(client, range, expression)| {
+                cx.background_executor().spawn(async move {
+                    let value = client.evaluate_hover(expression.clone()).await;
+                    (range, expression, value.ok().flatten())
+                })
+            });
+
             if let Some(delay) = delay {
                 delay.await;
             }
@@ -328,7 +354,31 @@ fn show_hover(
                 info_popovers.push(info_popover);
             }
 
+            if let Some(debug_hover_request) = debug_hover_request {
+                if let (range, expression, Some(value)) = debug_hover_request.await {
+                    let text = match value.type_ {
+                        Some(type_) => format!("{expression}: {type_} = {}", value.value),
+                        None => format!("{expression} = {}", value.value),
+                    };
+                    let blocks = [HoverBlock {
+                        text,
+                        kind: HoverBlockKind::PlainText,
+                    }];
+                    let parsed_content = parse_blocks(&blocks, &language_registry, None).await;
+                    hover_highlights.push(range.clone());
+                    info_popovers.insert(
+                        0,
+                        InfoPopover {
+                            symbol_range: RangeInEditor::Text(range),
+                            parsed_content,
+                            scroll_handle: ScrollHandle::new(),
+                        },
+                    );
+                }
+            }
+
             this.update(&mut cx, |editor, cx| {
+                editor.hover_state.evaluating_debug_hover = false;
                 if hover_highlights.is_empty() {
                     editor.clear_background_highlights::<HoverState>(cx);
                 } else {
@@ -353,6 +403,29 @@ fn show_hover(
     editor.hover_state.info_task = Some(task);
 }
 
+/// The session, range and text of the word under the mouse, whose value is shown in
+/// the hover while the current thread of the active debug session is stopped.
+fn debug_hover_expression(
+    project: &Model<Project>,
+    snapshot: &EditorSnapshot,
+    anchor: Anchor,
+    cx: &AppContext,
+) -> Option<(Arc<DebugAdapterClient>, Range<Anchor>, String)> {
+    let client = project.read(cx).dap_store().read(cx).active_client()?;
+    let thread_id = client.current_thread_id()?;
+    if client.thread_state_by_id(thread_id).status != ThreadStatus::Stopped {
+        return None;
+    }
+
+    let buffer = &snapshot.buffer_snapshot;
+    let (range, kind) = buffer.surrounding_word(anchor);
+    if kind != Some(CharKind::Word) || range.is_empty() {
+        return None;
+    }
+    let expression = buffer.text_for_range(range.clone()).collect::<String>();
+    Some((client, range.to_anchors(buffer), expression))
+}
+
 async fn parse_blocks(
     blocks: &[HoverBlock],
     language_registry: &Arc<LanguageRegistry>,
@@ -431,6 +504,8 @@ pub struct HoverState {
     pub diagnostic_popover: Option<DiagnosticPopover>,
     pub triggered_from: Option<Anchor>,
     pub info_task: Option<Task<Option<()>>>,
+    /// Whether the value of the hovered word is being evaluated by the active debug session.
+    pub evaluating_debug_hover: bool,
 }
 
 impl HoverState {
@@ -633,6 +708,7 @@ mod tests {
         InlayId, PointForPosition,
     };
     use collections::BTreeSet;
+    use dap::{client::ThreadState, requests::Evaluate, EvaluateResponse};
     use gpui::{FontWeight, HighlightStyle, UnderlineStyle};
     use indoc::indoc;
     use language::{language_settings::InlayHintSettings, Diagnostic, DiagnosticSet};
@@ -953,6 +1029,81 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_debug_hover(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(Default::default(), cx).await;
+        cx.set_state(indoc! {"
+            fn test() { let count = 1;ˇ }
+        "});
+        let (client, adapter) =
+            DebugAdapterClient::with_capabilities(Default::default(), cx.executor());
+        let client = Arc::new(client);
+        client.thread_states().insert(
+            1,
+            ThreadState {
+                status: ThreadStatus::Running,
+                current_stack_frame_id: Some(10),
+                ..Default::default()
+            },
+        );
+        client.set_current_thread_id(Some(1));
+        cx.update_editor(|editor, cx| {
+            let project = editor.project.clone().unwrap();
+            project.update(cx, |project, cx| {
+                project
+                    .dap_store()
+                    .update(cx, |dap_store, cx| dap_store.add_client(client.clone(), cx))
+            });
+        });
+        let hover_point = cx.display_point(indoc! {"
+            fn test() { let couˇnt = 1; }
+        "});
+        let hover = |cx: &mut EditorLspTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                let anchor = snapshot
+                    .buffer_snapshot
+                    .anchor_before(hover_point.to_offset(&snapshot, Bias::Left));
+                hover_at(editor, Some(anchor), cx)
+            });
+            for _ in 0..2 {
+                cx.background_executor
+                    .advance_clock(Duration::from_millis(HOVER_DELAY_MILLIS + 100));
+            }
+        };
+
+        // Nothing is evaluated while the debuggee is running.
+        hover(&mut cx);
+        assert!(adapter.requests.try_recv().is_err());
+        cx.editor(|editor, _| assert!(!editor.hover_state.visible()));
+
+        cx.update_editor(|editor, cx| hide_hover(editor, cx));
+        client.update_thread_state_status(1, ThreadStatus::Stopped);
+        hover(&mut cx);
+        let arguments = adapter
+            .respond::<Evaluate>(EvaluateResponse {
+                result: "1".into(),
+                type_: Some("i32".into()),
+                presentation_hint: None,
+                variables_reference: 0,
+                named_variables: None,
+                indexed_variables: None,
+                memory_reference: None,
+            })
+            .await;
+        assert_eq!(arguments["expression"], "count");
+        assert_eq!(arguments["context"], "hover");
+        assert_eq!(arguments["frameId"], 10);
+        cx.run_until_parked();
+        cx.editor(|editor, _| {
+            assert!(!editor.hover_state.evaluating_debug_hover);
+            let rendered = &editor.hover_state.info_popovers[0].parsed_content;
+            assert_eq!(rendered.text, "count: i32 = 1");
+        });
+    }
+
     #[gpui::test]
     async fn test_empty_hovers_filtered(cx: &mut gpui::TestAppContext) {
         init_test(cx, |_| {});
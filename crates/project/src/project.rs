@@ -20,6 +20,7 @@ use client::{
 };
 use clock::ReplicaId;
 use collections::{hash_map, BTreeMap, HashMap, HashSet, VecDeque};
+use dap::dap_store::DapStore;
 use debounced_delay::DebouncedDelay;
 use futures::{
     channel::{
@@ -225,6 +226,7 @@ pub struct Project {
     prettiers_per_worktree: HashMap<WorktreeId, HashSet<Option<PathBuf>>>,
     prettier_instances: HashMap<PathBuf, PrettierInstance>,
     tasks: Model<Inventory>,
+    dap_store: Model<DapStore>,
     hosted_project_id: Option<ProjectId>,
     dev_server_project_id: Option<client::DevServerProjectId>,
     search_history: SearchHistory,
@@ -727,6 +729,7 @@ impl Project {
             cx.spawn(move |this, cx| Self::send_buffer_ordered_messages(this, rx, cx))
                 .detach();
             let tasks = Inventory::new(cx);
+            let dap_store = cx.new_model(|_| DapStore::default());
 
             Self {
                 worktrees: Vec::new(),
@@ -784,6 +787,7 @@ impl Project {
                 prettiers_per_worktree: HashMap::default(),
                 prettier_instances: HashMap::default(),
                 tasks,
+                dap_store,
                 hosted_project_id: None,
                 dev_server_project_id: None,
                 search_history: Self::new_search_history(),
@@ -852,6 +856,7 @@ impl Project {
         let this = cx.new_model(|cx| {
             let replica_id = response.payload.replica_id as ReplicaId;
             let tasks = Inventory::new(cx);
+            let dap_store = cx.new_model(|_| DapStore::default());
             // BIG CAUTION NOTE: The order in which we initialize fields here matters and it should match what's done in Self::local.
             // Otherwise, you might run into issues where worktree id on remote is different than what's on local host.
             // That's because Worktree's identifier is entity id, which should probably be changed.
@@ -946,6 +951,7 @@ impl Project {
                 prettiers_per_worktree: HashMap::default(),
                 prettier_instances: HashMap::default(),
                 tasks,
+                dap_store,
                 hosted_project_id: None,
                 dev_server_project_id: response
                     .payload
@@ -1350,6 +1356,10 @@ impl Project {
         &self.tasks
     }
 
+    pub fn dap_store(&self) -> &Model<DapStore> {
+        &self.dap_store
+    }
+
     pub fn search_history(&self) -> &SearchHistory {
         &self.search_history
     }
@@ -2743,6 +2753,7 @@ impl Project {
                 let buffer = buffer.read(cx);
                 let file = File::from_dyn(buffer.file())?;
                 let abs_path = file.as_local()?.abs_path(cx);
+                self.dap_store.read(cx).mark_source_modified(&abs_path);
                 let uri = lsp::Url::from_file_path(abs_path).unwrap();
                 let next_snapshot = buffer.text_snapshot();
 
@@ -1,4 +1,10 @@
 use crate::{Event, *};
+use dap::{
+    breakpoint_store::BreakpointStore,
+    client::{DebugAdapterClient, DebugAdapterClientId, BREAKPOINT_UPDATE_DEBOUNCE},
+    requests::SetBreakpoints,
+    SetBreakpointsResponse,
+};
 use fs::FakeFs;
 use futures::{future, StreamExt};
 use gpui::{AppContext, UpdateGlobal};
@@ -5127,6 +5133,58 @@ async fn search(
         .collect())
 }
 
+#[gpui::test]
+async fn test_edits_mark_breakpoint_sources_modified(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "main.rs": "fn main() {\n    let x = 1;\n}\n",
+        }),
+    )
+    .await;
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/main.rs", cx)
+        })
+        .await
+        .unwrap();
+
+    let (client, adapter) =
+        DebugAdapterClient::new_fake(DebugAdapterClientId(0), Default::default(), cx.executor());
+    let client = Arc::new(client);
+    client.configure(&BreakpointStore::default()).await.unwrap();
+    let dap_store = project.read_with(cx, |project, _| project.dap_store().clone());
+    dap_store.update(cx, |dap_store, cx| dap_store.add_client(client, cx));
+
+    let main_rs: Arc<Path> = Path::new("/dir/main.rs").into();
+    let toggle_breakpoint = |row, cx: &mut gpui::TestAppContext| {
+        dap_store.update(cx, |dap_store, cx| {
+            dap_store.toggle_breakpoint(main_rs.clone(), row, cx)
+        });
+        cx.executor().advance_clock(BREAKPOINT_UPDATE_DEBOUNCE);
+        adapter.respond::<SetBreakpoints>(SetBreakpointsResponse {
+            breakpoints: Vec::new(),
+        })
+    };
+
+    let arguments = toggle_breakpoint(1, cx).await;
+    assert_eq!(arguments["sourceModified"], serde_json::Value::Null);
+
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "// entry point\n")], None, cx)
+    });
+    let arguments = toggle_breakpoint(2, cx).await;
+    assert_eq!(arguments["sourceModified"], true);
+
+    // the adapter resolved the lines of the edited file again
+    let arguments = toggle_breakpoint(2, cx).await;
+    assert_eq!(arguments["sourceModified"], serde_json::Value::Null);
+}
+
 fn init_test(cx: &mut gpui::TestAppContext) {
     if std::env::var("RUST_LOG").is_ok() {
         env_logger::try_init().ok();
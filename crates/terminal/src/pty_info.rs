@@ -96,6 +96,11 @@ impl PtyProcessInfo {
         }
     }
 
+    /// The id of the foreground process of the terminal, e.g. the command it was spawned with.
+    pub fn pid(&self) -> Option<Pid> {
+        self.pid_getter.pid()
+    }
+
     fn refresh(&mut self) -> Option<&Process> {
         let pid = self.pid_getter.pid()?;
         if self
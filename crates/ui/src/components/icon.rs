@@ -114,6 +114,7 @@ pub enum IconName {
     Copy,
     CountdownTimer,
     Dash,
+    Debug,
     Delete,
     Disconnected,
     Ellipsis,
@@ -155,6 +156,7 @@ pub enum IconName {
     Option,
     PageDown,
     PageUp,
+    Pause,
     Pencil,
     Person,
     Play,
@@ -241,6 +243,7 @@ impl IconName {
             IconName::Copy => "icons/copy.svg",
             IconName::CountdownTimer => "icons/countdown_timer.svg",
             IconName::Dash => "icons/dash.svg",
+            IconName::Debug => "icons/debug.svg",
             IconName::Delete => "icons/delete.svg",
             IconName::Disconnected => "icons/disconnected.svg",
             IconName::Ellipsis => "icons/ellipsis.svg",
@@ -282,6 +285,7 @@ impl IconName {
             IconName::Option => "icons/option.svg",
             IconName::PageDown => "icons/page_down.svg",
             IconName::PageUp => "icons/page_up.svg",
+            IconName::Pause => "icons/pause.svg",
             IconName::Pencil => "icons/pencil.svg",
             IconName::Person => "icons/person.svg",
             IconName::Play => "icons/play.svg",
@@ -147,6 +147,7 @@ fn init_ui(app_state: Arc<AppState>, cx: &mut AppContext) -> Result<()> {
     project_symbols::init(cx);
     project_panel::init(Assets, cx);
     tasks_ui::init(cx);
+    debugger_ui::init(cx);
     channel::init(&app_state.client.clone(), app_state.user_store.clone(), cx);
     search::init(cx);
     vim::init(cx);
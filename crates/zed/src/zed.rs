@@ -199,6 +199,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 workspace_handle.clone(),
                 cx.clone(),
             );
+            let debug_panel = debugger_ui::DebugPanel::load(workspace_handle.clone(), cx.clone());
 
             let (
                 project_panel,
@@ -207,6 +208,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 channels_panel,
                 chat_panel,
                 notification_panel,
+                debug_panel,
             ) = futures::try_join!(
                 project_panel,
                 terminal_panel,
@@ -214,6 +216,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 channels_panel,
                 chat_panel,
                 notification_panel,
+                debug_panel,
             )?;
 
             workspace_handle.update(&mut cx, |workspace, cx| {
@@ -223,6 +226,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 workspace.add_panel(channels_panel, cx);
                 workspace.add_panel(chat_panel, cx);
                 workspace.add_panel(notification_panel, cx);
+                workspace.add_panel(debug_panel, cx);
                 cx.focus_self();
             })
         })